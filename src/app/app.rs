@@ -1,21 +1,40 @@
 use crate::app::cfg::cfg::Cfg;
+use crate::app::cfg::handle::CfgHandle;
 use crate::app::error::kernel::NotBootedKernelError;
+use crate::app::model::health::Health;
 use crate::app::model::state::{AppState, State};
 use crate::domain::factory::command::CommandFactory;
-use crate::domain::model::command::WifeMessageCmd;
-use crate::domain::model::event::ExecutableEvent;
+use crate::domain::model::command::{HeartbeatCmd, WifeMessageCmd};
+use crate::domain::model::lifecycle::Lifecycle;
+use crate::domain::service::audit::log::{AuditLog, AuditLogTrait};
+use crate::domain::service::audit::subscriber::AuditSubscriber;
+use crate::domain::service::event::bus::{EventBus, LifecycleBus};
 use crate::domain::service::event::r#loop::{CommandEventLoop, EventLoop};
 use crate::domain::service::executor::executor::{CommandExecutor, Executor};
+use crate::domain::service::job::registry::JobRegistry;
+use crate::domain::service::process::builder::ProcessBuilder;
+use crate::domain::service::process::tracker::ProcessTracker;
 use crate::domain::service::runner::runner::{AppRunner, Runner};
+use crate::domain::service::session::manager::SessionManager;
+use crate::domain::service::transport::transport::Transport;
 use crate::domain::service::wife::message::parser::CsvParser;
 use crate::domain::service::wife::message::service::{MessageService, MessageServiceTrait};
 use crate::infrastructure;
-use crate::infrastructure::broadcasting::mpsc::channel::{Chan, Channel};
-use crate::infrastructure::service::executor::responder::ExitCommandResponder;
-use crate::infrastructure::service::message::poller::LongPoller;
-use chrono::{Datelike, Local, NaiveDate, NaiveDateTime};
+use crate::infrastructure::helper::logger;
+use crate::infrastructure::metrics::metrics::Metrics;
+use crate::infrastructure::metrics::server::{MetricsServer, Server};
+use crate::infrastructure::metrics::subscriber::MetricsSubscriber;
+use crate::infrastructure::broadcasting::mpsc::channel::Chan;
+use crate::infrastructure::service::executor::responder::{CompositeResponder, ExitCommandResponder, WebhookResponder};
+use crate::infrastructure::integration::telegram::poller::LongPoller;
+use chrono::{Datelike, Local, NaiveDate};
 use infrastructure::integration::telegram;
+use infrastructure::integration::telegram::transport::TelegramTransport;
+use infrastructure::integration::webhook;
+use infrastructure::integration::webhook::service::{WebhookService, WebhookServiceTrait};
+use infrastructure::integration::webhook::subscriber::WebhookSubscriber;
 use infrastructure::service::message;
+use std::error::Error;
 use std::sync::{Arc, Mutex};
 use rust_embed::RustEmbed;
 
@@ -36,83 +55,138 @@ pub struct App {
     app_runner: Box<dyn Runner>,
 }
 impl App {
-    pub fn new() -> Self {
-        App::boot(Cfg::new().unwrap())
+    // new reads the config and boots the app, an invalid config is returned as an error
+    pub fn new() -> Result<Self, Box<dyn Error>> {
+        App::boot(Cfg::new()?)
     }
 
-    fn boot(cfg: Cfg) -> Self {
-        env_logger::init();
+    // telegram makes the telegram client of the config, the token and the timeout are read once
+    pub fn telegram(cfg: &Cfg, metrics: Arc<Metrics>) -> Arc<Box<dyn telegram::facade::TelegramFacadeTrait>> {
+        Arc::new(Box::new(telegram::facade::TelegramFacade::new(Box::new(
+            telegram::service::TelegramService::new(
+                Box::new(telegram::http::Client::new(cfg.token.clone(), cfg.poll_frequency)),
+                metrics,
+            ),
+        ))))
+    }
 
-        let token = cfg.token.clone();
-        let frequency = cfg.poll_frequency.clone();
-        let frequency_cloned = frequency.clone();
+    fn boot(cfg: Cfg) -> Result<Self, Box<dyn Error>> {
+        logger::init(cfg.log_format, cfg.log_level.as_str());
+        cfg.log();
 
-        let telegram_facade: Arc<Box<dyn telegram::facade::TelegramFacadeTrait>> =
-            Arc::new(Box::new(telegram::facade::TelegramFacade::new(Box::new(
-                telegram::service::TelegramService::new(Box::new(telegram::http::Client::new(
-                    token, frequency,
-                ))),
-            ))));
+        let metrics = Arc::new(Metrics::new());
+        let health = Arc::new(Health::new());
+        let telegram_facade = App::telegram(&cfg, metrics.clone());
+        // the chat is talked to by the transport, telegram is polled for the messages by the poller
+        let transport: Arc<Box<dyn Transport>> = Arc::new(Box::new(TelegramTransport::new(telegram_facade.clone())));
+        // the components share the handle, so a reloaded config applies to all of them
+        let cfg = CfgHandle::new(cfg);
 
+        let audit: Arc<Box<dyn AuditLogTrait>> = Arc::new(Box::new(AuditLog::new(cfg.clone())));
+        // the results are sent to the chat and posted to the webhooks if there are any
+        let webhook: Arc<Box<dyn WebhookServiceTrait>> =
+            Arc::new(Box::new(WebhookService::new(cfg.clone(), Box::new(webhook::http::Client::new()))));
         let executor: Arc<Box<dyn Executor>> = Arc::new(Box::new(CommandExecutor::new(Box::new(
-            ExitCommandResponder::new(cfg.clone(), telegram_facade.clone()),
+            CompositeResponder::new(vec![
+                Box::new(ExitCommandResponder::new(cfg.clone(), transport.clone())),
+                Box::new(WebhookResponder::new(webhook.clone())),
+            ]),
         ))));
 
-        let chan: Chan<Arc<Box<dyn ExecutableEvent>>> = Chan::new();
+        // the loop publishes the lifecycle of the events, the subscribers are keyed by topic
+        let chan: Chan<Arc<Lifecycle>> = Chan::with_capacity(cfg.get().bus_capacity);
+        let bus: Arc<Box<dyn EventBus>> = Arc::new(Box::new(LifecycleBus::new(Box::new(chan), cfg.get().bus_overflow, metrics.clone())));
+        bus.subscribe(Arc::new(Box::new(AuditSubscriber::new(audit.clone()))))?;
+        bus.subscribe(Arc::new(Box::new(MetricsSubscriber::new(metrics.clone()))))?;
+        bus.subscribe(Arc::new(Box::new(WebhookSubscriber::new(cfg.clone(), webhook))))?;
+        // the job registry is not a subscriber: a /bg command is finished once its job is spawned, so
+        // command.finished tells nothing of the job, the registry puts the end of the job into the loop
+        // itself (JobFinishedCmd) and the loop publishes it to the subscribers above
+
         let state: Arc<Box<dyn State>> = Arc::new(Box::new(AppState::new()));
-        let channel: Box<dyn Channel<Arc<Box<dyn ExecutableEvent>>>> = Box::new(chan);
         let event_loop: Arc<Box<dyn EventLoop>> = Arc::new(Box::new(CommandEventLoop::new(
             state.clone(),
-            channel,
+            bus.clone(),
             executor.clone(),
+            metrics.clone(),
+            health.clone(),
         )));
+        // it is not ready while HEARTBEAT_INTERVAL_SEC is zero
+        event_loop.add_event(Arc::new(Box::new(HeartbeatCmd::new(cfg.clone(), health.clone()))));
 
-        let now = Local::now().naive_local();
-        let date = NaiveDateTime::from(NaiveDate::from_ymd_opt(now.year(), now.month(), now.day()).unwrap().and_hms_opt(10, 0, 0).unwrap());
-        let message_service: Arc<Box<dyn MessageServiceTrait>> = Arc::new(Box::new(MessageService::new(
-            Arc::new(Box::new(CsvParser::new(cfg.clone())
-        ))).unwrap()));
-        event_loop.add_event(Arc::new(Box::new(WifeMessageCmd::new(
-            date.clone(), "С добрым утром моя радость, я безумно тебя люблю.".to_string(), message_service.clone(),
-        ))));
-        event_loop.add_event(Arc::new(Box::new(WifeMessageCmd::new(
-            date, "Спокойной ночи, моя любовь, сладких снов.".to_string(), message_service,
-        ))));
+        if cfg.get().is_wife_mode_enabled {
+            let now = Local::now().naive_local();
+            let date = NaiveDate::from_ymd_opt(now.year(), now.month(), now.day()).unwrap().and_hms_opt(10, 0, 0).unwrap();
+            let message_service: Arc<Box<dyn MessageServiceTrait>> = Arc::new(Box::new(MessageService::new(
+                Arc::new(Box::new(CsvParser::new(cfg.clone())
+            )))?));
+            event_loop.add_event(Arc::new(Box::new(WifeMessageCmd::new(
+                date, "С добрым утром моя радость, я безумно тебя люблю.".to_string(), message_service.clone(),
+            ))));
+            event_loop.add_event(Arc::new(Box::new(WifeMessageCmd::new(
+                date, "Спокойной ночи, моя любовь, сладких снов.".to_string(), message_service,
+            ))));
+        }
 
         let provider: Arc<Mutex<Box<dyn message::provider::Provider>>> =
             Arc::new(Mutex::new(Box::new(LongPoller::new(
                 cfg.clone(),
-                frequency_cloned,
                 state.clone(),
-                telegram_facade.clone(),
+                telegram_facade,
+                metrics.clone(),
+                health.clone(),
             ))));
 
         let events_mutex = Arc::new(Mutex::new(Vec::new()));
+        let process_tracker = Arc::new(ProcessTracker::new());
+        let process_builder = Arc::new(ProcessBuilder::new(cfg.clone(), process_tracker.clone()));
 
         let consumer: Arc<Mutex<Box<dyn message::consumer::Consumer>>> = Arc::new(Mutex::new(
             Box::new(message::consumer::MessageConsumer::new(
                 Box::new(CommandFactory::new(
+                    cfg.clone(),
                     events_mutex.clone(),
+                    Arc::new(Box::new(SessionManager::new(cfg.clone(), process_builder.clone(), event_loop.clone()))),
+                    process_builder,
+                    Arc::new(Box::new(JobRegistry::new(cfg.clone(), event_loop.clone(), process_tracker.clone()))),
+                    transport,
+                    state.clone(),
+                    audit,
                     event_loop.clone(),
                 )),
                 state.clone(),
                 event_loop.clone(),
+                health.clone(),
             )),
         ));
 
-        App {
+        let metrics_server: Arc<Box<dyn Server>> =
+            Arc::new(Box::new(MetricsServer::new(cfg.clone(), state.clone(), metrics, health.clone())));
+
+        Ok(App {
             is_init: true,
-            app_runner: Box::new(AppRunner::new(cfg, event_loop, provider, consumer)),
-        }
+            app_runner: Box::new(AppRunner::new(
+                cfg,
+                state,
+                process_tracker,
+                event_loop,
+                provider,
+                consumer,
+                metrics_server,
+                bus,
+                health,
+            )),
+        })
     }
 }
 
 impl Kernel for App {
     fn run(&self) -> Result<(), NotBootedKernelError> {
         if !self.is_init {
-            Err(NotBootedKernelError::new().into())
+            Err(NotBootedKernelError::new())
         } else {
-            Ok(self.app_runner.run())
+            self.app_runner.run();
+            Ok(())
         }
     }
 }
@@ -1,60 +1,650 @@
+use crate::app::error::cfg::InvalidCfgError;
+use crate::domain::r#enum::topic::Topic;
+use crate::domain::model::limits::Limits;
+use crate::infrastructure::broadcasting::mpsc::queue::Overflow;
+use crate::infrastructure::helper::logger::LogFormat;
+use serde_json::Value;
+use std::collections::HashMap;
 use std::env;
+use std::fmt::Display;
+use std::fs;
+use std::net::SocketAddr;
+use std::str::FromStr;
 use std::time::Duration;
 
+// The variables which hold the secrets of the daemon, they are never passed to the commands.
+pub const SECRET_VARS: &[&str] = &["TG_TOKEN", "TG_TOKEN_FILE", "WEBHOOK_SECRET", "WEBHOOK_SECRET_FILE", "WEBHOOK_URLS"];
+
 #[derive(Clone)]
 pub struct Cfg {
-    pub chat_id: u64,
-    pub wife_chat_id: u64,
+    pub chat_id: i64,
+    pub wife_chat_id: i64,
     pub token: String,
     pub poll_frequency: Duration,
     pub wife_filepath: String,
     pub is_wife_mode_enabled: bool,
     pub event_loop_channel_capacity: usize,
+    // how many lifecycle events are queued per subscriber of the bus
+    pub bus_capacity: usize,
+    // what the bus does when the queue of a subscriber is full: block, drop-oldest or drop-newest,
+    // the audit subscriber always blocks
+    pub bus_overflow: Overflow,
+    // shell (with its arguments) which receives a script as the last argument in shell mode
+    pub exec_shell: Vec<String>,
+    // when enabled, the /cmd is executed through the exec_shell as well as /sh
+    pub is_shell_mode_enabled: bool,
+    // shell (with its arguments) which is kept running by /session start
+    pub session_shell: Vec<String>,
+    // a session without commands for this long is closed (zero disables the timeout)
+    pub session_idle_timeout: Duration,
+    // a command in a session is interrupted when it is running for this long
+    pub session_command_timeout: Duration,
+    // default working directory of executed commands (the daemon one if None)
+    pub exec_working_dir: Option<String>,
+    // names of the daemon variables inherited by executed commands (all of them if None),
+    // the SECRET_VARS are not inherited in any case
+    pub exec_env_allowlist: Option<Vec<String>>,
+    // default user (name or uid) which executed commands are run as (the daemon one if None)
+    pub exec_run_as_user: Option<String>,
+    // default group (name or gid) which executed commands are run as (the user one if None)
+    pub exec_run_as_group: Option<String>,
+    // roles of telegram users by their ids, the users without a role get the defaults
+    pub user_roles: HashMap<i64, String>,
+    // default resource limits of executed commands, nproc is counted per user by the kernel,
+    // so it bounds the commands run as a dedicated user but not the ones run as root
+    pub exec_limits: Limits,
+    // resource limits which override the default ones for the users of a role
+    pub exec_role_limits: HashMap<String, Limits>,
+    // maximum size of a message body or a replied document which is passed to stdin
+    pub exec_max_stdin_bytes: u64,
+    // how long a command may run before it is killed, zero means no timeout
+    pub exec_timeout: Duration,
+    // how many of the last output bytes of a background job are kept
+    pub job_output_buffer_bytes: usize,
+    // telegram users who may run the administrative commands (e.g. /shutdown),
+    // the owner of the chat if it is empty
+    pub admin_user_ids: Vec<i64>,
+    // how long the in-flight commands may run after the shutdown has been requested
+    pub shutdown_timeout: Duration,
+    // format of the log lines: text or json
+    pub log_format: LogFormat,
+    // levels of the log targets like "info" or "info,poller=debug" (RUST_LOG takes precedence)
+    pub log_level: String,
+    // append-only file of the executed commands (not kept if None)
+    pub audit_filepath: Option<String>,
+    // the audit file is rotated when it grows over the size (zero disables the rotation)
+    pub audit_max_file_bytes: u64,
+    // how many rotated audit files are kept
+    pub audit_max_files: usize,
+    // whether each audit entry holds the hash of the previous one, so a change breaks the chain
+    pub is_audit_hash_chained: bool,
+    // local address of the metrics endpoint like 127.0.0.1:9090 (not served if None)
+    pub metrics_addr: Option<SocketAddr>,
+    // endpoints which the command results are POSTed to as JSON (none if it is empty)
+    pub webhook_urls: Vec<String>,
+    // key of the HMAC-SHA256 signature of the webhook bodies (not signed if None)
+    pub webhook_secret: Option<String>,
+    // lifecycle events which are POSTed to the webhooks along with the results
+    pub webhook_topics: Vec<Topic>,
+    // how many times a webhook request is sent before it is given up
+    pub webhook_max_attempts: u32,
+    pub webhook_timeout: Duration,
+    // how often the admin chat is told that the app is alive (zero disables the heartbeat)
+    pub heartbeat_interval: Duration,
+    // JSON file which the config is read of along with the environment (CONFIG_FILE_PATH)
+    pub config_filepath: Option<String>,
+    // file which keeps the polling offset between restarts (not kept if None)
+    pub offset_filepath: Option<String>,
+    // name of the installed service, the systemd unit and the log files are named by it
+    pub service_name: String,
+    // label of the installed launchd job
+    pub service_label: String,
+    // user which the installed service is run as (root if None)
+    pub service_user: Option<String>,
+    // file which keeps the environment of the installed systemd service
+    pub service_env_filepath: String,
+    // directory of the service logs, the journal (systemd) or /var/log (launchd) if None
+    pub service_log_dir: Option<String>,
+    // whether the systemd sandboxing options are applied, the executed commands inherit them
+    pub is_service_hardened: bool,
 }
 impl Cfg {
-    pub fn new() -> Result<Self, env::VarError> {
+    // new reads the config of the environment variables and of the config file (CONFIG_FILE_PATH)
+    // if it is set, the environment variables take precedence over the file values. All the
+    // invalid values are reported at once instead of the first one.
+    pub fn new() -> Result<Self, InvalidCfgError> {
+        let config_filepath = env::var("CONFIG_FILE_PATH").ok().filter(|s| !s.is_empty());
+        let mut vars = Vars::new(config_filepath.as_deref())?;
+
         let s = Self {
-            chat_id: env::var("TG_CHAT_ID")
-                .unwrap_or("".to_string())
-                .parse::<u64>()
-                .unwrap(),
-            wife_chat_id: env::var("TG_WIFE_CHAT_ID")
-                .unwrap_or("".to_string())
-                .parse::<u64>()
-                .unwrap(),
-            token: env::var("TG_TOKEN")
-                .unwrap_or("".to_string()),
-            poll_frequency: Duration::from_secs(
-                env::var("TG_POLL_FREQUENCY_SEC")
-                    .unwrap_or("5".to_string())
-                    .parse()
-                    .unwrap(),
+            chat_id: vars.required("TG_CHAT_ID"),
+            wife_chat_id: vars.parse("TG_WIFE_CHAT_ID", 0),
+            token: vars.required_secret("TG_TOKEN"),
+            poll_frequency: vars.secs("TG_POLL_FREQUENCY_SEC", 5),
+            wife_filepath: vars.string("WIFE_FILE_PATH", "beloved_wife.csv"),
+            is_wife_mode_enabled: vars.bool("IS_WIFE_MODE_ENABLED", false),
+            event_loop_channel_capacity: vars.parse("EVENT_LOOP_CHANNEL_CAPACITY", 100),
+            bus_capacity: vars.parse("BUS_CAPACITY", 1000),
+            bus_overflow: vars.parse("BUS_OVERFLOW", Overflow::Block),
+            exec_shell: vars.argv("EXEC_SHELL", "/bin/sh -c"),
+            is_shell_mode_enabled: vars.bool("IS_SHELL_MODE_ENABLED", false),
+            session_shell: vars.argv("SESSION_SHELL", "/bin/sh"),
+            session_idle_timeout: vars.secs("SESSION_IDLE_TIMEOUT_SEC", 900),
+            session_command_timeout: vars.secs("SESSION_COMMAND_TIMEOUT_SEC", 60),
+            exec_working_dir: vars.optional("EXEC_WORKING_DIR"),
+            exec_env_allowlist: vars.optional("EXEC_ENV_ALLOWLIST").map(|s| split_list(s.as_str(), ',')),
+            exec_run_as_user: vars.optional("EXEC_RUN_AS_USER"),
+            exec_run_as_group: vars.optional("EXEC_RUN_AS_GROUP"),
+            // format: "<user id>:<role>,<user id>:<role>"
+            user_roles: vars.map("USER_ROLES", HashMap::new(), |s| {
+                split_list(s, ',')
+                    .into_iter()
+                    .map(|pair| {
+                        let (id, role) = pair
+                            .split_once(':')
+                            .ok_or(format!("`{}` is not a <user id>:<role> pair", pair))?;
+                        let id = id.trim().parse::<i64>().map_err(|e| format!("`{}` is not a user id: {}", id, e))?;
+                        Ok((id, role.trim().to_string()))
+                    })
+                    .collect()
+            }),
+            // "cpu=600,output=1M" by default
+            exec_limits: vars.map(
+                "EXEC_LIMITS",
+                Limits { cpu_seconds: Some(600), output_bytes: Some(1024 * 1024), ..Limits::default() },
+                Limits::parse,
             ),
-            wife_filepath: env::var("WIFE_FILE_PATH")
-                .unwrap_or("beloved_wife.csv".to_string()),
-            is_wife_mode_enabled: env::var("IS_WIFE_MODE_ENABLED").unwrap_or("false".to_string())
-                == "true",
-            event_loop_channel_capacity: env::var("EVENT_LOOP_CHANNEL_CAPACITY")
-                .unwrap_or("100".to_string())
-                .parse()
-                .unwrap(),
+            // format: "<role>:<limits>;<role>:<limits>", see Limits::parse
+            exec_role_limits: vars.map("EXEC_ROLE_LIMITS", HashMap::new(), |s| {
+                split_list(s, ';')
+                    .into_iter()
+                    .map(|pair| {
+                        let (role, limits) = pair
+                            .split_once(':')
+                            .ok_or(format!("`{}` is not a <role>:<limits> pair", pair))?;
+                        Ok((role.trim().to_string(), Limits::parse(limits)?))
+                    })
+                    .collect()
+            }),
+            exec_max_stdin_bytes: vars.parse("EXEC_MAX_STDIN_BYTES", 1048576),
+            exec_timeout: vars.secs("EXEC_TIMEOUT_SEC", 0),
+            job_output_buffer_bytes: vars.parse("JOB_OUTPUT_BUFFER_BYTES", 65536),
+            admin_user_ids: vars.map("ADMIN_USER_IDS", vec![], |s| {
+                split_list(s, ',')
+                    .into_iter()
+                    .map(|id| id.parse::<i64>().map_err(|e| format!("`{}` is not a user id: {}", id, e)))
+                    .collect()
+            }),
+            shutdown_timeout: vars.secs("SHUTDOWN_TIMEOUT_SEC", 10),
+            log_format: vars.parse("LOG_FORMAT", LogFormat::Text),
+            log_level: vars.string("LOG_LEVEL", "info"),
+            audit_filepath: Some(vars.string("AUDIT_FILE_PATH", "relp.audit.log")).filter(|s| !s.is_empty()),
+            audit_max_file_bytes: vars.parse("AUDIT_MAX_FILE_BYTES", 10485760),
+            audit_max_files: vars.parse("AUDIT_MAX_FILES", 5),
+            is_audit_hash_chained: vars.bool("IS_AUDIT_HASH_CHAINED", false),
+            metrics_addr: vars.map("METRICS_ADDR", None, |s| match s {
+                "" => Ok(None),
+                s => s.parse::<SocketAddr>().map(Some).map_err(|e| format!("`{}`: {}", s, e)),
+            }),
+            webhook_urls: vars.map("WEBHOOK_URLS", vec![], |s| {
+                split_list(s, ',')
+                    .into_iter()
+                    .map(|url| match url.starts_with("http://") || url.starts_with("https://") {
+                        true => Ok(url),
+                        false => Err(format!("`{}` is not an http(s) url", url)),
+                    })
+                    .collect()
+            }),
+            webhook_secret: vars.secret("WEBHOOK_SECRET"),
+            webhook_topics: vars.map("WEBHOOK_TOPICS", vec![], |s| {
+                split_list(s, ',').iter().map(|topic| topic.parse::<Topic>()).collect()
+            }),
+            webhook_max_attempts: vars.map("WEBHOOK_MAX_ATTEMPTS", 3, |s| match s.parse::<u32>() {
+                Ok(n) if n > 0 => Ok(n),
+                _ => Err(format!("`{}` is not a positive number", s)),
+            }),
+            webhook_timeout: vars.secs("WEBHOOK_TIMEOUT_SEC", 10),
+            heartbeat_interval: vars.secs("HEARTBEAT_INTERVAL_SEC", 0),
+            config_filepath,
+            offset_filepath: Some(vars.string("OFFSET_FILE_PATH", "relp.offset")).filter(|s| !s.is_empty()),
+            service_name: vars.map("SERVICE_NAME", "repl".to_string(), |s| {
+                if s.is_empty() || !s.chars().all(|c| c.is_ascii_alphanumeric() || "-_.".contains(c)) {
+                    return Err(format!("`{}` may contain just letters, digits, -, _ and .", s));
+                }
+                Ok(s.to_string())
+            }),
+            service_label: vars.string("SERVICE_LABEL", "com.jared1.repl"),
+            service_user: vars.optional("SERVICE_USER"),
+            service_env_filepath: vars.string("SERVICE_ENV_FILE_PATH", "/etc/repl/repl.env"),
+            service_log_dir: vars.optional("SERVICE_LOG_DIR"),
+            is_service_hardened: vars.bool("IS_SERVICE_HARDENED", true),
         };
 
-        println!("Using environment variable TG_TOKEN={}", s.token);
-        println!("Using environment variable TG_CHAT_ID={}", s.chat_id);
-        println!(
-            "Using environment variable TG_POLL_FREQUENCY_SEC={:?}",
-            s.poll_frequency
-        );
-        println!(
-            "Using environment variable IS_WIFE_MODE_ENABLED={}",
-            s.is_wife_mode_enabled
+        if s.is_wife_mode_enabled && s.wife_chat_id == 0 {
+            vars.error("TG_WIFE_CHAT_ID is required when IS_WIFE_MODE_ENABLED is true.".to_string());
+        }
+        if s.is_shell_mode_enabled && s.exec_shell.is_empty() {
+            vars.error("EXEC_SHELL must not be empty when IS_SHELL_MODE_ENABLED is true.".to_string());
+        }
+        if s.session_shell.is_empty() {
+            vars.error("SESSION_SHELL must not be empty.".to_string());
+        }
+        vars.finish()?;
+
+        Ok(s)
+    }
+}
+
+impl Cfg {
+    // is_admin tells whether the user may run the administrative commands, these are the
+    // configured admins or the owner of the chat (its id is the user one in a private chat)
+    pub fn is_admin(&self, user_id: i64) -> bool {
+        if self.admin_user_ids.is_empty() {
+            return user_id == self.chat_id;
+        }
+        self.admin_user_ids.contains(&user_id)
+    }
+    // limits returns the default limits overridden by the ones of the user role (if any)
+    pub fn limits(&self, user_id: i64) -> Limits {
+        let role_limits = self.user_roles.get(&user_id).and_then(|role| self.exec_role_limits.get(role));
+        match role_limits {
+            Some(role_limits) => self.exec_limits.merge(role_limits),
+            None => self.exec_limits,
+        }
+    }
+    // vars returns the config as the variables it has been read of, the secrets are redacted
+    pub fn vars(&self) -> Vec<(&'static str, String)> {
+        vec![
+            ("TG_TOKEN", redact(self.token.as_str())),
+            ("TG_CHAT_ID", self.chat_id.to_string()),
+            ("TG_WIFE_CHAT_ID", self.wife_chat_id.to_string()),
+            ("TG_POLL_FREQUENCY_SEC", format!("{:?}", self.poll_frequency)),
+            ("WIFE_FILE_PATH", self.wife_filepath.clone()),
+            ("IS_WIFE_MODE_ENABLED", self.is_wife_mode_enabled.to_string()),
+            ("EVENT_LOOP_CHANNEL_CAPACITY", self.event_loop_channel_capacity.to_string()),
+            ("BUS_CAPACITY", self.bus_capacity.to_string()),
+            ("BUS_OVERFLOW", self.bus_overflow.to_string()),
+            ("EXEC_SHELL", format!("{:?}", self.exec_shell)),
+            ("IS_SHELL_MODE_ENABLED", self.is_shell_mode_enabled.to_string()),
+            ("SESSION_SHELL", format!("{:?}", self.session_shell)),
+            ("SESSION_IDLE_TIMEOUT_SEC", format!("{:?}", self.session_idle_timeout)),
+            ("SESSION_COMMAND_TIMEOUT_SEC", format!("{:?}", self.session_command_timeout)),
+            ("EXEC_WORKING_DIR", format!("{:?}", self.exec_working_dir)),
+            ("EXEC_ENV_ALLOWLIST", format!("{:?}", self.exec_env_allowlist)),
+            ("EXEC_RUN_AS_USER", format!("{:?}", self.exec_run_as_user)),
+            ("EXEC_RUN_AS_GROUP", format!("{:?}", self.exec_run_as_group)),
+            ("USER_ROLES", format!("{:?}", self.user_roles)),
+            ("EXEC_LIMITS", format!("{:?}", self.exec_limits)),
+            ("EXEC_ROLE_LIMITS", format!("{:?}", self.exec_role_limits)),
+            ("EXEC_MAX_STDIN_BYTES", self.exec_max_stdin_bytes.to_string()),
+            ("EXEC_TIMEOUT_SEC", format!("{:?}", self.exec_timeout)),
+            ("JOB_OUTPUT_BUFFER_BYTES", self.job_output_buffer_bytes.to_string()),
+            ("ADMIN_USER_IDS", format!("{:?}", self.admin_user_ids)),
+            ("SHUTDOWN_TIMEOUT_SEC", format!("{:?}", self.shutdown_timeout)),
+            ("LOG_FORMAT", self.log_format.to_string()),
+            ("LOG_LEVEL", self.log_level.clone()),
+            ("AUDIT_FILE_PATH", format!("{:?}", self.audit_filepath)),
+            ("AUDIT_MAX_FILE_BYTES", self.audit_max_file_bytes.to_string()),
+            ("AUDIT_MAX_FILES", self.audit_max_files.to_string()),
+            ("IS_AUDIT_HASH_CHAINED", self.is_audit_hash_chained.to_string()),
+            ("METRICS_ADDR", format!("{:?}", self.metrics_addr)),
+            ("WEBHOOK_URLS", format!("{:?}", self.webhook_urls.iter().map(|url| redact_url(url)).collect::<Vec<String>>())),
+            ("WEBHOOK_SECRET", redact(self.webhook_secret.as_deref().unwrap_or_default())),
+            ("WEBHOOK_TOPICS", format!("{:?}", self.webhook_topics.iter().map(|topic| topic.to_string()).collect::<Vec<String>>())),
+            ("WEBHOOK_MAX_ATTEMPTS", self.webhook_max_attempts.to_string()),
+            ("WEBHOOK_TIMEOUT_SEC", format!("{:?}", self.webhook_timeout)),
+            ("HEARTBEAT_INTERVAL_SEC", format!("{:?}", self.heartbeat_interval)),
+            ("CONFIG_FILE_PATH", format!("{:?}", self.config_filepath)),
+            ("OFFSET_FILE_PATH", format!("{:?}", self.offset_filepath)),
+            ("SERVICE_NAME", self.service_name.clone()),
+            ("SERVICE_LABEL", self.service_label.clone()),
+            ("SERVICE_USER", format!("{:?}", self.service_user)),
+            ("SERVICE_ENV_FILE_PATH", self.service_env_filepath.clone()),
+            ("SERVICE_LOG_DIR", format!("{:?}", self.service_log_dir)),
+            ("IS_SERVICE_HARDENED", self.is_service_hardened.to_string()),
+        ]
+    }
+
+    // log shows the config which is in use, the secrets are redacted
+    pub fn log(&self) {
+        for (name, value) in self.vars() {
+            log::info!(target: "cfg", "Using environment variable {}={}", name, value);
+        }
+    }
+}
+
+// Hides a secret value but tells whether it is set at all.
+fn redact(secret: &str) -> String {
+    if secret.is_empty() {
+        "".to_string()
+    } else {
+        "<redacted>".to_string()
+    }
+}
+
+// Hides the path of a url, the webhooks keep their tokens in it.
+fn redact_url(url: &str) -> String {
+    let (scheme, rest) = url.split_once("://").unwrap_or(("", url));
+    match rest.split_once('/') {
+        Some((host, path)) if !path.is_empty() => format!("{}://{}/<redacted>", scheme, host),
+        _ => url.to_string(),
+    }
+}
+
+// Vars are the config values of the environment with a fallback to the config file, the file is
+// a flat JSON object of the same names: {"TG_POLL_FREQUENCY_SEC": 5, "ADMIN_USER_IDS": [1, 2]}.
+// The readers below return a default on an invalid value and keep the error for finish().
+struct Vars {
+    file: HashMap<String, String>,
+    errors: Vec<String>,
+}
+impl Vars {
+    fn new(path: Option<&str>) -> Result<Vars, InvalidCfgError> {
+        let Some(path) = path else {
+            return Ok(Vars { file: HashMap::new(), errors: vec![] });
+        };
+
+        let data = fs::read_to_string(path)
+            .map_err(|e| InvalidCfgError::new(vec![format!("Failed to read the config file {}: {}.", path, e)]))?;
+        let object: serde_json::Map<String, Value> = serde_json::from_str(data.as_str())
+            .map_err(|e| InvalidCfgError::new(vec![format!("Failed to parse the config file {}: {}.", path, e)]))?;
+
+        let file = object
+            .into_iter()
+            .filter_map(|(name, value)| Self::to_string(value).map(|value| (name, value)))
+            .collect();
+
+        Ok(Vars { file, errors: vec![] })
+    }
+
+    fn var(&self, name: &str) -> Option<String> {
+        match env::var(name) {
+            Ok(value) => Some(value),
+            Err(env::VarError::NotPresent) => self.file.get(name).cloned(),
+            Err(env::VarError::NotUnicode(_)) => None,
+        }
+    }
+
+    fn error(&mut self, error: String) {
+        self.errors.push(error);
+    }
+
+    // finish fails with all the errors which have been met while the values were read
+    fn finish(&mut self) -> Result<(), InvalidCfgError> {
+        if self.errors.is_empty() {
+            return Ok(());
+        }
+        Err(InvalidCfgError::new(std::mem::take(&mut self.errors)))
+    }
+
+    fn string(&mut self, name: &str, default: &str) -> String {
+        self.var(name).unwrap_or(default.to_string())
+    }
+
+    // returns None if the variable is not set or is empty
+    fn optional(&mut self, name: &str) -> Option<String> {
+        self.var(name).filter(|s| !s.is_empty())
+    }
+
+    fn map<T>(&mut self, name: &str, default: T, parse: impl FnOnce(&str) -> Result<T, String>) -> T {
+        let Some(value) = self.var(name) else {
+            return default;
+        };
+
+        match parse(value.trim()) {
+            Ok(value) => value,
+            Err(e) => {
+                self.error(format!("{} is invalid: {}.", name, e.trim_end_matches('.')));
+                default
+            }
+        }
+    }
+
+    fn parse<T: FromStr>(&mut self, name: &str, default: T) -> T
+    where
+        T::Err: Display,
+    {
+        self.map(name, default, |s| s.parse::<T>().map_err(|e| format!("`{}`: {}", s, e)))
+    }
+
+    fn required<T: FromStr + Default>(&mut self, name: &str) -> T
+    where
+        T::Err: Display,
+    {
+        if self.optional(name).is_none() {
+            self.error(format!("{} is required.", name));
+            return T::default();
+        }
+        self.parse(name, T::default())
+    }
+
+    fn bool(&mut self, name: &str, default: bool) -> bool {
+        self.map(name, default, |s| match s {
+            "true" => Ok(true),
+            "false" => Ok(false),
+            other => Err(format!("`{}` is neither true nor false", other)),
+        })
+    }
+
+    fn secs(&mut self, name: &str, default: u64) -> Duration {
+        Duration::from_secs(self.parse(name, default))
+    }
+
+    // a program with its arguments which are split as the shell does
+    fn argv(&mut self, name: &str, default: &str) -> Vec<String> {
+        let default_argv = shlex::split(default).unwrap_or_default();
+        self.map(name, default_argv, |s| shlex::split(s).ok_or(format!("`{}` has unbalanced quotes", s)))
+    }
+
+    // a secret is read of the variable itself or of the file which <NAME>_FILE points to,
+    // so it does not have to be kept in the environment or in the config file
+    fn required_secret(&mut self, name: &str) -> String {
+        self.secret(name).unwrap_or_else(|| {
+            self.error(format!("{} (or {}_FILE) is required.", name, name));
+            "".to_string()
+        })
+    }
+
+    // returns None if neither the secret nor its file is set
+    fn secret(&mut self, name: &str) -> Option<String> {
+        let file_name = format!("{}_FILE", name);
+        let value = self.optional(name);
+        let path = self.optional(file_name.as_str());
+
+        match (value, path) {
+            (Some(_), Some(_)) => {
+                self.error(format!("Only one of {} and {} may be set.", name, file_name));
+                Some("".to_string())
+            }
+            (Some(value), None) => Some(value),
+            (None, Some(path)) => match fs::read_to_string(path.as_str()) {
+                Ok(value) if !value.trim().is_empty() => Some(value.trim().to_string()),
+                Ok(_) => {
+                    self.error(format!("{} points to an empty file {}.", file_name, path));
+                    Some("".to_string())
+                }
+                Err(e) => {
+                    self.error(format!("{} points to an unreadable file {}: {}.", file_name, path, e));
+                    Some("".to_string())
+                }
+            },
+            (None, None) => None,
+        }
+    }
+
+    // the values are read as the env ones, so the lists are joined by commas
+    fn to_string(value: Value) -> Option<String> {
+        match value {
+            Value::Null => None,
+            Value::String(value) => Some(value),
+            Value::Array(values) => Some(values.into_iter().filter_map(Self::to_string).collect::<Vec<String>>().join(",")),
+            value => Some(value.to_string()),
+        }
+    }
+}
+
+// Splits a list by the separator, the blank items are skipped.
+fn split_list(s: &str, separator: char) -> Vec<String> {
+    s.split(separator)
+        .map(|item| item.trim().to_string())
+        .filter(|item| !item.is_empty())
+        .collect()
+}
+
+// testing sets up the config of the environment for the tests of the modules which depend on it
+#[cfg(test)]
+pub mod testing {
+    use super::*;
+    use std::sync::Mutex;
+
+    // the environment is shared by the tests, so it is changed by one of them at a time
+    static ENV: Mutex<()> = Mutex::new(());
+
+    pub fn with_env<T>(vars: &[(&str, &str)], f: impl FnOnce() -> T) -> T {
+        let _guard = ENV.lock().unwrap_or_else(|e| e.into_inner());
+        for (name, value) in vars {
+            env::set_var(name, value);
+        }
+        let result = f();
+        for (name, _) in vars {
+            env::remove_var(name);
+        }
+        result
+    }
+
+    // with_cfg runs f with the environment of the required variables and the given ones
+    pub fn with_cfg<T>(vars: &[(&str, &str)], f: impl FnOnce(Result<Cfg, InvalidCfgError>) -> T) -> T {
+        let required = [("TG_CHAT_ID", "1"), ("TG_TOKEN", "token")];
+        with_env(&[&required[..], vars].concat(), || f(Cfg::new()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::testing::{with_cfg, with_env};
+    use super::*;
+
+    fn cfg(vars: &[(&str, &str)]) -> Result<Cfg, InvalidCfgError> {
+        with_cfg(vars, |cfg| cfg)
+    }
+
+    // TempFile is a file of a test, it is removed once it is dropped
+    struct TempFile(String);
+    impl TempFile {
+        fn new(name: &str, content: &str) -> TempFile {
+            let path = env::temp_dir().join(format!("repl-{}-{}", std::process::id(), name));
+            fs::write(&path, content).unwrap();
+            TempFile(path.to_string_lossy().to_string())
+        }
+    }
+    impl Drop for TempFile {
+        fn drop(&mut self) {
+            let _ = fs::remove_file(&self.0);
+        }
+    }
+
+    #[test]
+    fn the_limits_of_a_role_override_the_defaults() {
+        let cfg = cfg(&[
+            ("USER_ROLES", "10:guest, 20:ops"),
+            ("EXEC_LIMITS", "cpu=60,output=1M"),
+            ("EXEC_ROLE_LIMITS", "guest:cpu=10,nproc=32; ops:cpu=0"),
+        ])
+            .unwrap();
+
+        let guest = cfg.limits(10);
+        assert_eq!((guest.cpu_seconds, guest.processes, guest.output_bytes), (Some(10), Some(32), Some(1024 * 1024)));
+        assert_eq!(cfg.limits(20).cpu_seconds, Some(0));
+        assert_eq!(cfg.limits(30), cfg.exec_limits);
+    }
+
+    #[test]
+    fn the_users_get_the_default_limits_if_none_are_configured() {
+        let cfg = cfg(&[("USER_ROLES", "10:guest")]).unwrap();
+
+        assert_eq!(cfg.limits(10), Limits { cpu_seconds: Some(600), output_bytes: Some(1024 * 1024), ..Limits::default() });
+    }
+
+    #[test]
+    fn invalid_limits_are_reported() {
+        let error = cfg(&[("EXEC_LIMITS", "mem=1M"), ("EXEC_ROLE_LIMITS", "guest"), ("USER_ROLES", "x:guest")])
+            .err()
+            .unwrap();
+
+        assert_eq!(error.errors().len(), 3);
+        assert!(error.errors()[0].starts_with("USER_ROLES is invalid: `x` is not a user id"));
+        assert!(error.errors()[1].starts_with("EXEC_LIMITS is invalid: Unknown limit `mem`"));
+        assert_eq!(error.errors()[2], "EXEC_ROLE_LIMITS is invalid: `guest` is not a <role>:<limits> pair.");
+    }
+
+    #[test]
+    fn all_the_invalid_values_are_reported_at_once() {
+        let error = with_env(&[("TG_POLL_FREQUENCY_SEC", "often"), ("IS_WIFE_MODE_ENABLED", "yes")], Cfg::new)
+            .err()
+            .unwrap();
+
+        assert_eq!(
+            error.errors(),
+            [
+                "TG_CHAT_ID is required.",
+                "TG_TOKEN (or TG_TOKEN_FILE) is required.",
+                "TG_POLL_FREQUENCY_SEC is invalid: `often`: invalid digit found in string.",
+                "IS_WIFE_MODE_ENABLED is invalid: `yes` is neither true nor false.",
+            ]
         );
-        println!(
-            "Using environment variable EVENT_LOOP_CHANNEL_CAPACITY={}",
-            s.event_loop_channel_capacity
+    }
+
+    #[test]
+    fn a_secret_is_read_of_its_file() {
+        let token = TempFile::new("token", "secret-token\n");
+
+        let cfg = with_env(&[("TG_CHAT_ID", "1"), ("TG_TOKEN_FILE", token.0.as_str())], Cfg::new).unwrap();
+
+        assert_eq!(cfg.token, "secret-token");
+    }
+
+    #[test]
+    fn a_secret_may_not_be_set_along_with_its_file_or_point_to_an_empty_one() {
+        let token = TempFile::new("empty-token", " \n");
+
+        let error = cfg(&[("TG_TOKEN_FILE", token.0.as_str())]).err().unwrap();
+        assert_eq!(error.errors(), ["Only one of TG_TOKEN and TG_TOKEN_FILE may be set."]);
+
+        let error = with_env(&[("TG_CHAT_ID", "1"), ("TG_TOKEN_FILE", token.0.as_str())], Cfg::new).err().unwrap();
+        assert_eq!(error.errors(), [format!("TG_TOKEN_FILE points to an empty file {}.", token.0)]);
+    }
+
+    #[test]
+    fn the_config_file_is_a_fallback_of_the_environment() {
+        let file = TempFile::new(
+            "config.json",
+            r#"{"TG_CHAT_ID": 5, "TG_POLL_FREQUENCY_SEC": 7, "ADMIN_USER_IDS": [1, 2], "EXEC_WORKING_DIR": null}"#,
         );
 
-        Ok(s)
+        let cfg = cfg(&[("CONFIG_FILE_PATH", file.0.as_str())]).unwrap();
+
+        assert_eq!(cfg.chat_id, 1);
+        assert_eq!(cfg.poll_frequency, Duration::from_secs(7));
+        assert_eq!(cfg.admin_user_ids, vec![1, 2]);
+        assert_eq!(cfg.exec_working_dir, None);
+        assert_eq!(cfg.config_filepath, Some(file.0.clone()));
+    }
+
+    #[test]
+    fn an_unreadable_config_file_is_reported() {
+        let file = TempFile::new("broken.json", "{");
+
+        let error = cfg(&[("CONFIG_FILE_PATH", file.0.as_str())]).err().unwrap();
+
+        assert!(error.errors()[0].starts_with(format!("Failed to parse the config file {}", file.0).as_str()));
+    }
+
+    #[test]
+    fn the_secrets_and_the_url_paths_are_redacted() {
+        assert_eq!(redact("token"), "<redacted>");
+        assert_eq!(redact(""), "");
+        assert_eq!(redact_url("https://hooks.example.com/services/T0/B0/XXX"), "https://hooks.example.com/<redacted>");
+        assert_eq!(redact_url("https://example.com/"), "https://example.com/");
+        assert_eq!(redact_url("http://localhost:8080"), "http://localhost:8080");
     }
 }
@@ -0,0 +1,46 @@
+use crate::app::cfg::cfg::Cfg;
+use crate::app::error::cfg::InvalidCfgError;
+use std::sync::{Arc, RwLock};
+
+// CfgHandle shares the current config between the components, so a reloaded one applies to
+// all of them at once. The components take a snapshot by get() each time they use the config.
+#[derive(Clone)]
+pub struct CfgHandle {
+    cfg: Arc<RwLock<Arc<Cfg>>>,
+}
+
+impl CfgHandle {
+    pub fn new(cfg: Cfg) -> CfgHandle {
+        CfgHandle { cfg: Arc::new(RwLock::new(Arc::new(cfg))) }
+    }
+
+    // returns the current config
+    pub fn get(&self) -> Arc<Cfg> {
+        self.cfg.read().unwrap().clone()
+    }
+
+    // reload reads the config once again, the current one is kept if the new one is broken
+    pub fn reload(&self) -> Result<(), InvalidCfgError> {
+        let cfg = Cfg::new()?;
+        cfg.log();
+
+        let current = self.get();
+        // these are read once on boot
+        if cfg.token != current.token
+            || cfg.event_loop_channel_capacity != current.event_loop_channel_capacity
+            || cfg.bus_capacity != current.bus_capacity
+            || cfg.bus_overflow != current.bus_overflow
+            || cfg.metrics_addr != current.metrics_addr
+        {
+            log::warn!(
+                target: "cfg",
+                "TG_TOKEN, EVENT_LOOP_CHANNEL_CAPACITY, BUS_CAPACITY, BUS_OVERFLOW and METRICS_ADDR changes take effect after a restart."
+            );
+        }
+
+        *self.cfg.write().unwrap() = Arc::new(cfg);
+        log::info!(target: "cfg", "Config has been reloaded.");
+
+        Ok(())
+    }
+}
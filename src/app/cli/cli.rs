@@ -0,0 +1,307 @@
+use crate::app::app::{App, Kernel};
+use crate::app::cfg::cfg::Cfg;
+use crate::app::error::cli::UsageError;
+use crate::app::service::definition::ServiceDefinition;
+use crate::infrastructure::metrics::metrics::Metrics;
+use std::collections::BTreeMap;
+use std::env;
+use std::error::Error;
+use std::fs::{self, OpenOptions};
+use std::path::Path;
+use std::sync::Arc;
+use std::io::Write;
+use std::os::unix::fs::OpenOptionsExt;
+
+pub const USAGE: &str = "Usage: REPL [command]
+
+Commands:
+    run                       runs the daemon (the default one)
+    check-config              validates the config and prints it with the secrets redacted
+    send <chat id> <text>     sends a message to the chat
+    get-updates [offset]      prints the pending telegram updates without confirming them
+    whoami                    prints the bot identity and the chats it has seen
+    install-service [systemd|launchd] [--output <path>]
+                              writes a service definition (and the environment file of
+                              systemd) of the current config, `--output -` prints them instead
+    help                      prints this message";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Platform {
+    Systemd,
+    Launchd,
+}
+impl Platform {
+    // the service manager of the current OS
+    fn current() -> Platform {
+        if cfg!(target_os = "macos") {
+            Platform::Launchd
+        } else {
+            Platform::Systemd
+        }
+    }
+
+}
+
+// Cli is a command of the binary which is given by its arguments.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Cli {
+    Run,
+    CheckConfig,
+    Send { chat_id: i64, text: String },
+    GetUpdates { offset: i64 },
+    Whoami,
+    InstallService { platform: Platform, output: Option<String> },
+    Help,
+}
+
+impl Cli {
+    // parse reads the arguments without the binary name, no arguments mean run
+    pub fn parse(args: Vec<String>) -> Result<Cli, UsageError> {
+        let Some((command, args)) = args.split_first() else {
+            return Ok(Cli::Run);
+        };
+
+        let cli = match command.as_str() {
+            "run" => Cli::Run,
+            "check-config" => Cli::CheckConfig,
+            "send" => {
+                let Some((chat_id, text)) = args.split_first() else {
+                    return Err(UsageError::new("The chat id and the text are required.".to_string()));
+                };
+                // the ids of groups and channels are negative
+                let chat_id = chat_id
+                    .parse::<i64>()
+                    .map_err(|e| UsageError::new(format!("Invalid chat id `{}`: {}.", chat_id, e)))?;
+                if text.is_empty() {
+                    return Err(UsageError::new("The text is required.".to_string()));
+                }
+                return Ok(Cli::Send { chat_id, text: text.join(" ") });
+            }
+            "get-updates" => match args.first() {
+                Some(offset) => Cli::GetUpdates {
+                    offset: offset
+                        .parse::<i64>()
+                        .map_err(|e| UsageError::new(format!("Invalid offset `{}`: {}.", offset, e)))?,
+                },
+                None => Cli::GetUpdates { offset: 0 },
+            },
+            "whoami" => Cli::Whoami,
+            "install-service" => return Self::parse_install_service(args),
+            "help" | "--help" | "-h" => Cli::Help,
+            other => return Err(UsageError::new(format!("Unknown command `{}`.", other))),
+        };
+
+        if let Some(arg) = args.get(Self::arity(&cli)) {
+            return Err(UsageError::new(format!("Unexpected argument `{}`.", arg)));
+        }
+
+        Ok(cli)
+    }
+
+    // number of the positional arguments which the command takes
+    fn arity(cli: &Cli) -> usize {
+        match cli {
+            Cli::GetUpdates { .. } => 1,
+            _ => 0,
+        }
+    }
+
+    fn parse_install_service(args: &[String]) -> Result<Cli, UsageError> {
+        let mut platform = None;
+        let mut output = None;
+
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "systemd" if platform.is_none() => platform = Some(Platform::Systemd),
+                "launchd" if platform.is_none() => platform = Some(Platform::Launchd),
+                "--output" | "-o" => match args.next() {
+                    Some(path) => output = Some(path.clone()),
+                    None => return Err(UsageError::new("The output path is required after --output.".to_string())),
+                },
+                other => return Err(UsageError::new(format!("Unexpected argument `{}`.", other))),
+            }
+        }
+
+        Ok(Cli::InstallService { platform: platform.unwrap_or(Platform::current()), output })
+    }
+
+    // run executes the command, the daemon is run until it is shut down
+    pub fn run(self) -> Result<(), Box<dyn Error>> {
+        match self {
+            Cli::Run => {
+                App::new()?.run()?;
+            }
+            Cli::CheckConfig => {
+                for (name, value) in Cfg::new()?.vars() {
+                    println!("{}={}", name, value);
+                }
+                println!("The config is valid.");
+            }
+            Cli::Send { chat_id, text } => {
+                let response = App::telegram(&Cfg::new()?, Arc::new(Metrics::new())).send_message(chat_id, text.as_str())?;
+                if !response.ok {
+                    return Err("Telegram has refused to send the message.".into());
+                }
+                println!("Message {} has been sent.", response.result.message_id);
+            }
+            Cli::GetUpdates { offset } => {
+                let response = App::telegram(&Cfg::new()?, Arc::new(Metrics::new())).get_updates(offset)?;
+                println!("{}", serde_json::to_string_pretty(&response)?);
+            }
+            Cli::Whoami => {
+                let telegram = App::telegram(&Cfg::new()?, Arc::new(Metrics::new()));
+
+                let me = telegram.get_me()?.result;
+                println!("Bot: @{} ({}), id {}.", me.username, me.first_name, me.id);
+
+                // the chats of the pending updates, the confirmed ones are not returned by telegram
+                let mut chats = BTreeMap::new();
+                for update in telegram.get_updates(0)?.result {
+                    if let Some(message) = update.message.or(update.edited_message) {
+                        chats.insert(message.chat.id, message.chat);
+                    }
+                }
+                if chats.is_empty() {
+                    println!("No chats have been seen, send a message to the bot and try again.");
+                }
+                for chat in chats.values() {
+                    println!("Chat: @{} ({}), {}, id {}.", chat.username, chat.first_name, chat.r#type, chat.id);
+                }
+            }
+            Cli::InstallService { platform, output } => {
+                let cfg = Cfg::new()?;
+                let exe = env::current_exe()?;
+                let working_dir = env::current_dir()?;
+                let definition =
+                    ServiceDefinition::new(&cfg, exe.to_string_lossy().as_ref(), working_dir.to_string_lossy().as_ref());
+
+                // systemd keeps the environment in a separate file, launchd embeds it
+                let (path, text, env_file) = match platform {
+                    Platform::Systemd => (
+                        definition.systemd_path(),
+                        definition.systemd_unit(),
+                        Some((definition.env_filepath.clone(), definition.environment_file())),
+                    ),
+                    Platform::Launchd => (definition.launchd_path(), definition.launchd_plist(), None),
+                };
+                let path = output.unwrap_or(path);
+
+                if path == "-" {
+                    print!("{}", text);
+                    if let Some((env_path, env_text)) = env_file {
+                        print!("\n# {}\n{}", env_path, env_text);
+                    }
+                    return Ok(());
+                }
+
+                write_private(path.as_str(), text.as_str())?;
+                println!("The service definition has been written into {}.", path);
+                if let Some((env_path, env_text)) = env_file {
+                    write_private(env_path.as_str(), env_text.as_str())?;
+                    println!("The service environment has been written into {}.", env_path);
+                }
+
+                println!("Start the service by:");
+                match platform {
+                    Platform::Systemd => {
+                        println!("    systemctl daemon-reload && systemctl enable --now {}", definition.name)
+                    }
+                    Platform::Launchd => println!("    launchctl load -w {}", path),
+                }
+            }
+            Cli::Help => println!("{}", USAGE),
+        }
+
+        Ok(())
+    }
+}
+
+// write_private writes the file which is readable by the owner only, since it may hold the token
+fn write_private(path: &str, text: &str) -> Result<(), Box<dyn Error>> {
+    if let Some(dir) = Path::new(path).parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        fs::create_dir_all(dir).map_err(|e| format!("Failed to create {}: {}.", dir.display(), e))?;
+    }
+
+    OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(path)
+        .and_then(|mut file| file.write_all(text.as_bytes()))
+        .map_err(|e| format!("Failed to write {}: {}.", path, e))?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Cli, UsageError> {
+        Cli::parse(args.iter().map(|arg| arg.to_string()).collect())
+    }
+
+    fn error(args: &[&str]) -> String {
+        parse(args).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn no_arguments_mean_run() {
+        assert_eq!(parse(&[]).unwrap(), Cli::Run);
+        assert_eq!(parse(&["run"]).unwrap(), Cli::Run);
+    }
+
+    #[test]
+    fn send_takes_the_chat_id_and_joins_the_text() {
+        assert_eq!(parse(&["send", "42", "hello", "world"]).unwrap(), Cli::Send { chat_id: 42, text: "hello world".to_string() });
+        // a group chat
+        assert_eq!(parse(&["send", "-1001234", "hi"]).unwrap(), Cli::Send { chat_id: -1001234, text: "hi".to_string() });
+    }
+
+    #[test]
+    fn send_without_the_text_or_with_a_broken_chat_id_is_rejected() {
+        assert!(error(&["send"]).contains("The chat id and the text are required"));
+        assert!(error(&["send", "42"]).contains("The text is required"));
+        assert!(error(&["send", "chat", "hi"]).contains("Invalid chat id `chat`"));
+    }
+
+    #[test]
+    fn extra_arguments_are_rejected() {
+        assert_eq!(parse(&["get-updates", "7"]).unwrap(), Cli::GetUpdates { offset: 7 });
+        assert!(error(&["get-updates", "7", "8"]).contains("Unexpected argument `8`"));
+        assert!(error(&["get-updates", "next"]).contains("Invalid offset `next`"));
+        assert!(error(&["whoami", "me"]).contains("Unexpected argument `me`"));
+        assert!(error(&["check-config", "-v"]).contains("Unexpected argument `-v`"));
+        assert!(error(&["deploy"]).contains("Unknown command `deploy`"));
+    }
+
+    #[test]
+    fn install_service_takes_a_platform_and_an_output() {
+        assert_eq!(
+            parse(&["install-service", "launchd", "--output", "-"]).unwrap(),
+            Cli::InstallService { platform: Platform::Launchd, output: Some("-".to_string()) }
+        );
+        assert_eq!(
+            parse(&["install-service", "-o", "repl.service", "systemd"]).unwrap(),
+            Cli::InstallService { platform: Platform::Systemd, output: Some("repl.service".to_string()) }
+        );
+        assert_eq!(parse(&["install-service"]).unwrap(), Cli::InstallService { platform: Platform::current(), output: None });
+    }
+
+    #[test]
+    fn install_service_rejects_an_output_without_a_path_and_a_second_platform() {
+        assert!(error(&["install-service", "--output"]).contains("The output path is required"));
+        assert!(error(&["install-service", "systemd", "launchd"]).contains("Unexpected argument `launchd`"));
+        assert!(error(&["install-service", "systemd", "systemd"]).contains("Unexpected argument `systemd`"));
+        assert!(error(&["install-service", "upstart"]).contains("Unexpected argument `upstart`"));
+    }
+
+    #[test]
+    fn help_is_given_by_any_of_its_names() {
+        for name in ["help", "--help", "-h"] {
+            assert_eq!(parse(&[name]).unwrap(), Cli::Help);
+        }
+    }
+}
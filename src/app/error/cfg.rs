@@ -0,0 +1,30 @@
+use std::error::Error;
+use std::fmt;
+
+// InvalidCfgError reports all the problems of the config at once, so they may be fixed in one go.
+#[derive(Debug)]
+pub struct InvalidCfgError {
+    errors: Vec<String>,
+}
+
+impl InvalidCfgError {
+    pub fn new(errors: Vec<String>) -> InvalidCfgError {
+        InvalidCfgError { errors }
+    }
+
+    pub fn errors(&self) -> &[String] {
+        self.errors.as_slice()
+    }
+}
+
+impl fmt::Display for InvalidCfgError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "The config is invalid:")?;
+        for error in &self.errors {
+            write!(f, "\n  - {}", error)?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for InvalidCfgError {}
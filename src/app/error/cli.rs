@@ -0,0 +1,21 @@
+use std::error::Error;
+use std::fmt;
+
+#[derive(Debug)]
+pub struct UsageError {
+    reason: String,
+}
+
+impl UsageError {
+    pub fn new(reason: String) -> UsageError {
+        UsageError { reason }
+    }
+}
+
+impl fmt::Display for UsageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}\n\n{}", self.reason, crate::app::cli::cli::USAGE)
+    }
+}
+
+impl Error for UsageError {}
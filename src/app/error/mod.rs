@@ -1 +1,3 @@
+pub mod cfg;
+pub mod cli;
 pub mod kernel;
@@ -1,4 +1,7 @@
+#[allow(clippy::module_inception)]
 pub mod app;
 pub mod cfg;
+pub mod cli;
 pub mod error;
 pub mod model;
+pub mod service;
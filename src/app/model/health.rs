@@ -0,0 +1,78 @@
+use chrono::{DateTime, Local};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+// The supervised components of the app.
+pub const POLLER: &str = "poller";
+pub const CONSUMER: &str = "consumer";
+pub const LOOP: &str = "loop";
+
+#[derive(Serialize, Debug, Clone)]
+pub struct ComponentHealth {
+    // whether the thread of the component is running
+    pub is_alive: bool,
+    // the last time the component has made a round of its work
+    pub last_activity: Option<DateTime<Local>>,
+    // how many times the component has been restarted after a panic
+    pub restarts: u64,
+}
+
+// Health keeps the liveness of the components, the supervisor sets whether they are running and
+// the components beat on each round of their work.
+pub struct Health {
+    started_at: DateTime<Local>,
+    components: Mutex<BTreeMap<&'static str, ComponentHealth>>,
+}
+
+impl Health {
+    pub fn new() -> Health {
+        Health { started_at: Local::now(), components: Mutex::new(BTreeMap::new()) }
+    }
+
+    fn update(&self, name: &'static str, f: impl FnOnce(&mut ComponentHealth)) {
+        let mut components = self.components.lock().unwrap();
+        f(components.entry(name).or_insert(ComponentHealth { is_alive: false, last_activity: None, restarts: 0 }));
+    }
+
+    // the component has been (re)started
+    pub fn start(&self, name: &'static str) {
+        self.update(name, |component| component.is_alive = true);
+    }
+
+    // the component has panicked, it is started again after a backoff
+    pub fn fail(&self, name: &'static str) {
+        self.update(name, |component| {
+            component.is_alive = false;
+            component.restarts += 1;
+        });
+    }
+
+    // the component has returned by itself (e.g. on shutdown)
+    pub fn stop(&self, name: &'static str) {
+        self.update(name, |component| component.is_alive = false);
+    }
+
+    pub fn beat(&self, name: &'static str) {
+        self.update(name, |component| component.last_activity = Some(Local::now()));
+    }
+
+    pub fn started_at(&self) -> DateTime<Local> {
+        self.started_at
+    }
+
+    pub fn components(&self) -> BTreeMap<&'static str, ComponentHealth> {
+        self.components.lock().unwrap().clone()
+    }
+
+    // the app is healthy while all of its components are running
+    pub fn is_healthy(&self) -> bool {
+        self.components.lock().unwrap().values().all(|component| component.is_alive)
+    }
+}
+
+impl Default for Health {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -15,13 +15,17 @@ impl AppState {
         }
     }
 }
+
+impl Default for AppState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 impl State for AppState {
+    // close may be called many times (a signal and /shutdown at once), the first call wins
     fn close(&self) {
-        for _ in 0..1000 {
-            match self.c.compare_exchange(false, true, Ordering::SeqCst, Ordering::Relaxed) {
-                Ok(_) => return,
-                Err(_) => panic!("app::model::state: unable to close application (CAS challenge has been failed)"),
-            }
+        if self.c.compare_exchange(false, true, Ordering::SeqCst, Ordering::Relaxed).is_ok() {
+            log::info!(target: "state", "Application is closing...");
         }
     }
     fn is_closed(&self) -> bool {
@@ -0,0 +1,314 @@
+use crate::app::cfg::cfg::Cfg;
+use crate::domain::service::runner::runner::KILL_GRACE;
+use std::env;
+use std::time::Duration;
+
+// How long the service manager waits before the service is started again after a failure.
+const RESTART_DELAY: Duration = Duration::from_secs(5);
+
+// ServiceDefinition is the service made of the config, both the systemd unit and the launchd
+// plist are rendered of it, so the two platforms run the service the same way.
+pub struct ServiceDefinition {
+    pub name: String,
+    pub label: String,
+    pub description: String,
+    // the binary with its arguments
+    pub program: Vec<String>,
+    pub user: Option<String>,
+    // the relative paths of the config (e.g. OFFSET_FILE_PATH) are resolved against it
+    pub working_dir: String,
+    // the config variables of the service, they are kept in the environment file by systemd
+    pub environment: Vec<(String, String)>,
+    pub env_filepath: String,
+    // the stdout and stderr files, None means the journal (systemd)
+    pub logs: Option<(String, String)>,
+    // the service is restarted unless it has exited successfully (e.g. by /shutdown)
+    pub restart_delay: Duration,
+    // how long the service may shut down before it is killed
+    pub stop_timeout: Duration,
+    pub is_hardened: bool,
+}
+
+impl ServiceDefinition {
+    pub fn new(cfg: &Cfg, exe: &str, working_dir: &str) -> ServiceDefinition {
+        ServiceDefinition {
+            name: cfg.service_name.clone(),
+            label: cfg.service_label.clone(),
+            description: "REPL - remote executor helper".to_string(),
+            program: vec![exe.to_string(), "run".to_string()],
+            user: cfg.service_user.clone(),
+            working_dir: working_dir.to_string(),
+            environment: Self::environment(cfg),
+            env_filepath: cfg.service_env_filepath.clone(),
+            logs: cfg.service_log_dir.as_ref().map(|dir| {
+                (
+                    format!("{}/{}.log", dir.trim_end_matches('/'), cfg.service_name),
+                    format!("{}/{}.err.log", dir.trim_end_matches('/'), cfg.service_name),
+                )
+            }),
+            restart_delay: RESTART_DELAY,
+            // the in-flight commands are waited for and then the remaining ones are terminated
+            stop_timeout: cfg.shutdown_timeout + KILL_GRACE + Duration::from_secs(1),
+            is_hardened: cfg.is_service_hardened,
+        }
+    }
+
+    // environment returns the config variables which are set in the current environment, so the
+    // service is run with the same config as the current process.
+    fn environment(cfg: &Cfg) -> Vec<(String, String)> {
+        cfg.vars()
+            .into_iter()
+            .map(|(name, _)| name)
+            .chain(["TG_TOKEN_FILE"])
+            .filter_map(|name| env::var(name).ok().map(|value| (name.to_string(), value)))
+            .collect()
+    }
+
+    pub fn systemd_path(&self) -> String {
+        format!("/etc/systemd/system/{}.service", self.name)
+    }
+
+    pub fn launchd_path(&self) -> String {
+        format!("/Library/LaunchDaemons/{}.plist", self.label)
+    }
+
+    // Returns the systemd unit, its environment is read of the environment file.
+    pub fn systemd_unit(&self) -> String {
+        let mut service = vec![
+            "Type=simple".to_string(),
+            format!(
+                "ExecStart={}",
+                self.program.iter().map(|arg| quote_systemd(arg)).collect::<Vec<String>>().join(" "),
+            ),
+            format!("WorkingDirectory={}", self.working_dir),
+            format!("EnvironmentFile={}", self.env_filepath),
+            "Restart=on-failure".to_string(),
+            format!("RestartSec={}", self.restart_delay.as_secs()),
+            "KillSignal=SIGTERM".to_string(),
+            format!("TimeoutStopSec={}", self.stop_timeout.as_secs()),
+        ];
+        if let Some(user) = &self.user {
+            service.push(format!("User={}", user));
+        }
+        if let Some((stdout, stderr)) = &self.logs {
+            service.push(format!("StandardOutput=append:{}", stdout));
+            service.push(format!("StandardError=append:{}", stderr));
+        }
+        if self.is_hardened {
+            // the executed commands are the children of the service, so the options are kept
+            // to the ones which do not take the usual tools away from them
+            service.extend(
+                [
+                    "NoNewPrivileges=yes",
+                    "PrivateTmp=yes",
+                    "ProtectSystem=full",
+                    "ProtectKernelTunables=yes",
+                    "ProtectKernelModules=yes",
+                    "ProtectControlGroups=yes",
+                    "RestrictSUIDSGID=yes",
+                    "RestrictRealtime=yes",
+                    "LockPersonality=yes",
+                    "UMask=0077",
+                ]
+                    .map(str::to_string),
+            );
+        }
+
+        format!(
+            "[Unit]
+Description={}
+After=network-online.target
+Wants=network-online.target
+
+[Service]
+{}
+
+[Install]
+WantedBy=multi-user.target
+",
+            self.description,
+            service.join("\n"),
+        )
+    }
+
+    // Returns the environment file of the systemd unit, it may hold the token.
+    pub fn environment_file(&self) -> String {
+        self.environment
+            .iter()
+            .map(|(name, value)| format!("{}=\"{}\"\n", name, value.replace('\\', "\\\\").replace('"', "\\\"")))
+            .collect()
+    }
+
+    // Returns the launchd plist, its environment is embedded since launchd has no environment file.
+    pub fn launchd_plist(&self) -> String {
+        let program = self
+            .program
+            .iter()
+            .map(|arg| format!("        <string>{}</string>\n", escape_xml(arg)))
+            .collect::<String>();
+        let environment = self
+            .environment
+            .iter()
+            .map(|(name, value)| {
+                format!(
+                    "        <key>{}</key>\n        <string>{}</string>\n",
+                    escape_xml(name),
+                    escape_xml(value),
+                )
+            })
+            .collect::<String>();
+        let (stdout, stderr) = self.logs.clone().unwrap_or((
+            format!("/var/log/{}.log", self.name),
+            format!("/var/log/{}.err.log", self.name),
+        ));
+        let user = match &self.user {
+            Some(user) => format!("    <key>UserName</key>\n    <string>{}</string>\n", escape_xml(user)),
+            None => "".to_string(),
+        };
+
+        format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+    <key>Label</key>
+    <string>{}</string>
+    <key>ProgramArguments</key>
+    <array>
+{}    </array>
+    <key>WorkingDirectory</key>
+    <string>{}</string>
+{}    <key>RunAtLoad</key>
+    <true/>
+    <key>KeepAlive</key>
+    <dict>
+        <key>SuccessfulExit</key>
+        <false/>
+    </dict>
+    <key>ThrottleInterval</key>
+    <integer>{}</integer>
+    <key>ExitTimeOut</key>
+    <integer>{}</integer>
+    <key>StandardOutPath</key>
+    <string>{}</string>
+    <key>StandardErrorPath</key>
+    <string>{}</string>
+    <key>EnvironmentVariables</key>
+    <dict>
+{}    </dict>
+</dict>
+</plist>
+"#,
+            escape_xml(self.label.as_str()),
+            program,
+            escape_xml(self.working_dir.as_str()),
+            user,
+            self.restart_delay.as_secs(),
+            self.stop_timeout.as_secs(),
+            escape_xml(stdout.as_str()),
+            escape_xml(stderr.as_str()),
+            environment,
+        )
+    }
+}
+
+fn escape_xml(s: &str) -> String {
+    s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+// an argument of ExecStart is double quoted if needed, % starts a specifier there
+fn quote_systemd(s: &str) -> String {
+    let s = s.replace('%', "%%");
+    if s.is_empty() || s.contains(|c: char| c.is_whitespace() || c == '"' || c == '\\' || c == '\'') {
+        return format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""));
+    }
+    s
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::cfg::cfg::testing::with_cfg;
+
+    fn definition(vars: &[(&str, &str)]) -> ServiceDefinition {
+        with_cfg(vars, |cfg| ServiceDefinition::new(&cfg.unwrap(), "/usr/local/bin/REPL", "/var/lib/repl"))
+    }
+
+    #[test]
+    fn the_systemd_unit_runs_the_service_of_the_environment_file() {
+        let unit = definition(&[("SERVICE_USER", "repl"), ("SERVICE_ENV_FILE_PATH", "/etc/repl/env")]).systemd_unit();
+
+        assert!(unit.contains("ExecStart=/usr/local/bin/REPL run\n"));
+        assert!(unit.contains("WorkingDirectory=/var/lib/repl\n"));
+        assert!(unit.contains("EnvironmentFile=/etc/repl/env\n"));
+        assert!(unit.contains("Restart=on-failure\nRestartSec=5\n"));
+        assert!(unit.contains("TimeoutStopSec=13\n"));
+        assert!(unit.contains("User=repl\n"));
+        assert!(!unit.contains("StandardOutput="));
+    }
+
+    #[test]
+    fn the_systemd_unit_is_hardened_unless_it_is_turned_off() {
+        let hardened = definition(&[]).systemd_unit();
+        assert!(hardened.contains("NoNewPrivileges=yes\n"));
+        assert!(hardened.contains("ProtectSystem=full\n"));
+        assert!(hardened.contains("UMask=0077\n"));
+        assert!(!hardened.contains("User="));
+
+        let plain = definition(&[("IS_SERVICE_HARDENED", "false")]).systemd_unit();
+        assert!(!plain.contains("NoNewPrivileges"));
+        assert!(!plain.contains("ProtectSystem"));
+    }
+
+    #[test]
+    fn the_systemd_unit_appends_to_the_log_files() {
+        let unit = definition(&[("SERVICE_LOG_DIR", "/var/log/repl/")]).systemd_unit();
+
+        assert!(unit.contains("StandardOutput=append:/var/log/repl/repl.log\n"));
+        assert!(unit.contains("StandardError=append:/var/log/repl/repl.err.log\n"));
+    }
+
+    #[test]
+    fn the_launchd_plist_embeds_the_environment() {
+        let definition = definition(&[
+            ("SERVICE_LABEL", "com.example.repl"),
+            ("SERVICE_USER", "repl"),
+            ("SERVICE_LOG_DIR", "/var/log/repl"),
+            ("EXEC_WORKING_DIR", "/tmp/a&b"),
+        ]);
+        let plist = definition.launchd_plist();
+
+        assert_eq!(definition.launchd_path(), "/Library/LaunchDaemons/com.example.repl.plist");
+        assert!(plist.contains("<key>Label</key>\n    <string>com.example.repl</string>\n"));
+        assert!(plist.contains("<string>/usr/local/bin/REPL</string>\n        <string>run</string>\n"));
+        assert!(plist.contains("<key>UserName</key>\n    <string>repl</string>\n"));
+        assert!(plist.contains("<key>StandardOutPath</key>\n    <string>/var/log/repl/repl.log</string>\n"));
+        assert!(plist.contains("<key>StandardErrorPath</key>\n    <string>/var/log/repl/repl.err.log</string>\n"));
+        assert!(plist.contains("<key>TG_TOKEN</key>\n        <string>token</string>\n"));
+        assert!(plist.contains("<key>EXEC_WORKING_DIR</key>\n        <string>/tmp/a&amp;b</string>\n"));
+    }
+
+    #[test]
+    fn the_launchd_plist_logs_into_var_log_by_default() {
+        let plist = definition(&[]).launchd_plist();
+
+        assert!(plist.contains("<string>/var/log/repl.log</string>"));
+        assert!(plist.contains("<string>/var/log/repl.err.log</string>"));
+        assert!(!plist.contains("UserName"));
+    }
+
+    #[test]
+    fn the_environment_file_quotes_the_values() {
+        let file = definition(&[("WIFE_FILE_PATH", r#"say "hi"\n"#)]).environment_file();
+
+        assert!(file.contains("TG_CHAT_ID=\"1\"\n"));
+        assert!(file.contains(r#"WIFE_FILE_PATH="say \"hi\"\\n""#));
+    }
+
+    #[test]
+    fn the_arguments_of_exec_start_are_quoted() {
+        assert_eq!(quote_systemd("/opt/my app/REPL"), "\"/opt/my app/REPL\"");
+        assert_eq!(quote_systemd("100%"), "100%%");
+        assert_eq!(quote_systemd(""), "\"\"");
+    }
+}
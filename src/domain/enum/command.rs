@@ -2,6 +2,17 @@
 pub enum Type {
     Ping,
     Exec,
+    Every,
+    Shell,
+    Session,
+    Background,
+    Jobs,
+    Tail,
+    Kill,
+    Shutdown,
+    Config,
+    Audit,
+    Dlq,
     Note,
     Event,
     NotFound,
@@ -11,20 +22,20 @@ impl std::fmt::Display for Type {
         match self {
             Self::Ping => write!(f, "Ping"),
             Self::Exec => write!(f, "Exec"),
+            Self::Every => write!(f, "Every"),
+            Self::Shell => write!(f, "Shell"),
+            Self::Session => write!(f, "Session"),
+            Self::Background => write!(f, "Background"),
+            Self::Jobs => write!(f, "Jobs"),
+            Self::Tail => write!(f, "Tail"),
+            Self::Kill => write!(f, "Kill"),
+            Self::Shutdown => write!(f, "Shutdown"),
+            Self::Config => write!(f, "Config"),
+            Self::Audit => write!(f, "Audit"),
+            Self::Dlq => write!(f, "Dlq"),
             Self::Note => write!(f, "Note"),
             Self::Event => write!(f, "Event"),
             Self::NotFound => write!(f, "NotFound"),
         }
     }
 }
-impl Type {
-    fn to_string(&self) -> String {
-        match self {
-            Type::Ping => String::from("Ping"),
-            Type::Exec => String::from("Exec"),
-            Type::Note => String::from("Note"),
-            Type::Event => String::from("Event"),
-            Type::NotFound => String::from("NotFound"),
-        }
-    }
-}
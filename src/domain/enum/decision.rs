@@ -0,0 +1,17 @@
+use serde::{Deserialize, Serialize};
+
+// Decision is what the policies have decided about a command: it has been run or refused.
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Decision {
+    Allowed,
+    Denied,
+}
+impl std::fmt::Display for Decision {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Allowed => write!(f, "allowed"),
+            Self::Denied => write!(f, "denied"),
+        }
+    }
+}
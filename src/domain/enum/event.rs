@@ -1,6 +1,11 @@
+use std::time::Duration;
+
 #[derive(PartialEq, Eq, Debug, Clone, Copy)]
 pub enum Repeat {
     Once,
     Always,
+    // the event is executed n times in total
     Times(i64),
+    // the event is executed n times in total, once in the interval
+    Every(Duration, i64),
 }
@@ -0,0 +1,8 @@
+// ExecMode tells the ExecCmd how the input string must be turned into a process.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum ExecMode {
+    // the input is tokenized by shlex and the first token is spawned directly
+    Direct,
+    // the input is passed as a single script argument to the configured shell
+    Shell,
+}
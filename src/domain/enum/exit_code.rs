@@ -1,7 +1,133 @@
+use crate::domain::error::exec::RunAsDeniedError;
+use crate::domain::r#enum::limit::Limit;
+use crate::infrastructure::helper::signal::signal_name;
+use std::error::Error;
+use std::io;
+use std::os::unix::process::ExitStatusExt;
+use std::process::ExitStatus;
+
 #[derive(PartialEq, Eq, Debug, Clone, Copy)]
 pub enum ExitCode {
     Success,
     Failed,
     Wife,
     Other(i32),
+    LimitExceeded(Limit),
+    // the process has been terminated by the signal
+    Signal { signal: i32, core_dumped: bool },
+    // the command has not finished in time and has been killed
+    Timeout,
+    // the command has been refused (e.g. the permissions do not allow to run it)
+    Denied,
+}
+impl ExitCode {
+    // label returns the code as a short value of a few kinds, so it may be used as a metric label
+    pub fn label(&self) -> String {
+        match self {
+            Self::Success | Self::Wife => "0".to_string(),
+            Self::Failed => "1".to_string(),
+            Self::Other(code) => code.to_string(),
+            Self::LimitExceeded(_) => "limit_exceeded".to_string(),
+            Self::Signal { .. } => "signal".to_string(),
+            Self::Timeout => "timeout".to_string(),
+            Self::Denied => "denied".to_string(),
+        }
+    }
+}
+impl std::fmt::Display for ExitCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Success | Self::Wife => write!(f, "0"),
+            Self::Failed => write!(f, "1"),
+            Self::Other(code) => write!(f, "{}", code),
+            Self::LimitExceeded(limit) => write!(f, "{} limit exceeded", limit),
+            Self::Signal { signal, core_dumped } => {
+                match signal_name(*signal) {
+                    Some(name) => write!(f, "killed by {} ({})", name, signal)?,
+                    None => write!(f, "killed by signal {}", signal)?,
+                }
+                if *core_dumped {
+                    write!(f, ", core dumped")?;
+                }
+                Ok(())
+            }
+            Self::Timeout => write!(f, "timed out"),
+            Self::Denied => write!(f, "denied"),
+        }
+    }
+}
+
+// exit_code tells how the process has finished: by itself with a code or by a signal.
+pub fn exit_code(status: ExitStatus) -> ExitCode {
+    match (status.code(), status.signal()) {
+        (Some(code), _) => ExitCode::Other(code),
+        (None, Some(signal)) => ExitCode::Signal { signal, core_dumped: status.core_dumped() },
+        (None, None) => ExitCode::Failed,
+    }
+}
+
+// spawn_error_code tells a refusal to run the process apart from the other spawn failures.
+pub fn spawn_error_code(error: &io::Error) -> ExitCode {
+    match error.kind() {
+        io::ErrorKind::PermissionDenied => ExitCode::Denied,
+        _ => ExitCode::Failed,
+    }
+}
+
+// build_error_code tells a refusal to run the process as the requested user apart from the other build failures.
+pub fn build_error_code(error: &(dyn Error + 'static)) -> ExitCode {
+    match error.downcast_ref::<RunAsDeniedError>() {
+        Some(_) => ExitCode::Denied,
+        None => ExitCode::Failed,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_process_status_is_mapped_to_the_exit_code() {
+        let cases = [
+            // a wait status keeps an exit code in the second byte and a signal in the first one
+            (0, ExitCode::Other(0)),
+            (3 << 8, ExitCode::Other(3)),
+            (255 << 8, ExitCode::Other(255)),
+            (libc::SIGKILL, ExitCode::Signal { signal: libc::SIGKILL, core_dumped: false }),
+            (libc::SIGSEGV | 0x80, ExitCode::Signal { signal: libc::SIGSEGV, core_dumped: true }),
+        ];
+
+        for (status, code) in cases {
+            assert_eq!(exit_code(ExitStatus::from_raw(status)), code, "status {}", status);
+        }
+    }
+
+    #[test]
+    fn a_refusal_is_told_apart_from_the_other_failures() {
+        assert_eq!(spawn_error_code(&io::Error::from(io::ErrorKind::PermissionDenied)), ExitCode::Denied);
+        assert_eq!(spawn_error_code(&io::Error::from(io::ErrorKind::NotFound)), ExitCode::Failed);
+        assert_eq!(build_error_code(&RunAsDeniedError::new("root".to_string())), ExitCode::Denied);
+        assert_eq!(build_error_code(&io::Error::from(io::ErrorKind::NotFound)), ExitCode::Failed);
+    }
+
+    #[test]
+    fn the_exit_codes_are_shown_and_labeled() {
+        let cases = [
+            (ExitCode::Success, "0", "0"),
+            (ExitCode::Wife, "0", "0"),
+            (ExitCode::Failed, "1", "1"),
+            (ExitCode::Other(127), "127", "127"),
+            (ExitCode::LimitExceeded(Limit::Output), "output limit exceeded", "limit_exceeded"),
+            (ExitCode::LimitExceeded(Limit::AddressSpace), "as limit exceeded", "limit_exceeded"),
+            (ExitCode::Signal { signal: libc::SIGKILL, core_dumped: false }, "killed by SIGKILL (9)", "signal"),
+            (ExitCode::Signal { signal: libc::SIGSEGV, core_dumped: true }, "killed by SIGSEGV (11), core dumped", "signal"),
+            (ExitCode::Signal { signal: 64, core_dumped: false }, "killed by signal 64", "signal"),
+            (ExitCode::Timeout, "timed out", "timeout"),
+            (ExitCode::Denied, "denied", "denied"),
+        ];
+
+        for (code, text, label) in cases {
+            assert_eq!((code.to_string().as_str(), code.label().as_str()), (text, label), "{:?}", code);
+        }
+    }
 }
@@ -0,0 +1,18 @@
+// JobState is a state of a background job process.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum JobState {
+    Running,
+    // the process has exited with the code
+    Exited(i32),
+    // the process has been terminated by the signal
+    Signaled(i32),
+}
+impl std::fmt::Display for JobState {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Running => write!(f, "running"),
+            Self::Exited(code) => write!(f, "exited with code {}", code),
+            Self::Signaled(signal) => write!(f, "killed by signal {}", signal),
+        }
+    }
+}
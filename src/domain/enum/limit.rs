@@ -0,0 +1,22 @@
+// Limit names a limit which a process has exceeded.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum Limit {
+    Cpu,
+    Output,
+    // RLIMIT_AS, RLIMIT_NOFILE and RLIMIT_NPROC do not kill a process, its calls fail instead,
+    // so they are just suspected by the errors it fails with
+    AddressSpace,
+    OpenFiles,
+    Processes,
+}
+impl std::fmt::Display for Limit {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Cpu => write!(f, "cpu"),
+            Self::Output => write!(f, "output"),
+            Self::AddressSpace => write!(f, "as"),
+            Self::OpenFiles => write!(f, "nofile"),
+            Self::Processes => write!(f, "nproc"),
+        }
+    }
+}
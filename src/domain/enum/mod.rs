@@ -1,3 +1,8 @@
 pub mod command;
+pub mod decision;
 pub mod event;
+pub mod exec_mode;
 pub mod exit_code;
+pub mod job;
+pub mod limit;
+pub mod topic;
@@ -0,0 +1,32 @@
+// Topic is a kind of the lifecycle events which the event loop publishes, the subscribers are
+// keyed by it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Topic {
+    // a command is going to be executed
+    CommandStarted,
+    // a command has been executed, its result is not delivered yet
+    CommandFinished,
+    // an event has been taken off the queue to be run
+    EventFired,
+}
+impl std::str::FromStr for Topic {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "command.started" => Ok(Topic::CommandStarted),
+            "command.finished" => Ok(Topic::CommandFinished),
+            "event.fired" => Ok(Topic::EventFired),
+            other => Err(format!("unknown topic `{}`, use command.started, command.finished or event.fired", other)),
+        }
+    }
+}
+impl std::fmt::Display for Topic {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::CommandStarted => write!(f, "command.started"),
+            Self::CommandFinished => write!(f, "command.finished"),
+            Self::EventFired => write!(f, "event.fired"),
+        }
+    }
+}
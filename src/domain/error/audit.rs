@@ -0,0 +1,39 @@
+use std::error::Error;
+use std::fmt;
+
+#[derive(Debug)]
+pub struct AuditTamperedError {
+    file: String,
+    line: usize,
+}
+
+impl AuditTamperedError {
+    pub fn new(file: String, line: usize) -> AuditTamperedError {
+        AuditTamperedError { file, line }
+    }
+}
+
+impl fmt::Display for AuditTamperedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "The audit entry at line {} of {} does not match the hash chain.", self.line, self.file)
+    }
+}
+
+impl Error for AuditTamperedError {}
+
+#[derive(Debug, Default)]
+pub struct AuditDisabledError {}
+
+impl AuditDisabledError {
+    pub fn new() -> AuditDisabledError {
+        AuditDisabledError {}
+    }
+}
+
+impl fmt::Display for AuditDisabledError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "The audit log is disabled, please check the AUDIT_FILE_PATH variable.")
+    }
+}
+
+impl Error for AuditDisabledError {}
@@ -0,0 +1,133 @@
+use std::error::Error;
+use std::fmt;
+
+#[derive(Debug, Default)]
+pub struct EmptyCommandError {}
+
+impl EmptyCommandError {
+    pub fn new() -> EmptyCommandError {
+        EmptyCommandError {}
+    }
+}
+
+impl fmt::Display for EmptyCommandError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "The command is empty, please check the send data and try again.")
+    }
+}
+
+impl Error for EmptyCommandError {}
+
+#[derive(Debug)]
+pub struct UnknownUserError {
+    user: String,
+}
+
+impl UnknownUserError {
+    pub fn new(user: String) -> UnknownUserError {
+        UnknownUserError { user }
+    }
+}
+
+impl fmt::Display for UnknownUserError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Unknown user `{}`, unable to run the command as it.", self.user)
+    }
+}
+
+impl Error for UnknownUserError {}
+
+#[derive(Debug)]
+pub struct RunAsDeniedError {
+    user: String,
+}
+
+impl RunAsDeniedError {
+    pub fn new(user: String) -> RunAsDeniedError {
+        RunAsDeniedError { user }
+    }
+}
+
+impl fmt::Display for RunAsDeniedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Only the admins may run the commands as another user, `{}` is not allowed.", self.user)
+    }
+}
+
+impl Error for RunAsDeniedError {}
+
+#[derive(Debug)]
+pub struct UnknownGroupError {
+    group: String,
+}
+
+impl UnknownGroupError {
+    pub fn new(group: String) -> UnknownGroupError {
+        UnknownGroupError { group }
+    }
+}
+
+impl fmt::Display for UnknownGroupError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Unknown group `{}`, unable to run the command as it.", self.group)
+    }
+}
+
+impl Error for UnknownGroupError {}
+
+#[derive(Debug)]
+pub struct WorkingDirNotFoundError {
+    path: String,
+}
+
+impl WorkingDirNotFoundError {
+    pub fn new(path: String) -> WorkingDirNotFoundError {
+        WorkingDirNotFoundError { path }
+    }
+}
+
+impl fmt::Display for WorkingDirNotFoundError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Working directory `{}` does not exist or is not a directory.", self.path)
+    }
+}
+
+impl Error for WorkingDirNotFoundError {}
+
+#[derive(Debug)]
+pub struct StdinTooLargeError {
+    limit: u64,
+}
+
+impl StdinTooLargeError {
+    pub fn new(limit: u64) -> StdinTooLargeError {
+        StdinTooLargeError { limit }
+    }
+}
+
+impl fmt::Display for StdinTooLargeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "The command input is larger than the limit of {} bytes.", self.limit)
+    }
+}
+
+impl Error for StdinTooLargeError {}
+
+#[derive(Debug)]
+pub struct FileUnavailableError {
+    file_id: String,
+}
+
+impl FileUnavailableError {
+    pub fn new(file_id: String) -> FileUnavailableError {
+        FileUnavailableError { file_id }
+    }
+}
+
+impl fmt::Display for FileUnavailableError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "The file `{}` is not available for download.", self.file_id)
+    }
+}
+
+impl Error for FileUnavailableError {}
@@ -0,0 +1,98 @@
+use crate::infrastructure::model::command::Exit;
+use std::error::Error;
+use std::fmt;
+
+// The command has not been executed to the end (e.g. it has panicked), so there is no result.
+#[derive(Debug)]
+pub struct ExecutionFailedError {
+    name: String,
+    reason: String,
+}
+
+impl ExecutionFailedError {
+    pub fn new(name: String, reason: String) -> ExecutionFailedError {
+        ExecutionFailedError { name, reason }
+    }
+}
+
+impl fmt::Display for ExecutionFailedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Command `{}` has failed to execute: {}.", self.name, self.reason)
+    }
+}
+
+impl Error for ExecutionFailedError {}
+
+// The command has been executed but its result has not been sent, the result is kept, so the
+// delivery may be retried without executing the command again.
+#[derive(Debug)]
+pub struct DeliveryFailedError {
+    exit: Exit,
+    source: Box<dyn Error>,
+}
+
+impl DeliveryFailedError {
+    pub fn new(exit: Exit, source: Box<dyn Error>) -> DeliveryFailedError {
+        DeliveryFailedError { exit, source }
+    }
+
+    pub fn into_exit(self) -> Exit {
+        self.exit
+    }
+}
+
+impl fmt::Display for DeliveryFailedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Failed to deliver the result: {}", self.source)
+    }
+}
+
+impl Error for DeliveryFailedError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(self.source.as_ref())
+    }
+}
+
+// The result has been sent by some of the responders alone, the retry sends it by the rest of them.
+#[derive(Debug)]
+pub struct PartiallyDeliveredError {
+    delivered: Vec<String>,
+    reason: String,
+}
+
+impl PartiallyDeliveredError {
+    pub fn new(delivered: Vec<String>, reason: String) -> PartiallyDeliveredError {
+        PartiallyDeliveredError { delivered, reason }
+    }
+
+    pub fn delivered(&self) -> &[String] {
+        &self.delivered
+    }
+}
+
+impl fmt::Display for PartiallyDeliveredError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.reason)
+    }
+}
+
+impl Error for PartiallyDeliveredError {}
+
+#[derive(Debug)]
+pub struct DeadLetterNotFoundError {
+    id: String,
+}
+
+impl DeadLetterNotFoundError {
+    pub fn new(id: String) -> DeadLetterNotFoundError {
+        DeadLetterNotFoundError { id }
+    }
+}
+
+impl fmt::Display for DeadLetterNotFoundError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Dead letter `{}` not found, see the list of them by /dlq.", self.id)
+    }
+}
+
+impl Error for DeadLetterNotFoundError {}
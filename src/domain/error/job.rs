@@ -0,0 +1,59 @@
+use std::error::Error;
+use std::fmt;
+
+#[derive(Debug)]
+pub struct JobNotFoundError {
+    id: String,
+}
+
+impl JobNotFoundError {
+    pub fn new(id: String) -> JobNotFoundError {
+        JobNotFoundError { id }
+    }
+}
+
+impl fmt::Display for JobNotFoundError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Job `{}` not found, see the list of jobs by /jobs.", self.id)
+    }
+}
+
+impl Error for JobNotFoundError {}
+
+#[derive(Debug)]
+pub struct JobFinishedError {
+    id: u64,
+}
+
+impl JobFinishedError {
+    pub fn new(id: u64) -> JobFinishedError {
+        JobFinishedError { id }
+    }
+}
+
+impl fmt::Display for JobFinishedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Job [{}] has already finished.", self.id)
+    }
+}
+
+impl Error for JobFinishedError {}
+
+#[derive(Debug)]
+pub struct UnknownSignalError {
+    signal: String,
+}
+
+impl UnknownSignalError {
+    pub fn new(signal: String) -> UnknownSignalError {
+        UnknownSignalError { signal }
+    }
+}
+
+impl fmt::Display for UnknownSignalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Unknown signal `{}`, use a number or a name like TERM or SIGKILL.", self.signal)
+    }
+}
+
+impl Error for UnknownSignalError {}
@@ -1,7 +1,7 @@
 use std::error::Error;
 use std::fmt;
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub struct UnknownMessageTypeError {}
 
 impl UnknownMessageTypeError {
@@ -36,7 +36,7 @@ impl OffsetFetchError {
 
 impl fmt::Display for OffsetFetchError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "Failed to fetch offset: {}.", self.parent.to_string())
+        write!(f, "Failed to fetch offset: {}.", self.parent)
     }
 }
 
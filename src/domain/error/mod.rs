@@ -1,3 +1,8 @@
+pub mod audit;
 pub mod date;
+pub mod exec;
+pub mod executor;
+pub mod job;
 pub mod message;
+pub mod session;
 pub mod wife;
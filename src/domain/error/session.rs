@@ -0,0 +1,76 @@
+use std::error::Error;
+use std::fmt;
+
+#[derive(Debug, Default)]
+pub struct SessionNotFoundError {}
+
+impl SessionNotFoundError {
+    pub fn new() -> SessionNotFoundError {
+        SessionNotFoundError {}
+    }
+}
+
+impl fmt::Display for SessionNotFoundError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "There is no active session, start a new one by /session start.")
+    }
+}
+
+impl Error for SessionNotFoundError {}
+
+#[derive(Debug, Default)]
+pub struct SessionAlreadyExistsError {}
+
+impl SessionAlreadyExistsError {
+    pub fn new() -> SessionAlreadyExistsError {
+        SessionAlreadyExistsError {}
+    }
+}
+
+impl fmt::Display for SessionAlreadyExistsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "The session is already started, stop it by /session stop first.")
+    }
+}
+
+impl Error for SessionAlreadyExistsError {}
+
+#[derive(Debug, Default)]
+pub struct SessionClosedError {}
+
+impl SessionClosedError {
+    pub fn new() -> SessionClosedError {
+        SessionClosedError {}
+    }
+}
+
+impl fmt::Display for SessionClosedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "The session shell has exited, start a new one by /session start.")
+    }
+}
+
+impl Error for SessionClosedError {}
+
+#[derive(Debug)]
+pub struct SessionTimeoutError {
+    output: String,
+}
+
+impl SessionTimeoutError {
+    pub fn new(output: String) -> SessionTimeoutError {
+        SessionTimeoutError { output }
+    }
+    // output returns everything the command has printed before the timeout
+    pub fn output(&self) -> &str {
+        self.output.as_str()
+    }
+}
+
+impl fmt::Display for SessionTimeoutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "The command has not finished in time and has been interrupted.")
+    }
+}
+
+impl Error for SessionTimeoutError {}
@@ -1,7 +1,7 @@
 use std::error::Error;
 use std::fmt;
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub struct WifeMessagesVecIsEmptyError {}
 
 impl WifeMessagesVecIsEmptyError {
@@ -18,7 +18,7 @@ impl fmt::Display for WifeMessagesVecIsEmptyError {
 
 impl Error for WifeMessagesVecIsEmptyError {}
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub struct WifeMessageIsNoneError {}
 
 impl WifeMessageIsNoneError {
@@ -1,15 +1,38 @@
+use crate::app::cfg::handle::CfgHandle;
+use crate::app::model::state::State;
 use crate::domain::model::command::{
-    Event, EventCmd, ExecCmd, NotFoundCmd, Note, NoteCmd, PingCmd,
+    AuditCmd, BgCmd, ConfigCmd, DlqCmd, Event, EventCmd, EveryCmd, ExecCmd, FailedCmd, JobsCmd, KillCmd, NotFoundCmd, Note, NoteCmd, PingCmd,
+    SessionCmd, SessionExecCmd, ShutdownCmd, TailCmd,
 };
+use crate::domain::error::exec::StdinTooLargeError;
 use crate::domain::model::event::ExecutableEvent;
+use crate::domain::model::message::Message;
+use crate::domain::model::schedule::Schedule;
 use crate::domain::r#enum::command::Type;
+use crate::domain::r#enum::exec_mode::ExecMode;
+use crate::domain::service::audit::log::AuditLogTrait;
 use crate::domain::service::event::r#loop::EventLoop;
-use crate::infrastructure::integration;
-use crate::infrastructure::model::command::Command;
-use integration::telegram::model::Message;
+use crate::domain::service::job::registry::JobRegistryTrait;
+use crate::domain::service::process::builder::ProcessBuilder;
+use crate::domain::service::session::manager::SessionManagerTrait;
+use crate::domain::service::transport::transport::Transport;
+use crate::infrastructure::model::command::{Command, Stdin};
+use std::error::Error;
 use std::sync::{Arc, Mutex};
 
 const CMD_PREFIX: &str = "/cmd";
+const SHELL_PREFIX: &str = "/sh";
+const EVERY_PREFIX: &str = "/every";
+const SESSION_PREFIX: &str = "/session";
+const BG_PREFIX: &str = "/bg";
+const JOBS_PREFIX: &str = "/jobs";
+const TAIL_PREFIX: &str = "/tail";
+const FG_PREFIX: &str = "/fg";
+const KILL_PREFIX: &str = "/kill";
+const SHUTDOWN_PREFIX: &str = "/shutdown";
+const CONFIG_PREFIX: &str = "/config";
+const AUDIT_PREFIX: &str = "/audit";
+const DLQ_PREFIX: &str = "/dlq";
 const NOTE_PREFIX: &str = "/note";
 const EVENT_PREFIX: &str = "/event";
 const PING_PREFIX: &str = "/ping";
@@ -20,41 +43,153 @@ pub trait Factoryer: Send + Sync {
 }
 
 pub struct CommandFactory {
+    cfg: CfgHandle,
     note_mutex: Arc<Mutex<Vec<Note>>>,
     event_mutex: Arc<Mutex<Vec<Event>>>,
+    sessions: Arc<Box<dyn SessionManagerTrait>>,
+    builder: Arc<ProcessBuilder>,
+    jobs: Arc<Box<dyn JobRegistryTrait>>,
+    transport: Arc<Box<dyn Transport>>,
+    state: Arc<Box<dyn State>>,
+    audit: Arc<Box<dyn AuditLogTrait>>,
     event_loop: Arc<Box<dyn EventLoop>>,
 }
 
 impl CommandFactory {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
+        cfg: CfgHandle,
         event_mutex: Arc<Mutex<Vec<Event>>>,
+        sessions: Arc<Box<dyn SessionManagerTrait>>,
+        builder: Arc<ProcessBuilder>,
+        jobs: Arc<Box<dyn JobRegistryTrait>>,
+        transport: Arc<Box<dyn Transport>>,
+        state: Arc<Box<dyn State>>,
+        audit: Arc<Box<dyn AuditLogTrait>>,
         event_loop: Arc<Box<dyn EventLoop>>,
     ) -> CommandFactory {
         CommandFactory {
+            cfg,
             note_mutex: Arc::new(Mutex::new(vec![])),
             event_mutex,
+            sessions,
+            builder,
+            jobs,
+            transport,
+            state,
+            audit,
             event_loop,
         }
     }
+    // stdin returns the message body if any, otherwise the text or the file of the message
+    // which the command replies to. The file is downloaded by the command itself, so a large
+    // one does not hold the consumer.
+    fn stdin(&self, body: Option<String>, msg: &Message) -> Result<Option<Stdin>, Box<dyn Error>> {
+        let limit = self.cfg.get().exec_max_stdin_bytes;
+
+        let stdin = match (body, msg.reply_to.as_deref()) {
+            (Some(body), _) if !body.is_empty() => body.into_bytes(),
+            (_, Some(reply)) => match &reply.file {
+                Some(file) => return Ok(Some(Stdin::File(file.clone()))),
+                None if !reply.text.is_empty() => reply.text.clone().into_bytes(),
+                None => reply.caption.clone().unwrap_or_default().into_bytes(),
+            },
+            _ => return Ok(None),
+        };
+
+        if stdin.len() as u64 > limit {
+            return Err(Box::new(StdinTooLargeError::new(limit)));
+        }
+
+        Ok(Some(Stdin::Data(stdin)))
+    }
+    // exec_mode returns the mode of /cmd which may be switched to the shell one by cfg
+    fn exec_mode(&self) -> ExecMode {
+        if self.cfg.get().is_shell_mode_enabled {
+            ExecMode::Shell
+        } else {
+            ExecMode::Direct
+        }
+    }
 }
 
 impl Factoryer for CommandFactory {
     fn make(&self, msg: Message) -> Box<dyn ExecutableEvent> {
         let (cmd_type, prefix) = match msg.text.clone() {
             str if str.starts_with(CMD_PREFIX) => (Type::Exec, CMD_PREFIX),
+            str if str.starts_with(EVERY_PREFIX) => (Type::Every, EVERY_PREFIX),
+            str if str.starts_with(SESSION_PREFIX) => (Type::Session, SESSION_PREFIX),
+            str if str.starts_with(SHUTDOWN_PREFIX) => (Type::Shutdown, SHUTDOWN_PREFIX),
+            str if str.starts_with(CONFIG_PREFIX) => (Type::Config, CONFIG_PREFIX),
+            str if str.starts_with(AUDIT_PREFIX) => (Type::Audit, AUDIT_PREFIX),
+            str if str.starts_with(DLQ_PREFIX) => (Type::Dlq, DLQ_PREFIX),
+            str if str.starts_with(SHELL_PREFIX) => (Type::Shell, SHELL_PREFIX),
+            str if str.starts_with(BG_PREFIX) => (Type::Background, BG_PREFIX),
+            str if str.starts_with(JOBS_PREFIX) => (Type::Jobs, JOBS_PREFIX),
+            str if str.starts_with(TAIL_PREFIX) => (Type::Tail, TAIL_PREFIX),
+            str if str.starts_with(FG_PREFIX) => (Type::Tail, FG_PREFIX),
+            str if str.starts_with(KILL_PREFIX) => (Type::Kill, KILL_PREFIX),
             str if str.starts_with(NOTE_PREFIX) => (Type::Note, NOTE_PREFIX),
             str if str.starts_with(EVENT_PREFIX) => (Type::Event, EVENT_PREFIX),
             str if str.starts_with(PING_PREFIX) => (Type::Ping, PING_PREFIX),
             _ => (Type::NotFound, NOT_FOUND_PREFIX),
         };
 
-        let cmd = Command::new(msg.text.clone().replace(prefix, ""), cmd_type.clone(), msg);
+        let is_exec = matches!(cmd_type, Type::Exec | Type::Every | Type::Shell | Type::Background);
+
+        // while the chat has an active session, its commands share one shell process
+        if matches!(cmd_type, Type::Exec | Type::Shell) && self.sessions.is_active(msg.chat_id) {
+            let cmd = Command::new(msg.text.clone().replacen(prefix, "", 1), cmd_type, msg, None);
+            return Box::new(SessionExecCmd::new(cmd, self.sessions.clone()));
+        }
+
+        // the first line of an exec command is the command itself, the rest is its stdin
+        let (str, body) = match msg.text.split_once('\n') {
+            Some((head, body)) if is_exec => (head.to_string(), Some(body.to_string())),
+            _ => (msg.text.clone(), None),
+        };
+        let stdin = if is_exec { self.stdin(body, &msg) } else { Ok(None) };
+        let cmd = Command::new(str.replacen(prefix, "", 1), cmd_type.clone(), msg, None);
+
+        let cmd = match stdin {
+            Ok(stdin) => Command { stdin, ..cmd },
+            Err(error) => return Box::new(FailedCmd::new(cmd, error.to_string())),
+        };
 
         match cmd_type {
             Type::Ping => Box::new(PingCmd::new(cmd)),
             Type::Note => Box::new(NoteCmd::new(cmd, self.note_mutex.clone())),
             Type::Event => Box::new(EventCmd::new(cmd, self.event_mutex.clone())),
-            Type::Exec => Box::new(ExecCmd::new(cmd)),
+            Type::Exec => Box::new(ExecCmd::new(cmd, self.exec_mode(), self.builder.clone(), self.transport.clone())),
+            Type::Shell => Box::new(ExecCmd::new(cmd, ExecMode::Shell, self.builder.clone(), self.transport.clone())),
+            Type::Every => match Schedule::parse(cmd.str.as_str()) {
+                Ok((schedule, str)) => {
+                    let exec = ExecCmd::new(Command { str, ..cmd }, self.exec_mode(), self.builder.clone(), self.transport.clone());
+                    Box::new(EveryCmd::new(exec, schedule))
+                }
+                Err(error) => Box::new(FailedCmd::new(cmd, error)),
+            },
+            Type::Session => Box::new(SessionCmd::new(cmd, self.sessions.clone())),
+            Type::Background => Box::new(BgCmd::new(cmd, self.exec_mode(), self.builder.clone(), self.transport.clone(), self.jobs.clone())),
+            Type::Jobs => Box::new(JobsCmd::new(cmd, self.jobs.clone())),
+            Type::Tail => Box::new(TailCmd::new(cmd, self.jobs.clone())),
+            Type::Kill => Box::new(KillCmd::new(cmd, self.jobs.clone())),
+            Type::Shutdown => {
+                let is_authorized = self.cfg.get().is_admin(cmd.message.user.id);
+                Box::new(ShutdownCmd::new(cmd, self.state.clone(), is_authorized))
+            }
+            Type::Config => {
+                let is_authorized = self.cfg.get().is_admin(cmd.message.user.id);
+                Box::new(ConfigCmd::new(cmd, self.cfg.clone(), is_authorized))
+            }
+            Type::Audit => {
+                let is_authorized = self.cfg.get().is_admin(cmd.message.user.id);
+                Box::new(AuditCmd::new(cmd, self.audit.clone(), is_authorized))
+            }
+            Type::Dlq => {
+                let is_authorized = self.cfg.get().is_admin(cmd.message.user.id);
+                Box::new(DlqCmd::new(cmd, self.event_loop.clone(), is_authorized))
+            }
             _ => Box::new(NotFoundCmd::new(cmd)),
         }
     }
@@ -0,0 +1,155 @@
+use crate::domain::r#enum::decision::Decision;
+use crate::domain::r#enum::exit_code::ExitCode;
+use crate::infrastructure::model::command::{Command, Exit};
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::time::Duration;
+
+// AuditEntry is a record of an executed command, the entries are kept one JSON object per line.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AuditEntry {
+    pub timestamp: String,
+    pub user_id: i64,
+    pub username: String,
+    pub chat_id: i64,
+    pub message_id: i64,
+    // the text of the message as it has been received
+    pub input: String,
+    pub command: String,
+    // the program with its arguments if the command has spawned one
+    pub argv: Option<Vec<String>>,
+    pub exit_code: String,
+    pub duration_ms: u64,
+    pub stdout_bytes: usize,
+    pub stderr_bytes: usize,
+    pub attachment_bytes: usize,
+    pub decision: Decision,
+    // the hash of the previous entry and of this one, set if the entries are hash-chained
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prev_hash: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hash: Option<String>,
+}
+
+impl AuditEntry {
+    pub fn new(command: &Command, exit: &Exit, duration: Duration) -> AuditEntry {
+        AuditEntry {
+            timestamp: Local::now().format("%Y-%m-%dT%H:%M:%S%.3f%:z").to_string(),
+            user_id: command.message.user.id,
+            username: command.message.user.username.clone(),
+            chat_id: command.message.chat_id,
+            message_id: command.message.id,
+            input: command.message.text.clone(),
+            command: command.r#type.to_string(),
+            argv: exit.argv.clone(),
+            exit_code: exit.code.to_string(),
+            duration_ms: duration.as_millis() as u64,
+            stdout_bytes: exit.stdout.len(),
+            stderr_bytes: exit.stderr.len(),
+            attachment_bytes: exit.attachments.iter().map(|attachment| attachment.data.len()).sum(),
+            decision: match exit.code {
+                ExitCode::Denied => Decision::Denied,
+                _ => Decision::Allowed,
+            },
+            prev_hash: None,
+            hash: None,
+        }
+    }
+
+    // chain links the entry to the previous one: the hash covers the entry along with prev_hash,
+    // so an entry may not be changed, removed or inserted without breaking the following hashes
+    pub fn chain(&mut self, prev_hash: Option<String>) {
+        self.prev_hash = Some(prev_hash.unwrap_or_default());
+        self.hash = None;
+        self.hash = Some(self.digest());
+    }
+
+    // tells whether the hash matches the entry
+    pub fn is_intact(&self) -> bool {
+        let mut entry = self.clone();
+        entry.hash = None;
+        self.hash.as_deref() == Some(entry.digest().as_str())
+    }
+
+    fn digest(&self) -> String {
+        let json = serde_json::to_string(self).unwrap_or_default();
+        Sha256::digest(json.as_bytes()).iter().map(|byte| format!("{:02x}", byte)).collect()
+    }
+}
+
+impl std::fmt::Display for AuditEntry {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "{} user {} (@{}) chat {}: {} `{}`, code {}, {}ms, out {}/{}/{} bytes, {}",
+            self.timestamp,
+            self.user_id,
+            self.username,
+            self.chat_id,
+            self.command,
+            self.input,
+            self.exit_code,
+            self.duration_ms,
+            self.stdout_bytes,
+            self.stderr_bytes,
+            self.attachment_bytes,
+            self.decision,
+        )?;
+        if let Some(argv) = &self.argv {
+            write!(f, ", argv {:?}", argv)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(input: &str) -> AuditEntry {
+        AuditEntry {
+            timestamp: "2025-01-01T00:00:00.000+00:00".to_string(),
+            user_id: 1,
+            username: "user".to_string(),
+            chat_id: 1,
+            message_id: 1,
+            input: input.to_string(),
+            command: "exec".to_string(),
+            argv: None,
+            exit_code: "0".to_string(),
+            duration_ms: 0,
+            stdout_bytes: 0,
+            stderr_bytes: 0,
+            attachment_bytes: 0,
+            decision: Decision::Allowed,
+            prev_hash: None,
+            hash: None,
+        }
+    }
+
+    #[test]
+    fn a_chained_entry_is_intact_until_it_is_changed() {
+        let mut first = entry("/cmd ls");
+        first.chain(None);
+        assert_eq!(first.prev_hash.as_deref(), Some(""));
+        assert!(first.is_intact());
+
+        let mut edited = first.clone();
+        edited.input = "/cmd rm".to_string();
+        assert!(!edited.is_intact());
+        assert!(!entry("/cmd ls").is_intact());
+    }
+
+    #[test]
+    fn the_hash_covers_the_previous_one() {
+        let (mut a, mut b) = (entry("/cmd ls"), entry("/cmd ls"));
+        a.chain(Some("1".to_string()));
+        b.chain(Some("2".to_string()));
+
+        assert_ne!(a.hash, b.hash);
+
+        b.prev_hash = a.prev_hash.clone();
+        assert!(!b.is_intact());
+    }
+}
@@ -1,41 +1,118 @@
+use crate::app::cfg::handle::CfgHandle;
+use crate::app::model::health::Health;
+use crate::app::model::state::State;
 use crate::domain::model;
-use crate::domain::model::event::ExecutableEvent;
+use crate::domain::model::event::{EventSender, ExecutableEvent};
+use crate::domain::model::exec::ExecOptions;
+use crate::domain::model::limits::Limits;
+use crate::domain::model::retry::RetryPolicy;
+use crate::domain::model::schedule::Schedule;
 use crate::domain::r#enum::event::Repeat;
 use crate::infrastructure::helper::date::parse_yyyy_mm_dd_hm_from_str;
-use crate::infrastructure::model::command::{Command, Exit};
+use crate::infrastructure::helper::output::{is_binary, strip_ansi, to_text};
+use crate::domain::model::message::Attachment;
+use crate::infrastructure::model::command::{Command, Exit, Stdin};
 use chrono::{Datelike, Local, NaiveDateTime, Timelike};
-use shlex::split;
 use std::cmp::Ordering;
-use std::process::Command as OsCmd;
+use std::error::Error;
+use std::io;
+use std::os::unix::process::ExitStatusExt;
 use std::sync::atomic::AtomicI64;
 use std::sync::atomic::Ordering::SeqCst;
-use std::sync::mpsc::Sender;
 use std::sync::{Arc, Mutex};
-use crate::domain::r#enum::exit_code::ExitCode;
+use std::time::{Duration, Instant};
+use crate::domain::r#enum::exec_mode::ExecMode;
+use crate::domain::r#enum::exit_code::{build_error_code, exit_code, spawn_error_code, ExitCode};
+use crate::domain::error::session::SessionTimeoutError;
+use crate::domain::r#enum::limit::Limit;
+use crate::domain::error::job::UnknownSignalError;
+use crate::domain::error::executor::DeadLetterNotFoundError;
+use crate::domain::service::event::r#loop::EventLoop;
+use crate::domain::model::job::Job;
+use crate::domain::r#enum::job::JobState;
+use crate::domain::service::job::registry::JobRegistryTrait;
+use crate::domain::service::process::builder::ProcessBuilder;
+use crate::infrastructure::helper::signal::{parse_signal, signal_name};
+use crate::domain::service::process::capture::capture;
+use crate::domain::service::session::manager::SessionManagerTrait;
 use crate::domain::service::wife::message::service::MessageServiceTrait;
+use crate::domain::service::audit::log::AuditLogTrait;
+use crate::domain::service::transport::transport::Transport;
+
+// how many entries /audit shows by default and at most
+const AUDIT_DEFAULT_ENTRIES: usize = 10;
+const AUDIT_MAX_ENTRIES: usize = 50;
+// how often a disabled heartbeat is checked whether it has been enabled
+const HEARTBEAT_RECHECK_INTERVAL: Duration = Duration::from_secs(60);
 
 pub trait Executable {
     fn exec(&self) -> Exit;
 }
 
+// limit_note explains a failure by a resource limit which the process has most likely hit, the
+// kernel does not report these limits, so the note is a guess of the error (or of the stderr).
+fn limit_note(limits: &Limits, errno: Option<i32>, stderr: &str) -> Option<String> {
+    let (limit, value) = limits.suspect(errno, stderr)?;
+    let what = match limit {
+        Limit::AddressSpace => format!("the address space limit of {} bytes", value),
+        Limit::OpenFiles => format!("the limit of {} open files", value),
+        Limit::Processes => format!("the limit of {} processes", value),
+        _ => return None,
+    };
+    Some(format!("\nThe command may have failed because of {} ({}).", what, limit))
+}
+
+// read_stdin returns the input of a command, the file which it replies to is downloaded up to the limit.
+fn read_stdin(stdin: &Option<Stdin>, transport: &dyn Transport, limit: u64) -> Result<Option<Vec<u8>>, Box<dyn Error>> {
+    match stdin {
+        Some(Stdin::Data(data)) => Ok(Some(data.clone())),
+        Some(Stdin::File(file)) => transport.download(file, limit).map(Some),
+        None => Ok(None),
+    }
+}
+
+// output_text decodes the output of a process for the response, a binary one is attached as a file instead.
+// A text which is not valid UTF-8 (e.g. Latin-1) is shown decoded and its raw bytes are attached as well,
+// so the replaced characters are not lost.
+fn output_text(stream: &str, output: Vec<u8>, attachments: &mut Vec<Attachment>) -> String {
+    if !is_binary(&output) {
+        let mut text = to_text(&output);
+        if std::str::from_utf8(&output).is_err() {
+            let name = format!("{}.txt", stream);
+            text.push_str(format!("\n[the output is not valid UTF-8, its raw bytes are attached as {}]", name).as_str());
+            attachments.push(Attachment::new(name, output));
+        }
+        return text;
+    }
+
+    let name = format!("{}.bin", stream);
+    let text = format!("[binary output of {} bytes is attached as {}]", output.len(), name);
+    attachments.push(Attachment::new(name, output));
+    text
+}
+
+// how many times /ping answers
+const PING_TIMES: i64 = 3;
+
 pub struct PingCmd {
     cmd: Command,
-    atm: AtomicI64,
+    // the runs so far, the loop repeats the command, it just alternates the answer
+    runs: AtomicI64,
 }
 impl PingCmd {
     pub fn new(cmd: Command) -> PingCmd {
         PingCmd {
             cmd,
-            atm: AtomicI64::new(3),
+            runs: AtomicI64::new(0),
         }
     }
 }
 impl Executable for PingCmd {
     fn exec(&self) -> Exit {
-        let remaining_repeats = self.atm.fetch_sub(1, SeqCst);
+        let run = self.runs.fetch_add(1, SeqCst);
 
         let mut msg = "pong".to_string();
-        if remaining_repeats % 2 == 0 {
+        if run % 2 == 1 {
             msg = "ping".to_string();
         }
 
@@ -50,13 +127,16 @@ impl model::event::Event for PingCmd {
         true
     }
     fn repeats(&self) -> Repeat {
-        Repeat::Times(self.atm.load(SeqCst))
+        Repeat::Times(PING_TIMES)
     }
 }
 impl ExecutableEvent for PingCmd {
-    fn sender(&self) -> Option<Arc<Sender<Arc<Box<dyn ExecutableEvent>>>>> {
+    fn sender(&self) -> Option<EventSender> {
         None
     }
+    fn command(&self) -> Option<&Command> {
+        Some(&self.cmd)
+    }
 }
 
 pub struct WifeMessageCmd {
@@ -90,55 +170,798 @@ impl model::event::Event for WifeMessageCmd {
     }
 }
 impl ExecutableEvent for WifeMessageCmd {
-    fn sender(&self) -> Option<Arc<Sender<Arc<Box<dyn ExecutableEvent>>>>> {
+    fn sender(&self) -> Option<EventSender> {
+        None
+    }
+    fn command(&self) -> Option<&Command> {
+        None
+    }
+}
+
+// HeartbeatCmd tells the admin chat that the app is alive once in HEARTBEAT_INTERVAL_SEC, it is
+// executed by the event loop, so a stuck loop stops the heartbeat as well.
+pub struct HeartbeatCmd {
+    cfg: CfgHandle,
+    health: Arc<Health>,
+    last: Mutex<Instant>,
+}
+impl HeartbeatCmd {
+    pub fn new(cfg: CfgHandle, health: Arc<Health>) -> HeartbeatCmd {
+        HeartbeatCmd { cfg, health, last: Mutex::new(Instant::now()) }
+    }
+}
+impl Executable for HeartbeatCmd {
+    fn exec(&self) -> Exit {
+        *self.last.lock().unwrap() = Instant::now();
+
+        let components = self
+            .health
+            .components()
+            .iter()
+            .map(|(name, component)| {
+                format!(
+                    "{}: {}, restarts {}, last activity {}",
+                    name,
+                    if component.is_alive { "alive" } else { "down" },
+                    component.restarts,
+                    component.last_activity.map(|at| at.format("%Y-%m-%d %H:%M:%S").to_string()).unwrap_or("never".to_string()),
+                )
+            })
+            .collect::<Vec<String>>()
+            .join("\n");
+
+        Exit::new(
+            ExitCode::Success,
+            format!("I'm alive, up since {}.\n{}", self.health.started_at().format("%Y-%m-%d %H:%M:%S"), components),
+            "".to_string(),
+            None,
+        )
+    }
+}
+impl model::event::Event for HeartbeatCmd {
+    fn name(&self) -> String {
+        "Heartbeat".to_string()
+    }
+    fn is_ready(&self) -> bool {
+        // the interval is read each time, so a reloaded one applies at once
+        let interval = self.cfg.get().heartbeat_interval;
+        !interval.is_zero() && self.last.lock().unwrap().elapsed() >= interval
+    }
+    fn repeats(&self) -> Repeat {
+        Repeat::Always
+    }
+    fn delay(&self) -> Duration {
+        let interval = self.cfg.get().heartbeat_interval;
+        if interval.is_zero() {
+            // checked once in a while, so an enabled one by a reload is picked up
+            return HEARTBEAT_RECHECK_INTERVAL;
+        }
+        interval.saturating_sub(self.last.lock().unwrap().elapsed())
+    }
+    fn retry_policy(&self) -> RetryPolicy {
+        // the next heartbeat tells the same
+        RetryPolicy::none()
+    }
+}
+impl ExecutableEvent for HeartbeatCmd {
+    fn sender(&self) -> Option<EventSender> {
+        None
+    }
+    fn command(&self) -> Option<&Command> {
         None
     }
 }
 
 pub struct ExecCmd {
     cmd: Command,
+    mode: ExecMode,
+    builder: Arc<ProcessBuilder>,
+    transport: Arc<Box<dyn Transport>>,
 }
 impl ExecCmd {
-    pub fn new(cmd: Command) -> ExecCmd {
-        ExecCmd { cmd }
+    pub fn new(cmd: Command, mode: ExecMode, builder: Arc<ProcessBuilder>, transport: Arc<Box<dyn Transport>>) -> ExecCmd {
+        ExecCmd { cmd, mode, builder, transport }
     }
 }
 impl Executable for ExecCmd {
     fn exec(&self) -> Exit {
-        let cmd_parts: &mut Vec<String> =
-            &mut split(self.cmd.str.as_str()).expect("Failed to split str command.");
+        let msg = Some(self.cmd.message.clone());
+
+        let (options, input) = match ExecOptions::parse(self.cmd.str.as_str()) {
+            Ok(parsed) => parsed,
+            Err(error) => return Exit::new(ExitCode::Failed, "".to_string(), error, msg),
+        };
+        let argv = match self.builder.argv(self.mode, input.as_str()) {
+            Ok(argv) => argv,
+            Err(error) => return Exit::new(ExitCode::Failed, "".to_string(), error, msg),
+        };
+        let exit = self.run(&argv, &options);
+
+        Exit { argv: Some(argv), ..exit }
+    }
+}
+impl ExecCmd {
+    // run spawns the process of argv and captures its output
+    fn run(&self, argv: &[String], options: &ExecOptions) -> Exit {
+        let msg = Some(self.cmd.message.clone());
+
+        let limits = self.builder.limits(self.cmd.message.user.id);
+        let process = match self.builder.build(argv, options, &limits, self.cmd.message.user.id) {
+            Ok(process) => process,
+            Err(error) => return Exit::new(build_error_code(error.as_ref()), "".to_string(), error.to_string(), msg),
+        };
+        let stdin = match read_stdin(&self.cmd.stdin, self.transport.as_ref().as_ref(), self.builder.max_stdin_bytes()) {
+            Ok(stdin) => stdin,
+            Err(error) => return Exit::new(ExitCode::Failed, "".to_string(), error.to_string(), msg),
+        };
+
+        let timeout = self.builder.timeout();
+        match capture(process, limits.output(), stdin, timeout, self.builder.tracker()) {
+            Ok(captured) => {
+                let mut attachments = vec![];
+                let stdout = output_text("stdout", captured.stdout, &mut attachments);
+                let mut stderr = output_text("stderr", captured.stderr, &mut attachments);
+
+                let code = if captured.is_truncated {
+                    stderr.push_str(
+                        format!(
+                            "\nThe output has exceeded the limit of {} bytes, the command has been killed.",
+                            limits.output().unwrap_or_default(),
+                        )
+                            .as_str(),
+                    );
+                    ExitCode::LimitExceeded(Limit::Output)
+                } else if captured.status.signal() == Some(libc::SIGXCPU) {
+                    stderr.push_str(
+                        format!(
+                            "\nThe command has exceeded the CPU time limit of {} seconds and has been killed.",
+                            limits.cpu_seconds.unwrap_or_default(),
+                        )
+                            .as_str(),
+                    );
+                    ExitCode::LimitExceeded(Limit::Cpu)
+                } else if captured.is_timed_out {
+                    stderr.push_str(
+                        format!(
+                            "\nThe command has not finished in {} seconds and has been killed.",
+                            timeout.unwrap_or_default().as_secs(),
+                        )
+                            .as_str(),
+                    );
+                    ExitCode::Timeout
+                } else {
+                    if !captured.status.success() {
+                        if let Some(note) = limit_note(&limits, None, stderr.as_str()) {
+                            stderr.push_str(note.as_str());
+                        }
+                    }
+                    exit_code(captured.status)
+                };
+
+                Exit { attachments, ..Exit::new(code, stdout, stderr, msg) }
+            }
+            Err(error) => match limits.suspect(error.raw_os_error(), "") {
+                // the limits are set right before exec, so e.g. a too small address space fails it
+                Some((limit, _)) => {
+                    let note = limit_note(&limits, error.raw_os_error(), "").unwrap_or_default();
+                    Exit::new(ExitCode::LimitExceeded(limit), "".to_string(), format!("{}{}", error, note), msg)
+                }
+                None => Exit::new(spawn_error_code(&error), "".to_string(), error.to_string(), msg),
+            },
+        }
+    }
+}
+impl model::event::Event for ExecCmd {
+    fn name(&self) -> String {
+        self.cmd.str.clone()
+    }
+    fn is_ready(&self) -> bool {
+        true
+    }
+    fn repeats(&self) -> Repeat {
+        Repeat::Once
+    }
+}
+impl ExecutableEvent for ExecCmd {
+    fn sender(&self) -> Option<EventSender> {
+        None
+    }
+    fn command(&self) -> Option<&Command> {
+        Some(&self.cmd)
+    }
+}
+
+// EveryCmd is an exec command repeated by the schedule, the loop accounts the runs.
+pub struct EveryCmd {
+    exec: ExecCmd,
+    schedule: Schedule,
+}
+impl EveryCmd {
+    pub fn new(exec: ExecCmd, schedule: Schedule) -> EveryCmd {
+        EveryCmd { exec, schedule }
+    }
+}
+impl Executable for EveryCmd {
+    fn exec(&self) -> Exit {
+        self.exec.exec()
+    }
+}
+impl model::event::Event for EveryCmd {
+    fn name(&self) -> String {
+        self.exec.name()
+    }
+    fn is_ready(&self) -> bool {
+        true
+    }
+    fn repeats(&self) -> Repeat {
+        Repeat::Every(self.schedule.interval, self.schedule.times)
+    }
+}
+impl ExecutableEvent for EveryCmd {
+    fn sender(&self) -> Option<EventSender> {
+        None
+    }
+    fn command(&self) -> Option<&Command> {
+        self.exec.command()
+    }
+}
+
+pub struct SessionCmd {
+    cmd: Command,
+    sessions: Arc<Box<dyn SessionManagerTrait>>,
+}
+impl SessionCmd {
+    pub fn new(cmd: Command, sessions: Arc<Box<dyn SessionManagerTrait>>) -> SessionCmd {
+        SessionCmd { cmd, sessions }
+    }
+}
+impl Executable for SessionCmd {
+    fn exec(&self) -> Exit {
+        let msg = Some(self.cmd.message.clone());
+        let chat_id = self.cmd.message.chat_id;
 
-        if cmd_parts.len() == 0 {
+        let (action, rest) = self.cmd.str.trim().split_once(' ').unwrap_or((self.cmd.str.trim(), ""));
+        let result = match action {
+            "start" => match ExecOptions::parse(rest) {
+                Ok((options, rest)) if rest.is_empty() => self
+                    .sessions
+                    .start(chat_id, self.cmd.message.user.id, &options)
+                    .map(|_| "Session has been started.".to_string()),
+                Ok((_, rest)) => Err(format!("Unknown session option `{}`, use cwd=, as= or env.", rest).into()),
+                Err(error) => Err(error.into()),
+            },
+            "stop" => self.sessions.stop(chat_id).map(|_| "Session has been stopped.".to_string()),
+            "status" | "" => self.sessions.status(chat_id),
+            other => Err(format!("Unknown session action `{}`, use one of: start, stop, status.", other).into()),
+        };
+
+        match result {
+            Ok(stdout) => Exit::new(ExitCode::Success, stdout, "".to_string(), msg),
+            Err(error) => Exit::new(build_error_code(error.as_ref()), "".to_string(), error.to_string(), msg),
+        }
+    }
+}
+impl model::event::Event for SessionCmd {
+    fn name(&self) -> String {
+        self.cmd.str.clone()
+    }
+    fn is_ready(&self) -> bool {
+        true
+    }
+    fn repeats(&self) -> Repeat {
+        Repeat::Once
+    }
+}
+impl ExecutableEvent for SessionCmd {
+    fn sender(&self) -> Option<EventSender> {
+        None
+    }
+    fn command(&self) -> Option<&Command> {
+        Some(&self.cmd)
+    }
+}
+
+// SessionExecCmd runs /cmd and /sh inside the chat shell session instead of a new process.
+pub struct SessionExecCmd {
+    cmd: Command,
+    sessions: Arc<Box<dyn SessionManagerTrait>>,
+}
+impl SessionExecCmd {
+    pub fn new(cmd: Command, sessions: Arc<Box<dyn SessionManagerTrait>>) -> SessionExecCmd {
+        SessionExecCmd { cmd, sessions }
+    }
+}
+impl Executable for SessionExecCmd {
+    fn exec(&self) -> Exit {
+        let msg = Some(self.cmd.message.clone());
+
+        if self.cmd.str.trim().is_empty() {
             return Exit::new(
                 ExitCode::Failed,
                 "".to_string(),
                 "The command is empty, please check the send data and try again.".to_string(),
-                None,
+                msg,
+            );
+        }
+
+        match self.sessions.run(self.cmd.message.chat_id, self.cmd.str.as_str()) {
+            Ok(result) if result.is_truncated => Exit::new(
+                ExitCode::LimitExceeded(Limit::Output),
+                strip_ansi(result.output.as_str()),
+                "The output has exceeded the limit and has been cut, the session is kept.".to_string(),
+                msg,
+            ),
+            Ok(result) => Exit::new(ExitCode::Other(result.code), strip_ansi(result.output.as_str()), "".to_string(), msg),
+            Err(error) => match error.downcast_ref::<SessionTimeoutError>() {
+                Some(timeout) => Exit::new(ExitCode::Timeout, strip_ansi(timeout.output()), error.to_string(), msg),
+                None => Exit::new(ExitCode::Failed, "".to_string(), error.to_string(), msg),
+            },
+        }
+    }
+}
+impl model::event::Event for SessionExecCmd {
+    fn name(&self) -> String {
+        self.cmd.str.clone()
+    }
+    fn is_ready(&self) -> bool {
+        true
+    }
+    fn repeats(&self) -> Repeat {
+        Repeat::Once
+    }
+}
+impl ExecutableEvent for SessionExecCmd {
+    // the command is run by the worker of the session, a slow one does not hold the loop
+    fn sender(&self) -> Option<EventSender> {
+        self.sessions.sender(self.cmd.message.chat_id)
+    }
+    fn command(&self) -> Option<&Command> {
+        Some(&self.cmd)
+    }
+}
+
+// SessionResultCmd delivers the result of a command which has been run by the session worker.
+pub struct SessionResultCmd {
+    cmd: Command,
+    exit: Exit,
+}
+impl SessionResultCmd {
+    pub fn new(cmd: Command, exit: Exit) -> SessionResultCmd {
+        SessionResultCmd { cmd, exit }
+    }
+}
+impl Executable for SessionResultCmd {
+    fn exec(&self) -> Exit {
+        self.exit.clone()
+    }
+}
+impl model::event::Event for SessionResultCmd {
+    fn name(&self) -> String {
+        self.cmd.str.clone()
+    }
+    fn is_ready(&self) -> bool {
+        true
+    }
+    fn repeats(&self) -> Repeat {
+        Repeat::Once
+    }
+}
+impl ExecutableEvent for SessionResultCmd {
+    fn sender(&self) -> Option<EventSender> {
+        None
+    }
+    fn command(&self) -> Option<&Command> {
+        Some(&self.cmd)
+    }
+}
+
+// BgCmd starts the command as a background job, its output is kept by the job registry.
+pub struct BgCmd {
+    cmd: Command,
+    mode: ExecMode,
+    builder: Arc<ProcessBuilder>,
+    transport: Arc<Box<dyn Transport>>,
+    jobs: Arc<Box<dyn JobRegistryTrait>>,
+}
+impl BgCmd {
+    pub fn new(
+        cmd: Command,
+        mode: ExecMode,
+        builder: Arc<ProcessBuilder>,
+        transport: Arc<Box<dyn Transport>>,
+        jobs: Arc<Box<dyn JobRegistryTrait>>,
+    ) -> BgCmd {
+        BgCmd { cmd, mode, builder, transport, jobs }
+    }
+}
+impl Executable for BgCmd {
+    fn exec(&self) -> Exit {
+        let msg = Some(self.cmd.message.clone());
+
+        let (options, input) = match ExecOptions::parse(self.cmd.str.as_str()) {
+            Ok(parsed) => parsed,
+            Err(error) => return Exit::new(ExitCode::Failed, "".to_string(), error, msg),
+        };
+        let argv = match self.builder.argv(self.mode, input.as_str()) {
+            Ok(argv) => argv,
+            Err(error) => return Exit::new(ExitCode::Failed, "".to_string(), error, msg),
+        };
+        let exit = self.run(&argv, &options, input.trim());
+
+        Exit { argv: Some(argv), ..exit }
+    }
+}
+impl BgCmd {
+    // run spawns the process of argv as a job
+    fn run(&self, argv: &[String], options: &ExecOptions, input: &str) -> Exit {
+        let msg = Some(self.cmd.message.clone());
+
+        let limits = self.builder.limits(self.cmd.message.user.id);
+        let process = match self.builder.build(argv, options, &limits, self.cmd.message.user.id) {
+            Ok(process) => process,
+            Err(error) => return Exit::new(build_error_code(error.as_ref()), "".to_string(), error.to_string(), msg),
+        };
+        let stdin = match read_stdin(&self.cmd.stdin, self.transport.as_ref().as_ref(), self.builder.max_stdin_bytes()) {
+            Ok(stdin) => stdin,
+            Err(error) => return Exit::new(ExitCode::Failed, "".to_string(), error.to_string(), msg),
+        };
+
+        match self.jobs.start(process, input.to_string(), self.cmd.message.clone(), stdin) {
+            Ok(job) => Exit::new(
+                ExitCode::Success,
+                format!("Job [{}] has been started, pid {}.", job.id, job.pid),
+                "".to_string(),
+                msg,
+            ),
+            Err(error) => {
+                let code = error.downcast_ref::<io::Error>().map(spawn_error_code).unwrap_or(ExitCode::Failed);
+                Exit::new(code, "".to_string(), error.to_string(), msg)
+            }
+        }
+    }
+}
+impl model::event::Event for BgCmd {
+    fn name(&self) -> String {
+        self.cmd.str.clone()
+    }
+    fn is_ready(&self) -> bool {
+        true
+    }
+    fn repeats(&self) -> Repeat {
+        Repeat::Once
+    }
+}
+impl ExecutableEvent for BgCmd {
+    fn sender(&self) -> Option<EventSender> {
+        None
+    }
+    fn command(&self) -> Option<&Command> {
+        Some(&self.cmd)
+    }
+}
+
+pub struct JobsCmd {
+    cmd: Command,
+    jobs: Arc<Box<dyn JobRegistryTrait>>,
+}
+impl JobsCmd {
+    pub fn new(cmd: Command, jobs: Arc<Box<dyn JobRegistryTrait>>) -> JobsCmd {
+        JobsCmd { cmd, jobs }
+    }
+}
+impl Executable for JobsCmd {
+    fn exec(&self) -> Exit {
+        let jobs = self.jobs.list();
+        let stdout = if jobs.is_empty() {
+            "There are no jobs.".to_string()
+        } else {
+            jobs.iter()
+                .map(|job| {
+                    format!(
+                        "[{}] pid {}, {}, started at {}, up {}s: {}",
+                        job.id,
+                        job.pid,
+                        job.state(),
+                        job.started_at.format("%Y-%m-%dT%H:%M:%S"),
+                        job.uptime().as_secs(),
+                        job.command,
+                    )
+                })
+                .collect::<Vec<String>>()
+                .join("\n")
+        };
+
+        Exit::new(ExitCode::Success, stdout, "".to_string(), Some(self.cmd.message.clone()))
+    }
+}
+impl model::event::Event for JobsCmd {
+    fn name(&self) -> String {
+        self.cmd.str.clone()
+    }
+    fn is_ready(&self) -> bool {
+        true
+    }
+    fn repeats(&self) -> Repeat {
+        Repeat::Once
+    }
+}
+impl ExecutableEvent for JobsCmd {
+    fn sender(&self) -> Option<EventSender> {
+        None
+    }
+    fn command(&self) -> Option<&Command> {
+        Some(&self.cmd)
+    }
+}
+
+// TailCmd shows the recent output of a job (/tail and /fg).
+pub struct TailCmd {
+    cmd: Command,
+    jobs: Arc<Box<dyn JobRegistryTrait>>,
+}
+impl TailCmd {
+    pub fn new(cmd: Command, jobs: Arc<Box<dyn JobRegistryTrait>>) -> TailCmd {
+        TailCmd { cmd, jobs }
+    }
+}
+impl Executable for TailCmd {
+    fn exec(&self) -> Exit {
+        let msg = Some(self.cmd.message.clone());
+
+        match self.jobs.get(self.cmd.str.as_str()) {
+            Ok(job) => {
+                let (output, dropped) = job.output();
+                let mut attachments = vec![];
+                let stdout = output_text(format!("job-{}", job.id).as_str(), output, &mut attachments);
+                let mut stderr = format!("Job [{}] pid {}, {}, up {}s.", job.id, job.pid, job.state(), job.uptime().as_secs());
+                if dropped > 0 {
+                    stderr.push_str(format!(" The first {} bytes of the output have been dropped.", dropped).as_str());
+                }
+                Exit { attachments, ..Exit::new(ExitCode::Success, stdout, stderr, msg) }
+            }
+            Err(error) => Exit::new(ExitCode::Failed, "".to_string(), error.to_string(), msg),
+        }
+    }
+}
+impl model::event::Event for TailCmd {
+    fn name(&self) -> String {
+        self.cmd.str.clone()
+    }
+    fn is_ready(&self) -> bool {
+        true
+    }
+    fn repeats(&self) -> Repeat {
+        Repeat::Once
+    }
+}
+impl ExecutableEvent for TailCmd {
+    fn sender(&self) -> Option<EventSender> {
+        None
+    }
+    fn command(&self) -> Option<&Command> {
+        Some(&self.cmd)
+    }
+}
+
+// KillCmd sends a signal (TERM by default) to a job: `/kill <id> [signal]`.
+pub struct KillCmd {
+    cmd: Command,
+    jobs: Arc<Box<dyn JobRegistryTrait>>,
+}
+impl KillCmd {
+    pub fn new(cmd: Command, jobs: Arc<Box<dyn JobRegistryTrait>>) -> KillCmd {
+        KillCmd { cmd, jobs }
+    }
+}
+impl Executable for KillCmd {
+    fn exec(&self) -> Exit {
+        let msg = Some(self.cmd.message.clone());
+
+        let mut args = self.cmd.str.split_whitespace();
+        let id = args.next().unwrap_or_default();
+        let signal = match args.next() {
+            Some(signal) => match parse_signal(signal) {
+                Some(signal) => signal,
+                None => {
+                    let error = UnknownSignalError::new(signal.to_string());
+                    return Exit::new(ExitCode::Failed, "".to_string(), error.to_string(), msg);
+                }
+            },
+            None => libc::SIGTERM,
+        };
+
+        match self.jobs.kill(id, signal) {
+            Ok(job) => Exit::new(
+                ExitCode::Success,
+                format!(
+                    "Signal {} has been sent to job [{}].",
+                    signal_name(signal).unwrap_or(signal.to_string()),
+                    job.id,
+                ),
+                "".to_string(),
+                msg,
+            ),
+            Err(error) => Exit::new(ExitCode::Failed, "".to_string(), error.to_string(), msg),
+        }
+    }
+}
+impl model::event::Event for KillCmd {
+    fn name(&self) -> String {
+        self.cmd.str.clone()
+    }
+    fn is_ready(&self) -> bool {
+        true
+    }
+    fn repeats(&self) -> Repeat {
+        Repeat::Once
+    }
+}
+impl ExecutableEvent for KillCmd {
+    fn sender(&self) -> Option<EventSender> {
+        None
+    }
+    fn command(&self) -> Option<&Command> {
+        Some(&self.cmd)
+    }
+}
+
+// JobFinishedCmd notifies the chat that a background job has exited.
+pub struct JobFinishedCmd {
+    job: Arc<Job>,
+}
+impl JobFinishedCmd {
+    pub fn new(job: Arc<Job>) -> JobFinishedCmd {
+        JobFinishedCmd { job }
+    }
+}
+impl Executable for JobFinishedCmd {
+    fn exec(&self) -> Exit {
+        let state = self.job.state();
+        let code = match state {
+            JobState::Exited(0) => ExitCode::Success,
+            JobState::Exited(code) => ExitCode::Other(code),
+            JobState::Signaled(signal) => ExitCode::Signal { signal, core_dumped: false },
+            JobState::Running => ExitCode::Failed,
+        };
+
+        Exit::new(
+            code,
+            format!(
+                "Job [{}] `{}` has finished ({}) in {}s, see its output by /tail {}.",
+                self.job.id,
+                self.job.command,
+                state,
+                self.job.uptime().as_secs(),
+                self.job.id,
+            ),
+            "".to_string(),
+            Some(self.job.message.clone()),
+        )
+    }
+}
+impl model::event::Event for JobFinishedCmd {
+    fn name(&self) -> String {
+        format!("job [{}] finished", self.job.id)
+    }
+    fn is_ready(&self) -> bool {
+        true
+    }
+    fn repeats(&self) -> Repeat {
+        Repeat::Once
+    }
+}
+impl ExecutableEvent for JobFinishedCmd {
+    fn sender(&self) -> Option<EventSender> {
+        None
+    }
+    fn command(&self) -> Option<&Command> {
+        None
+    }
+}
+
+// ShutdownCmd closes the application, the in-flight commands are given time to finish.
+pub struct ShutdownCmd {
+    cmd: Command,
+    state: Arc<Box<dyn State>>,
+    is_authorized: bool,
+}
+impl ShutdownCmd {
+    pub fn new(cmd: Command, state: Arc<Box<dyn State>>, is_authorized: bool) -> ShutdownCmd {
+        ShutdownCmd { cmd, state, is_authorized }
+    }
+}
+impl Executable for ShutdownCmd {
+    fn exec(&self) -> Exit {
+        let msg = Some(self.cmd.message.clone());
+
+        if !self.is_authorized {
+            return Exit::new(
+                ExitCode::Denied,
+                "".to_string(),
+                "You are not allowed to shut the service down.".to_string(),
+                msg,
             );
         }
 
-        let cmd_name = cmd_parts.remove(0);
-        let output = OsCmd::new(cmd_name) // make a new process
-            .args(cmd_parts) // passing args. into the command
-            .output(); // run process;
-
-        match output {
-            Ok(output) => Exit::new(
-                ExitCode::Other(output.status.code().unwrap()),
-                String::from_utf8(output.stdout).unwrap(),
-                String::from_utf8(output.stderr).unwrap(),
-                Some(self.cmd.message.clone()),
+        log::warn!(
+            target: "commands",
+            chat_id = self.cmd.message.chat_id,
+            message_id = self.cmd.message.id,
+            user_id = self.cmd.message.user.id;
+            "Shutdown has been requested."
+        );
+        self.state.close();
+
+        Exit::new(ExitCode::Success, "Shutting down...".to_string(), "".to_string(), msg)
+    }
+}
+impl model::event::Event for ShutdownCmd {
+    fn name(&self) -> String {
+        self.cmd.str.clone()
+    }
+    fn is_ready(&self) -> bool {
+        true
+    }
+    fn repeats(&self) -> Repeat {
+        Repeat::Once
+    }
+}
+impl ExecutableEvent for ShutdownCmd {
+    fn sender(&self) -> Option<EventSender> {
+        None
+    }
+    fn command(&self) -> Option<&Command> {
+        Some(&self.cmd)
+    }
+}
+
+// ConfigCmd shows the current config with the secrets redacted: `/config show`.
+pub struct ConfigCmd {
+    cmd: Command,
+    cfg: CfgHandle,
+    is_authorized: bool,
+}
+impl ConfigCmd {
+    pub fn new(cmd: Command, cfg: CfgHandle, is_authorized: bool) -> ConfigCmd {
+        ConfigCmd { cmd, cfg, is_authorized }
+    }
+}
+impl Executable for ConfigCmd {
+    fn exec(&self) -> Exit {
+        let msg = Some(self.cmd.message.clone());
+
+        if !self.is_authorized {
+            return Exit::new(
+                ExitCode::Denied,
+                "".to_string(),
+                "You are not allowed to see the config.".to_string(),
+                msg,
+            );
+        }
+
+        match self.cmd.str.trim() {
+            "show" | "" => Exit::new(
+                ExitCode::Success,
+                self.cfg
+                    .get()
+                    .vars()
+                    .iter()
+                    .map(|(name, value)| format!("{}={}", name, value))
+                    .collect::<Vec<String>>()
+                    .join("\n"),
+                "".to_string(),
+                msg,
             ),
-            Err(error) => Exit::new(
+            other => Exit::new(
                 ExitCode::Failed,
                 "".to_string(),
-                error.to_string(),
-                Some(self.cmd.message.clone()),
+                format!("Unknown config action `{}`, use: show.", other),
+                msg,
             ),
         }
     }
 }
-impl model::event::Event for ExecCmd {
+impl model::event::Event for ConfigCmd {
     fn name(&self) -> String {
         self.cmd.str.clone()
     }
@@ -149,10 +972,183 @@ impl model::event::Event for ExecCmd {
         Repeat::Once
     }
 }
-impl ExecutableEvent for ExecCmd {
-    fn sender(&self) -> Option<Arc<Sender<Arc<Box<dyn ExecutableEvent>>>>> {
+impl ExecutableEvent for ConfigCmd {
+    fn sender(&self) -> Option<EventSender> {
         None
     }
+    fn command(&self) -> Option<&Command> {
+        Some(&self.cmd)
+    }
+}
+
+// AuditCmd shows the recent entries of the audit log or verifies its hash chain:
+// `/audit [n]`, `/audit verify`.
+pub struct AuditCmd {
+    cmd: Command,
+    audit: Arc<Box<dyn AuditLogTrait>>,
+    is_authorized: bool,
+}
+impl AuditCmd {
+    pub fn new(cmd: Command, audit: Arc<Box<dyn AuditLogTrait>>, is_authorized: bool) -> AuditCmd {
+        AuditCmd { cmd, audit, is_authorized }
+    }
+}
+impl Executable for AuditCmd {
+    fn exec(&self) -> Exit {
+        let msg = Some(self.cmd.message.clone());
+
+        if !self.is_authorized {
+            return Exit::new(
+                ExitCode::Denied,
+                "".to_string(),
+                "You are not allowed to see the audit log.".to_string(),
+                msg,
+            );
+        }
+
+        match self.cmd.str.trim() {
+            "verify" => match self.audit.verify() {
+                Ok(n) => Exit::new(
+                    ExitCode::Success,
+                    format!("The audit log is intact, {} entries have been checked.", n),
+                    "".to_string(),
+                    msg,
+                ),
+                Err(error) => Exit::new(ExitCode::Failed, "".to_string(), error.to_string(), msg),
+            },
+            arg => {
+                let n = match arg {
+                    "" => AUDIT_DEFAULT_ENTRIES,
+                    arg => match arg.parse::<usize>() {
+                        Ok(n) if n > 0 => n.min(AUDIT_MAX_ENTRIES),
+                        _ => {
+                            return Exit::new(
+                                ExitCode::Failed,
+                                "".to_string(),
+                                format!("Unknown audit action `{}`, use: [n] or verify.", arg),
+                                msg,
+                            )
+                        }
+                    },
+                };
+                match self.audit.recent(n) {
+                    Ok(entries) if entries.is_empty() => {
+                        Exit::new(ExitCode::Success, "The audit log is empty.".to_string(), "".to_string(), msg)
+                    }
+                    Ok(entries) => Exit::new(
+                        ExitCode::Success,
+                        entries.iter().map(|entry| entry.to_string()).collect::<Vec<String>>().join("\n"),
+                        "".to_string(),
+                        msg,
+                    ),
+                    Err(error) => Exit::new(ExitCode::Failed, "".to_string(), error.to_string(), msg),
+                }
+            }
+        }
+    }
+}
+impl model::event::Event for AuditCmd {
+    fn name(&self) -> String {
+        self.cmd.str.clone()
+    }
+    fn is_ready(&self) -> bool {
+        true
+    }
+    fn repeats(&self) -> Repeat {
+        Repeat::Once
+    }
+}
+impl ExecutableEvent for AuditCmd {
+    fn sender(&self) -> Option<EventSender> {
+        None
+    }
+    fn command(&self) -> Option<&Command> {
+        Some(&self.cmd)
+    }
+}
+
+// DlqCmd shows the dead-lettered events, replays or drops them:
+// `/dlq [list]`, `/dlq replay <id|all>`, `/dlq drop <id|all>`.
+pub struct DlqCmd {
+    cmd: Command,
+    event_loop: Arc<Box<dyn EventLoop>>,
+    is_authorized: bool,
+}
+impl DlqCmd {
+    pub fn new(cmd: Command, event_loop: Arc<Box<dyn EventLoop>>, is_authorized: bool) -> DlqCmd {
+        DlqCmd { cmd, event_loop, is_authorized }
+    }
+}
+impl Executable for DlqCmd {
+    fn exec(&self) -> Exit {
+        let msg = Some(self.cmd.message.clone());
+
+        if !self.is_authorized {
+            return Exit::new(
+                ExitCode::Denied,
+                "".to_string(),
+                "You are not allowed to manage the dead letters.".to_string(),
+                msg,
+            );
+        }
+
+        let args: Vec<&str> = self.cmd.str.split_whitespace().collect();
+        let id = |arg: &str| -> Result<Option<u64>, DeadLetterNotFoundError> {
+            match arg {
+                "all" => Ok(None),
+                arg => arg.trim_matches(['[', ']']).parse::<u64>().map(Some).map_err(|_| DeadLetterNotFoundError::new(arg.to_string())),
+            }
+        };
+
+        let result = match args.as_slice() {
+            [] | ["list"] => {
+                let letters = self.event_loop.dead_letters();
+                if letters.is_empty() {
+                    Ok("There are no dead letters.".to_string())
+                } else {
+                    Ok(letters.iter().map(|letter| letter.to_string()).collect::<Vec<String>>().join("\n"))
+                }
+            }
+            ["replay", arg] => id(arg)
+                .and_then(|id| self.event_loop.replay(id))
+                .map(|n| format!("{} dead letter(s) have been replayed.", n)),
+            ["drop", arg] => id(arg)
+                .and_then(|id| self.event_loop.discard(id))
+                .map(|n| format!("{} dead letter(s) have been dropped.", n)),
+            _ => {
+                return Exit::new(
+                    ExitCode::Failed,
+                    "".to_string(),
+                    format!("Unknown dlq action `{}`, use: list, replay <id|all> or drop <id|all>.", self.cmd.str.trim()),
+                    msg,
+                )
+            }
+        };
+
+        match result {
+            Ok(stdout) => Exit::new(ExitCode::Success, stdout, "".to_string(), msg),
+            Err(error) => Exit::new(ExitCode::Failed, "".to_string(), error.to_string(), msg),
+        }
+    }
+}
+impl model::event::Event for DlqCmd {
+    fn name(&self) -> String {
+        self.cmd.str.clone()
+    }
+    fn is_ready(&self) -> bool {
+        true
+    }
+    fn repeats(&self) -> Repeat {
+        Repeat::Once
+    }
+}
+impl ExecutableEvent for DlqCmd {
+    fn sender(&self) -> Option<EventSender> {
+        None
+    }
+    fn command(&self) -> Option<&Command> {
+        Some(&self.cmd)
+    }
 }
 
 pub struct NoteCmd {
@@ -201,9 +1197,12 @@ impl model::event::Event for NoteCmd {
     }
 }
 impl ExecutableEvent for NoteCmd {
-    fn sender(&self) -> Option<Arc<Sender<Arc<Box<dyn ExecutableEvent>>>>> {
+    fn sender(&self) -> Option<EventSender> {
         None
     }
+    fn command(&self) -> Option<&Command> {
+        Some(&self.cmd)
+    }
 }
 
 pub struct EventCmd {
@@ -243,6 +1242,11 @@ impl Executable for EventCmd {
         let msg = Some(self.cmd.message.clone());
 
         if self.cmd.str != String::new() {
+            // the date was not parsed on creation, so respond with the error at once
+            if let Some(exit) = &self.exit {
+                return exit.clone();
+            }
+
             let datetime = match parse_yyyy_mm_dd_hm_from_str(self.cmd.str.clone().as_str()) {
                 Ok(datetime) => datetime,
                 Err(err) => return Exit::new(ExitCode::Failed, "".to_string(), err.to_string(), msg),
@@ -328,11 +1332,52 @@ impl model::event::Event for EventCmd {
     fn repeats(&self) -> Repeat {
         Repeat::Once
     }
+    fn delay(&self) -> Duration {
+        (self.date - Local::now().naive_local()).to_std().unwrap_or(Duration::ZERO)
+    }
 }
 impl ExecutableEvent for EventCmd {
-    fn sender(&self) -> Option<Arc<Sender<Arc<Box<dyn ExecutableEvent>>>>> {
+    fn sender(&self) -> Option<EventSender> {
         None
     }
+    fn command(&self) -> Option<&Command> {
+        Some(&self.cmd)
+    }
+}
+
+// FailedCmd responds with an error which has occurred while the command was being made.
+pub struct FailedCmd {
+    cmd: Command,
+    error: String,
+}
+impl FailedCmd {
+    pub fn new(cmd: Command, error: String) -> FailedCmd {
+        FailedCmd { cmd, error }
+    }
+}
+impl Executable for FailedCmd {
+    fn exec(&self) -> Exit {
+        Exit::new(ExitCode::Failed, "".to_string(), self.error.clone(), Some(self.cmd.message.clone()))
+    }
+}
+impl model::event::Event for FailedCmd {
+    fn name(&self) -> String {
+        self.cmd.str.clone()
+    }
+    fn is_ready(&self) -> bool {
+        true
+    }
+    fn repeats(&self) -> Repeat {
+        Repeat::Once
+    }
+}
+impl ExecutableEvent for FailedCmd {
+    fn sender(&self) -> Option<EventSender> {
+        None
+    }
+    fn command(&self) -> Option<&Command> {
+        Some(&self.cmd)
+    }
 }
 
 pub struct NotFoundCmd {
@@ -365,9 +1410,12 @@ impl model::event::Event for NotFoundCmd {
     }
 }
 impl ExecutableEvent for NotFoundCmd {
-    fn sender(&self) -> Option<Arc<Sender<Arc<Box<dyn ExecutableEvent>>>>> {
+    fn sender(&self) -> Option<EventSender> {
         None
     }
+    fn command(&self) -> Option<&Command> {
+        Some(&self.cmd)
+    }
 }
 
 pub struct Note {
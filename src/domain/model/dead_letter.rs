@@ -0,0 +1,33 @@
+use crate::domain::model::event::ExecutableEvent;
+use crate::infrastructure::model::command::Exit;
+use chrono::{DateTime, Local};
+use std::sync::Arc;
+
+// DeadLetter is an event which has failed all of its attempts, it is kept until it is replayed
+// or dropped by /dlq.
+#[derive(Clone)]
+pub struct DeadLetter {
+    pub id: u64,
+    pub event: Arc<Box<dyn ExecutableEvent>>,
+    // the result of an executed event which has not been delivered, the event is not executed
+    // again on replay then
+    pub undelivered: Option<Exit>,
+    pub error: String,
+    pub attempts: u32,
+    pub failed_at: DateTime<Local>,
+}
+
+impl std::fmt::Display for DeadLetter {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "[{}] `{}` {} at {} after {} attempt(s): {}",
+            self.id,
+            self.event.name(),
+            if self.undelivered.is_some() { "has not been delivered" } else { "has failed" },
+            self.failed_at.format("%Y-%m-%d %H:%M:%S"),
+            self.attempts,
+            self.error,
+        )
+    }
+}
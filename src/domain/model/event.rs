@@ -1,13 +1,25 @@
 use crate::domain::model::command::Executable;
+use crate::domain::model::retry::RetryPolicy;
 use crate::domain::r#enum::event::Repeat;
+use crate::infrastructure::model::command::Command;
 use std::any::Any;
 use std::sync::mpsc::Sender;
 use std::sync::Arc;
+use std::time::Duration;
+
+// How often the loop checks an event which does not tell when it will be ready.
+const RECHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+// EventSender passes an event to the one who executes it instead of the loop.
+pub type EventSender = Arc<Sender<Arc<Box<dyn ExecutableEvent>>>>;
 
 pub trait ExecutableEvent: Executable + Event + Send + Sync {
     // if the sender is None, then the event loop will execute a cmd himself,
     // otherwise will send an event through sender.
-    fn sender(&self) -> Option<Arc<Sender<Arc<Box<dyn ExecutableEvent>>>>>;
+    fn sender(&self) -> Option<EventSender>;
+    // command returns the command of a chat which the event has been made of,
+    // it is None for the events of the app itself (e.g. scheduled ones).
+    fn command(&self) -> Option<&Command>;
 }
 
 pub trait Event: Send + Sync + Any {
@@ -17,4 +29,17 @@ pub trait Event: Send + Sync + Any {
     fn is_ready(&self) -> bool;
     // repeats tells the event loop how many times an event must be executed
     fn repeats(&self) -> Repeat;
+    // delay returns how long it is until the event is ready, the loop sleeps until the nearest
+    // event is due instead of checking all of them
+    fn delay(&self) -> Duration {
+        if self.is_ready() {
+            Duration::ZERO
+        } else {
+            RECHECK_INTERVAL
+        }
+    }
+    // retry_policy tells the event loop how the event is retried if it has failed
+    fn retry_policy(&self) -> RetryPolicy {
+        RetryPolicy::default()
+    }
 }
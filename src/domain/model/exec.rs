@@ -0,0 +1,187 @@
+const CWD_OPTION: &str = "cwd=";
+const USER_OPTION: &str = "as=";
+const ENV_OPTION: &str = "env";
+
+// ExecOptions are per-invocation settings of a spawned process, they are written in front
+// of the command: `/cmd cwd=/tmp env FOO=1 BAR=2 as=nobody ls -la`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ExecOptions {
+    // working directory of the process
+    pub cwd: Option<String>,
+    // variables which are set in addition to the inherited environment
+    pub env: Vec<(String, String)>,
+    // user (name or uid) which the process is run as
+    pub user: Option<String>,
+}
+
+impl ExecOptions {
+    // parse strips the leading options from the input and returns them with the rest of the
+    // input untouched (a shell script must reach the shell exactly as it was written).
+    pub fn parse(input: &str) -> Result<(ExecOptions, String), String> {
+        let mut options = ExecOptions::default();
+        let mut rest = input.trim_start();
+
+        loop {
+            let (token, tail) = Self::next_token(rest);
+            let Some(token) = token else {
+                break;
+            };
+
+            if let Some(cwd) = token.strip_prefix(CWD_OPTION) {
+                Self::set_once(&mut options.cwd, CWD_OPTION, cwd)?;
+                rest = tail;
+            } else if let Some(user) = token.strip_prefix(USER_OPTION) {
+                Self::set_once(&mut options.user, USER_OPTION, user)?;
+                rest = tail;
+            } else if token == ENV_OPTION {
+                // `env` without assignments is a regular command (prints the environment)
+                let mut env_rest = tail;
+                let mut vars = Vec::new();
+                while let (Some(token), tail) = Self::next_token(env_rest) {
+                    // the other options end the list of variables
+                    if token.starts_with(CWD_OPTION) || token.starts_with(USER_OPTION) {
+                        break;
+                    }
+                    match Self::assignment(token)? {
+                        Some(var) => vars.push(var),
+                        None => break,
+                    }
+                    env_rest = tail;
+                }
+                if vars.is_empty() {
+                    break;
+                }
+                options.env.extend(vars);
+                rest = env_rest;
+            } else {
+                break;
+            }
+        }
+
+        Ok((options, rest.to_string()))
+    }
+
+    // next_token returns a whitespace separated token (quotes are kept) and the rest of input
+    fn next_token(input: &str) -> (Option<&str>, &str) {
+        let input = input.trim_start();
+        if input.is_empty() {
+            return (None, input);
+        }
+
+        let mut quote: Option<char> = None;
+        for (i, c) in input.char_indices() {
+            match quote {
+                Some(q) if c == q => quote = None,
+                Some(_) => {}
+                None if c == '\'' || c == '"' => quote = Some(c),
+                None if c.is_whitespace() => return (Some(&input[..i]), input[i..].trim_start()),
+                None => {}
+            }
+        }
+
+        (Some(input), "")
+    }
+
+    // assignment parses a KEY=VALUE token, returns None if the token is not an assignment
+    fn assignment(token: &str) -> Result<Option<(String, String)>, String> {
+        let Some((key, value)) = token.split_once('=') else {
+            return Ok(None);
+        };
+
+        let mut chars = key.chars();
+        let is_identifier = chars.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+            && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if !is_identifier {
+            return Ok(None);
+        }
+
+        Ok(Some((key.to_string(), Self::unquote(value)?)))
+    }
+
+    // an option given twice is most likely a mistake, so it is not silently overridden
+    fn set_once(option: &mut Option<String>, name: &str, value: &str) -> Result<(), String> {
+        if option.is_some() {
+            return Err(format!("The option `{}` is given more than once.", name));
+        }
+        *option = Some(Self::unquote(value)?);
+        Ok(())
+    }
+
+    fn unquote(value: &str) -> Result<String, String> {
+        if value.is_empty() {
+            return Ok(String::new());
+        }
+
+        match shlex::split(value) {
+            Some(parts) if parts.len() == 1 => Ok(parts[0].clone()),
+            _ => Err(format!("Failed to parse the option value `{}`, please check the quotes.", value)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(input: &str) -> (ExecOptions, String) {
+        ExecOptions::parse(input).unwrap()
+    }
+
+    fn vars(vars: &[(&str, &str)]) -> Vec<(String, String)> {
+        vars.iter().map(|(key, value)| (key.to_string(), value.to_string())).collect()
+    }
+
+    #[test]
+    fn the_leading_options_are_stripped_of_the_command() {
+        let (options, rest) = parse("cwd=/tmp env FOO=1 BAR='a b' as=nobody ls -la");
+
+        assert_eq!(options.cwd, Some("/tmp".to_string()));
+        assert_eq!(options.env, vars(&[("FOO", "1"), ("BAR", "a b")]));
+        assert_eq!(options.user, Some("nobody".to_string()));
+        assert_eq!(rest, "ls -la");
+    }
+
+    #[test]
+    fn the_rest_of_the_input_is_kept_as_it_was_written() {
+        let (options, rest) = parse("  cwd=\"/my dir\"   echo  'a   b' | wc -c ");
+
+        assert_eq!(options.cwd, Some("/my dir".to_string()));
+        assert_eq!(rest, "echo  'a   b' | wc -c ");
+    }
+
+    #[test]
+    fn a_command_without_options_is_untouched() {
+        assert_eq!(parse("ls cwd=/tmp"), (ExecOptions::default(), "ls cwd=/tmp".to_string()));
+        assert_eq!(parse(""), (ExecOptions::default(), "".to_string()));
+    }
+
+    #[test]
+    fn env_without_assignments_is_the_command_itself() {
+        assert_eq!(parse("env"), (ExecOptions::default(), "env".to_string()));
+        assert_eq!(parse("env | grep PATH"), (ExecOptions::default(), "env | grep PATH".to_string()));
+        // not an identifier, so it is an argument of the env command
+        assert_eq!(parse("env 1A=2 ls"), (ExecOptions::default(), "env 1A=2 ls".to_string()));
+    }
+
+    #[test]
+    fn the_variables_end_by_the_command_or_another_option() {
+        let (options, rest) = parse("env A=1 B= cwd=/ env C=3 printenv A");
+
+        assert_eq!(options.env, vars(&[("A", "1"), ("B", ""), ("C", "3")]));
+        assert_eq!(options.cwd, Some("/".to_string()));
+        assert_eq!(rest, "printenv A");
+    }
+
+    #[test]
+    fn an_option_given_twice_is_rejected() {
+        assert!(ExecOptions::parse("cwd=/tmp cwd=/var ls").is_err());
+        assert!(ExecOptions::parse("as=nobody env A=1 as=root ls").is_err());
+    }
+
+    #[test]
+    fn an_option_with_broken_quotes_is_rejected() {
+        assert!(ExecOptions::parse("cwd='/tmp ls").is_err());
+        assert!(ExecOptions::parse("env A=\"1 ls").is_err());
+        assert!(ExecOptions::parse("as='a'b'c ls").is_err());
+    }
+}
@@ -0,0 +1,139 @@
+use crate::domain::model::message::Message;
+use crate::domain::r#enum::job::JobState;
+use chrono::NaiveDateTime;
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+// RingBuffer keeps the last `capacity` bytes written into it.
+pub struct RingBuffer {
+    capacity: usize,
+    data: VecDeque<u8>,
+    // total amount of bytes ever written, tells how much has been dropped
+    written: u64,
+}
+impl RingBuffer {
+    pub fn new(capacity: usize) -> RingBuffer {
+        RingBuffer {
+            capacity,
+            data: VecDeque::with_capacity(capacity),
+            written: 0,
+        }
+    }
+
+    pub fn write(&mut self, bytes: &[u8]) {
+        self.written += bytes.len() as u64;
+
+        // just the tail of a chunk which is larger than the whole buffer matters
+        let bytes = &bytes[bytes.len().saturating_sub(self.capacity)..];
+        let overflow = (self.data.len() + bytes.len()).saturating_sub(self.capacity);
+        self.data.drain(..overflow);
+        self.data.extend(bytes);
+    }
+
+    pub fn bytes(&self) -> Vec<u8> {
+        self.data.iter().copied().collect()
+    }
+
+    // returns how many bytes have been pushed out of the buffer
+    pub fn dropped(&self) -> u64 {
+        self.written - self.data.len() as u64
+    }
+}
+
+// Job is a background process started by /bg, its output is kept in a ring buffer.
+pub struct Job {
+    pub id: u64,
+    pub pid: u32,
+    // the message which has started the job, its result is replied to it
+    pub message: Message,
+    pub command: String,
+    pub started_at: NaiveDateTime,
+    started: Instant,
+    finished: Mutex<Option<Instant>>,
+    state: Mutex<JobState>,
+    output: Mutex<RingBuffer>,
+}
+impl Job {
+    pub fn new(id: u64, pid: u32, message: Message, command: String, started_at: NaiveDateTime, output_capacity: usize) -> Job {
+        Job {
+            id,
+            pid,
+            message,
+            command,
+            started_at,
+            started: Instant::now(),
+            finished: Mutex::new(None),
+            state: Mutex::new(JobState::Running),
+            output: Mutex::new(RingBuffer::new(output_capacity)),
+        }
+    }
+
+    pub fn state(&self) -> JobState {
+        *self.state.lock().unwrap()
+    }
+
+    pub fn finish(&self, state: JobState) {
+        *self.state.lock().unwrap() = state;
+        *self.finished.lock().unwrap() = Some(Instant::now());
+    }
+
+    // returns for how long the job is running or was running before it has finished
+    pub fn uptime(&self) -> Duration {
+        match *self.finished.lock().unwrap() {
+            Some(finished) => finished.duration_since(self.started),
+            None => self.started.elapsed(),
+        }
+    }
+
+    pub fn write_output(&self, bytes: &[u8]) {
+        self.output.lock().unwrap().write(bytes);
+    }
+
+    // returns the recent output and the amount of the older bytes which have been dropped
+    pub fn output(&self) -> (Vec<u8>, u64) {
+        let output = self.output.lock().unwrap();
+        (output.bytes(), output.dropped())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn buffer(capacity: usize, chunks: &[&[u8]]) -> RingBuffer {
+        let mut buffer = RingBuffer::new(capacity);
+        for chunk in chunks {
+            buffer.write(chunk);
+        }
+        buffer
+    }
+
+    #[test]
+    fn a_partial_buffer_keeps_everything() {
+        let buffer = buffer(8, &[b"abc", b"de"]);
+
+        assert_eq!((buffer.bytes(), buffer.dropped()), (b"abcde".to_vec(), 0));
+    }
+
+    #[test]
+    fn the_oldest_bytes_are_pushed_out_when_the_buffer_wraps_around() {
+        let buffer = buffer(4, &[b"abc", b"de", b"f"]);
+
+        assert_eq!((buffer.bytes(), buffer.dropped()), (b"cdef".to_vec(), 2));
+    }
+
+    #[test]
+    fn just_the_tail_of_a_chunk_larger_than_the_buffer_is_kept() {
+        let buffer = buffer(4, &[b"ab", b"0123456789"]);
+
+        assert_eq!((buffer.bytes(), buffer.dropped()), (b"6789".to_vec(), 8));
+    }
+
+    #[test]
+    fn an_empty_buffer_drops_everything() {
+        let buffer = buffer(0, &[b"abc"]);
+
+        assert_eq!((buffer.bytes(), buffer.dropped()), (vec![], 3));
+    }
+}
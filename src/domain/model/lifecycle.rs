@@ -0,0 +1,35 @@
+use crate::domain::r#enum::topic::Topic;
+use crate::infrastructure::model::command::{Command, Exit};
+use std::time::Duration;
+
+// Lifecycle is an event of the loop which is published to the subscribers of its topic.
+#[derive(Clone)]
+pub struct Lifecycle {
+    pub topic: Topic,
+    // the name of the event, it is the command line for the commands
+    pub name: String,
+    // the command of a chat, it is None for the events of the app itself
+    pub command: Option<Command>,
+    // the result of the command and how long it has taken, set once it has finished
+    pub exit: Option<Exit>,
+    pub duration: Option<Duration>,
+}
+
+impl Lifecycle {
+    pub fn fired(name: String, command: Option<Command>) -> Lifecycle {
+        Lifecycle { topic: Topic::EventFired, name, command, exit: None, duration: None }
+    }
+
+    pub fn started(name: String, command: Option<Command>) -> Lifecycle {
+        Lifecycle { topic: Topic::CommandStarted, name, command, exit: None, duration: None }
+    }
+
+    pub fn finished(name: String, command: Option<Command>, exit: Exit, duration: Duration) -> Lifecycle {
+        Lifecycle { topic: Topic::CommandFinished, name, command, exit: Some(exit), duration: Some(duration) }
+    }
+
+    // kind returns the type of the command or "Internal" for the events of the app itself
+    pub fn kind(&self) -> String {
+        self.command.as_ref().map(|command| command.r#type.to_string()).unwrap_or("Internal".to_string())
+    }
+}
@@ -0,0 +1,162 @@
+use crate::domain::r#enum::limit::Limit;
+
+// Limits are the resource limits applied to spawned processes, None means the limit is
+// not set at this level (inherited from the defaults), Some(0) means it is unlimited.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Limits {
+    // CPU time in seconds (RLIMIT_CPU)
+    pub cpu_seconds: Option<u64>,
+    // virtual memory in bytes (RLIMIT_AS)
+    pub address_space: Option<u64>,
+    // number of open descriptors (RLIMIT_NOFILE)
+    pub open_files: Option<u64>,
+    // number of processes of the user (RLIMIT_NPROC), the kernel counts all the processes of
+    // the real uid and does not apply it to root, so it bounds the commands run as another user only
+    pub processes: Option<u64>,
+    // captured stdout and stderr in bytes (each of them)
+    pub output_bytes: Option<u64>,
+}
+
+impl Limits {
+    // parse reads a comma separated list like "cpu=60,as=512M,nofile=256,nproc=64,output=1M"
+    pub fn parse(spec: &str) -> Result<Limits, String> {
+        let mut limits = Limits::default();
+
+        for pair in spec.split(',').map(str::trim).filter(|pair| !pair.is_empty()) {
+            let Some((name, value)) = pair.split_once('=') else {
+                return Err(format!("Invalid limit `{}`, expected name=value.", pair));
+            };
+            let value = Some(Self::parse_size(value.trim())?);
+
+            match name.trim() {
+                "cpu" => limits.cpu_seconds = value,
+                "as" => limits.address_space = value,
+                "nofile" => limits.open_files = value,
+                "nproc" => limits.processes = value,
+                "output" => limits.output_bytes = value,
+                other => return Err(format!("Unknown limit `{}`, use one of: cpu, as, nofile, nproc, output.", other)),
+            }
+        }
+
+        Ok(limits)
+    }
+
+    // merge returns these limits overridden by the set fields of the other ones
+    pub fn merge(&self, other: &Limits) -> Limits {
+        Limits {
+            cpu_seconds: other.cpu_seconds.or(self.cpu_seconds),
+            address_space: other.address_space.or(self.address_space),
+            open_files: other.open_files.or(self.open_files),
+            processes: other.processes.or(self.processes),
+            output_bytes: other.output_bytes.or(self.output_bytes),
+        }
+    }
+
+    // returns the output limit if it is set and is not unlimited
+    pub fn output(&self) -> Option<u64> {
+        self.output_bytes.filter(|bytes| *bytes > 0)
+    }
+
+    // suspect tells which of the set limits a failed process has most likely hit, judging by the
+    // error it has failed to start with or by what it has printed into stderr, along with its value.
+    pub fn suspect(&self, errno: Option<i32>, stderr: &str) -> Option<(Limit, u64)> {
+        let stderr = stderr.to_lowercase();
+        let has = |patterns: &[&str]| patterns.iter().any(|pattern| stderr.contains(pattern));
+
+        let limit = match errno {
+            Some(libc::ENOMEM) => Limit::AddressSpace,
+            Some(libc::EMFILE) => Limit::OpenFiles,
+            Some(libc::EAGAIN) => Limit::Processes,
+            Some(_) => return None,
+            None if has(&["cannot allocate memory", "out of memory", "memory allocation", "memoryerror"]) => Limit::AddressSpace,
+            None if has(&["too many open files"]) => Limit::OpenFiles,
+            None if has(&["resource temporarily unavailable", "cannot fork", "fork: retry"]) => Limit::Processes,
+            None => return None,
+        };
+        let value = match limit {
+            Limit::AddressSpace => self.address_space,
+            Limit::OpenFiles => self.open_files,
+            Limit::Processes => self.processes,
+            _ => None,
+        };
+
+        value.filter(|value| *value > 0).map(|value| (limit, value))
+    }
+
+    // a number with an optional K, M or G suffix (powers of 1024)
+    fn parse_size(value: &str) -> Result<u64, String> {
+        let (digits, multiplier) = match value.chars().last().map(|c| c.to_ascii_uppercase()) {
+            Some('K') => (&value[..value.len() - 1], 1024),
+            Some('M') => (&value[..value.len() - 1], 1024 * 1024),
+            Some('G') => (&value[..value.len() - 1], 1024 * 1024 * 1024),
+            _ => (value, 1),
+        };
+
+        match digits.parse::<u64>() {
+            Ok(number) => Ok(number.saturating_mul(multiplier)),
+            Err(_) => Err(format!("Invalid limit value `{}`, expected a number with optional K, M or G suffix.", value)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn limits_are_parsed_with_the_size_suffixes() {
+        let limits = Limits::parse(" cpu=60, as=512M,nofile=256 ,nproc=64,output=1k,").unwrap();
+
+        assert_eq!(
+            limits,
+            Limits {
+                cpu_seconds: Some(60),
+                address_space: Some(512 * 1024 * 1024),
+                open_files: Some(256),
+                processes: Some(64),
+                output_bytes: Some(1024),
+            }
+        );
+        assert_eq!(Limits::parse("as=2G").unwrap().address_space, Some(2 * 1024 * 1024 * 1024));
+        assert_eq!(Limits::parse("").unwrap(), Limits::default());
+    }
+
+    #[test]
+    fn invalid_limits_are_rejected() {
+        assert!(Limits::parse("mem=1M").unwrap_err().contains("Unknown limit `mem`"));
+        assert!(Limits::parse("cpu").unwrap_err().contains("expected name=value"));
+        assert!(Limits::parse("cpu=1T").unwrap_err().contains("Invalid limit value `1T`"));
+        assert!(Limits::parse("output=-1").is_err());
+    }
+
+    #[test]
+    fn the_set_fields_override_the_defaults_and_zero_is_unlimited() {
+        let defaults = Limits::parse("cpu=600,output=1M").unwrap();
+        let merged = defaults.merge(&Limits::parse("cpu=0,nproc=10").unwrap());
+
+        assert_eq!((merged.cpu_seconds, merged.processes, merged.output_bytes), (Some(0), Some(10), Some(1024 * 1024)));
+        assert_eq!(Limits::parse("output=0").unwrap().output(), None);
+    }
+
+    #[test]
+    fn the_hit_limit_is_suspected_by_the_error() {
+        let limits = Limits::parse("as=64M,nofile=16,nproc=8").unwrap();
+
+        assert_eq!(limits.suspect(Some(libc::ENOMEM), ""), Some((Limit::AddressSpace, 64 * 1024 * 1024)));
+        assert_eq!(limits.suspect(Some(libc::EMFILE), ""), Some((Limit::OpenFiles, 16)));
+        assert_eq!(limits.suspect(Some(libc::EAGAIN), ""), Some((Limit::Processes, 8)));
+        assert_eq!(limits.suspect(None, "python: MemoryError"), Some((Limit::AddressSpace, 64 * 1024 * 1024)));
+        assert_eq!(limits.suspect(None, "ls: Too many open files"), Some((Limit::OpenFiles, 16)));
+        assert_eq!(limits.suspect(None, "sh: fork: Resource temporarily unavailable"), Some((Limit::Processes, 8)));
+    }
+
+    #[test]
+    fn nothing_is_suspected_of_an_unset_limit_or_another_error() {
+        let limits = Limits::parse("as=0,nofile=16").unwrap();
+
+        assert_eq!(limits.suspect(Some(libc::ENOMEM), "Cannot allocate memory"), None);
+        assert_eq!(limits.suspect(Some(libc::EAGAIN), ""), None);
+        assert_eq!(limits.suspect(Some(libc::ENOENT), "Too many open files"), None);
+        assert_eq!(limits.suspect(None, "No such file or directory"), None);
+    }
+}
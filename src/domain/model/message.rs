@@ -0,0 +1,78 @@
+// Message is an inbound message of a transport, the commands are made of it. It is not bound
+// to a messenger, each transport converts its own messages into it.
+#[derive(Debug, Clone)]
+pub struct Message {
+    pub id: i64,
+    pub chat_id: i64,
+    pub user: User,
+    // unix time of the message
+    pub date: i64,
+    // messages with a file have no text, but may have a caption
+    pub text: String,
+    pub caption: Option<String>,
+    pub file: Option<File>,
+    // the message which this one replies to
+    pub reply_to: Option<Box<Message>>,
+}
+
+// User is the author of a message.
+#[derive(Debug, Clone)]
+pub struct User {
+    pub id: i64,
+    pub username: String,
+}
+
+// File is a file attached to a message, it is downloaded by the transport on demand.
+#[derive(Debug, Clone)]
+pub struct File {
+    // the id of the file within the transport
+    pub id: String,
+    pub name: Option<String>,
+    pub size: Option<u64>,
+}
+
+// The part of a reply which is its text, the attachments are named by part().
+pub const TEXT_PART: &str = "text";
+
+// Reply is an outbound message of a transport, the attachments are sent after the text. A reply
+// without the text is made of its attachments alone.
+#[derive(Debug, Clone)]
+pub struct Reply {
+    pub chat_id: i64,
+    pub text: String,
+    pub attachments: Vec<Attachment>,
+}
+
+impl Reply {
+    pub fn new(chat_id: i64, text: String, attachments: Vec<Attachment>) -> Reply {
+        Reply { chat_id, text, attachments }
+    }
+
+    // without drops the parts which have been sent already, so a retry sends the rest of them
+    pub fn without(self, sent: &[String]) -> Reply {
+        let is_sent = |part: String| sent.contains(&part);
+        Reply {
+            text: if is_sent(TEXT_PART.to_string()) { String::new() } else { self.text },
+            attachments: self.attachments.into_iter().filter(|attachment| !is_sent(attachment.part())).collect(),
+            ..self
+        }
+    }
+}
+
+// Attachment is a file which is sent along with a reply (e.g. binary output).
+#[derive(Debug, Clone)]
+pub struct Attachment {
+    pub name: String,
+    pub data: Vec<u8>,
+}
+
+impl Attachment {
+    pub fn new(name: String, data: Vec<u8>) -> Self {
+        Self { name, data }
+    }
+
+    // part is the name of the attachment among the parts of its reply
+    pub fn part(&self) -> String {
+        format!("file:{}", self.name)
+    }
+}
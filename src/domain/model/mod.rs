@@ -1,3 +1,12 @@
+pub mod audit;
 pub mod command;
+pub mod dead_letter;
 pub mod event;
+pub mod exec;
+pub mod job;
+pub mod lifecycle;
+pub mod limits;
+pub mod message;
+pub mod retry;
+pub mod schedule;
 pub mod wife;
@@ -0,0 +1,43 @@
+use std::time::Duration;
+
+// RetryPolicy tells the event loop how a failed event is retried. The delay is doubled on each
+// attempt up to the max one, an event which has failed all of its attempts is dead-lettered.
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    // attempts in total including the first one
+    pub max_attempts: u32,
+    pub backoff: Duration,
+    pub max_backoff: Duration,
+    // whether a result which has not been delivered is sent again, the command is not executed again
+    pub is_delivery_retried: bool,
+    // whether a command which has failed to execute is executed again, it may be not idempotent
+    pub is_execution_retried: bool,
+    // whether a failed event is kept in the dead-letter queue or is dropped
+    pub is_dead_lettered: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: 5,
+            backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(60),
+            is_delivery_retried: true,
+            is_execution_retried: false,
+            is_dead_lettered: true,
+        }
+    }
+}
+
+impl RetryPolicy {
+    // none is the policy of the events which are neither retried nor dead-lettered
+    // (e.g. the periodic ones, the next run replaces the failed one)
+    pub fn none() -> RetryPolicy {
+        RetryPolicy { max_attempts: 1, is_dead_lettered: false, ..RetryPolicy::default() }
+    }
+
+    // delay returns how long the loop waits before the next attempt after the failed one (from 1)
+    pub fn delay(&self, attempt: u32) -> Duration {
+        self.backoff.saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1))).min(self.max_backoff)
+    }
+}
@@ -0,0 +1,123 @@
+use std::time::Duration;
+
+// The bounds of a schedule, so a typo does not flood the chat.
+const MIN_INTERVAL: Duration = Duration::from_secs(1);
+const MAX_TIMES: i64 = 1000;
+
+// Schedule tells how many times a command is executed and how often, it is written in front of
+// the command: `/every 5m 3x df -h`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Schedule {
+    pub interval: Duration,
+    pub times: i64,
+}
+
+impl Schedule {
+    // parse strips the schedule from the input and returns it with the rest of the input
+    pub fn parse(input: &str) -> Result<(Schedule, String), String> {
+        let usage = "use: /every <interval like 30s, 5m, 1h or 1d> <times like 3x> <command>";
+
+        let Some((interval, rest)) = Self::next_token(input) else {
+            return Err(format!("The schedule is incomplete, {}.", usage));
+        };
+        let Some((times, rest)) = Self::next_token(rest) else {
+            return Err(format!("The schedule is incomplete, {}.", usage));
+        };
+        if rest.is_empty() {
+            return Err(format!("The command is missing, {}.", usage));
+        }
+
+        let interval = Self::interval(interval).ok_or(format!("Invalid interval `{}`, {}.", interval, usage))?;
+        if interval < MIN_INTERVAL {
+            return Err(format!("The interval must be {:?} at least.", MIN_INTERVAL));
+        }
+        let times = times
+            .strip_suffix('x')
+            .and_then(|n| n.parse::<i64>().ok())
+            .filter(|n| *n > 0)
+            .ok_or(format!("Invalid times `{}`, {}.", times, usage))?;
+        if times > MAX_TIMES {
+            return Err(format!("The times must be {} at most.", MAX_TIMES));
+        }
+
+        Ok((Schedule { interval, times }, rest.to_string()))
+    }
+
+    // next_token splits the input by the first run of whitespace, so the tokens may be separated
+    // by several spaces, returns None if there is no token
+    fn next_token(input: &str) -> Option<(&str, &str)> {
+        let input = input.trim_start();
+        if input.is_empty() {
+            return None;
+        }
+        match input.split_once(char::is_whitespace) {
+            Some((token, rest)) => Some((token, rest.trim_start())),
+            None => Some((input, "")),
+        }
+    }
+
+    // an interval is a number with a unit: s, m, h or d
+    fn interval(s: &str) -> Option<Duration> {
+        let unit = s.chars().last()?;
+        let n = s[..s.len() - unit.len_utf8()].parse::<u64>().ok()?;
+        let secs = match unit {
+            's' => 1,
+            'm' => 60,
+            'h' => 60 * 60,
+            'd' => 24 * 60 * 60,
+            _ => return None,
+        };
+        Some(Duration::from_secs(n.checked_mul(secs)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn schedule(secs: u64, times: i64) -> Schedule {
+        Schedule { interval: Duration::from_secs(secs), times }
+    }
+
+    #[test]
+    fn the_schedule_is_stripped_of_the_command() {
+        let cases = [
+            ("30s 1x uptime", schedule(30, 1), "uptime"),
+            ("5m 3x df -h", schedule(300, 3), "df -h"),
+            ("2h 1000x echo  'a  b'", schedule(7200, 1000), "echo  'a  b'"),
+            ("1d 7x backup", schedule(86400, 7), "backup"),
+            // the tokens may be separated by several spaces or tabs
+            ("  5m   3x\t df -h", schedule(300, 3), "df -h"),
+        ];
+
+        for (input, expected, command) in cases {
+            assert_eq!(Schedule::parse(input), Ok((expected, command.to_string())), "{}", input);
+        }
+    }
+
+    #[test]
+    fn an_incomplete_schedule_or_a_missing_command_is_rejected() {
+        assert!(Schedule::parse("").unwrap_err().contains("incomplete"));
+        assert!(Schedule::parse("5m").unwrap_err().contains("incomplete"));
+        assert!(Schedule::parse("5m 3x").unwrap_err().contains("The command is missing"));
+        assert!(Schedule::parse("5m 3x   ").unwrap_err().contains("The command is missing"));
+    }
+
+    #[test]
+    fn an_invalid_or_too_short_interval_is_rejected() {
+        for interval in ["5", "m", "5w", "-5m", "1.5h", "99999999999999999d"] {
+            let error = Schedule::parse(format!("{} 3x ls", interval).as_str()).unwrap_err();
+            assert!(error.contains(format!("Invalid interval `{}`", interval).as_str()), "{}", error);
+        }
+        assert!(Schedule::parse("0s 3x ls").unwrap_err().contains("The interval must be"));
+    }
+
+    #[test]
+    fn the_times_must_be_a_positive_number_with_x_up_to_the_max() {
+        for times in ["3", "x", "0x", "-1x", "3X", "times=3"] {
+            let error = Schedule::parse(format!("5m {} ls", times).as_str()).unwrap_err();
+            assert!(error.contains(format!("Invalid times `{}`", times).as_str()), "{}", error);
+        }
+        assert!(Schedule::parse("5m 1001x ls").unwrap_err().contains("The times must be 1000 at most"));
+    }
+}
@@ -0,0 +1,294 @@
+use crate::app::cfg::handle::CfgHandle;
+use crate::domain::error::audit::{AuditDisabledError, AuditTamperedError};
+use crate::domain::model::audit::AuditEntry;
+use std::error::Error;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::os::unix::fs::OpenOptionsExt;
+use std::sync::Mutex;
+
+pub trait AuditLogTrait: Send + Sync {
+    // record appends the entry to the log
+    fn record(&self, entry: AuditEntry) -> Result<(), Box<dyn Error>>;
+    // recent returns the last n entries, the oldest one first
+    fn recent(&self, n: usize) -> Result<Vec<AuditEntry>, Box<dyn Error>>;
+    // verify checks the hash chain of the kept entries and returns how many have been checked
+    fn verify(&self) -> Result<usize, Box<dyn Error>>;
+}
+
+// AuditLog is an append-only file of the executed commands, the file is rotated by size into
+// <path>.1, <path>.2, etc. (the greater the older) and the oldest one is removed.
+pub struct AuditLog {
+    cfg: CfgHandle,
+    // hash of the last entry, it is read of the file on the first record
+    last_hash: Mutex<Option<Option<String>>>,
+}
+
+impl AuditLog {
+    pub fn new(cfg: CfgHandle) -> AuditLog {
+        AuditLog { cfg, last_hash: Mutex::new(None) }
+    }
+
+    fn path(&self) -> Result<String, Box<dyn Error>> {
+        match &self.cfg.get().audit_filepath {
+            Some(path) => Ok(path.clone()),
+            None => Err(Box::new(AuditDisabledError::new())),
+        }
+    }
+
+    // returns the files of the log, the oldest one first
+    fn files(&self, path: &str) -> Vec<String> {
+        let mut files: Vec<String> = (1..=self.cfg.get().audit_max_files)
+            .rev()
+            .map(|i| format!("{}.{}", path, i))
+            .collect();
+        files.push(path.to_string());
+        files
+    }
+
+    fn read(file: &str) -> Result<Vec<AuditEntry>, Box<dyn Error>> {
+        let data = match fs::read_to_string(file) {
+            Ok(data) => data,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+            Err(e) => return Err(Box::new(e)),
+        };
+
+        let mut entries = vec![];
+        for (i, line) in data.lines().enumerate().filter(|(_, line)| !line.trim().is_empty()) {
+            match serde_json::from_str(line) {
+                Ok(entry) => entries.push(entry),
+                Err(_) => return Err(Box::new(AuditTamperedError::new(file.to_string(), i + 1))),
+            }
+        }
+        Ok(entries)
+    }
+
+    fn rotate(&self, path: &str) -> io::Result<()> {
+        let max_files = self.cfg.get().audit_max_files;
+        if max_files == 0 {
+            return fs::remove_file(path);
+        }
+
+        for i in (1..max_files).rev() {
+            let from = format!("{}.{}", path, i);
+            if fs::metadata(&from).is_ok() {
+                fs::rename(&from, format!("{}.{}", path, i + 1))?;
+            }
+        }
+        fs::rename(path, format!("{}.1", path))
+    }
+}
+
+impl AuditLogTrait for AuditLog {
+    fn record(&self, mut entry: AuditEntry) -> Result<(), Box<dyn Error>> {
+        let path = self.path()?;
+        let cfg = self.cfg.get();
+
+        // the lock is held while the entry is written, so the chain is kept in order
+        let mut last_hash = self.last_hash.lock().unwrap();
+        if cfg.is_audit_hash_chained {
+            let prev_hash = match last_hash.take() {
+                Some(hash) => hash,
+                None => self.recent(1)?.pop().and_then(|entry| entry.hash),
+            };
+            entry.chain(prev_hash);
+        }
+
+        let mut line = serde_json::to_string(&entry)?;
+        line.push('\n');
+
+        let size = fs::metadata(&path).map(|metadata| metadata.len()).unwrap_or(0);
+        if cfg.audit_max_file_bytes > 0 && size > 0 && size + line.len() as u64 > cfg.audit_max_file_bytes {
+            self.rotate(path.as_str())?;
+        }
+
+        OpenOptions::new()
+            .append(true)
+            .create(true)
+            .mode(0o600)
+            .open(&path)?
+            .write_all(line.as_bytes())?;
+
+        *last_hash = Some(entry.hash);
+        Ok(())
+    }
+
+    fn recent(&self, n: usize) -> Result<Vec<AuditEntry>, Box<dyn Error>> {
+        let path = self.path()?;
+
+        let mut entries = vec![];
+        for file in self.files(path.as_str()).iter().rev() {
+            if entries.len() >= n {
+                break;
+            }
+            let mut older = Self::read(file)?;
+            older.append(&mut entries);
+            entries = older;
+        }
+
+        Ok(entries.split_off(entries.len().saturating_sub(n)))
+    }
+
+    fn verify(&self) -> Result<usize, Box<dyn Error>> {
+        let path = self.path()?;
+
+        // the first kept entry may point to a removed one, so its prev_hash is taken as it is
+        let mut prev_hash: Option<Option<String>> = None;
+        let mut count = 0;
+        for file in self.files(path.as_str()) {
+            for (i, entry) in Self::read(file.as_str())?.into_iter().enumerate() {
+                // the entries which have been written while the chain was disabled reset it, the
+                // next chained entry starts a new chain (see record), so an unchained entry may
+                // not be inserted into a chain without breaking the link of the following one
+                if entry.hash.is_none() {
+                    prev_hash = Some(None);
+                    continue;
+                }
+
+                let is_linked = match &prev_hash {
+                    Some(prev_hash) => entry.prev_hash.as_ref() == Some(&prev_hash.clone().unwrap_or_default()),
+                    None => true,
+                };
+                if !is_linked || !entry.is_intact() {
+                    return Err(Box::new(AuditTamperedError::new(file, i + 1)));
+                }
+
+                prev_hash = Some(entry.hash);
+                count += 1;
+            }
+        }
+
+        Ok(count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::cfg::cfg::testing::with_cfg;
+    use crate::domain::r#enum::decision::Decision;
+    use std::env;
+
+    // TempLog is the path of a log of a test, its files are removed once it is dropped
+    struct TempLog(String);
+    impl TempLog {
+        fn new(name: &str) -> TempLog {
+            let log = TempLog(env::temp_dir().join(format!("repl-{}-{}.audit.log", std::process::id(), name)).to_string_lossy().to_string());
+            log.remove();
+            log
+        }
+        fn remove(&self) {
+            for i in 0..10 {
+                let _ = fs::remove_file(if i == 0 { self.0.clone() } else { format!("{}.{}", self.0, i) });
+            }
+        }
+        fn lines(&self) -> Vec<String> {
+            fs::read_to_string(&self.0).unwrap().lines().map(str::to_string).collect()
+        }
+        fn write(&self, lines: &[String]) {
+            fs::write(&self.0, lines.iter().map(|line| format!("{}\n", line)).collect::<String>()).unwrap();
+        }
+    }
+    impl Drop for TempLog {
+        fn drop(&mut self) {
+            self.remove();
+        }
+    }
+
+    fn audit_log(log: &TempLog, vars: &[(&str, &str)]) -> AuditLog {
+        let vars = [&[("AUDIT_FILE_PATH", log.0.as_str())], vars].concat();
+        with_cfg(&vars, |cfg| AuditLog::new(CfgHandle::new(cfg.unwrap())))
+    }
+
+    fn entry(input: &str) -> AuditEntry {
+        AuditEntry {
+            timestamp: "2025-01-01T00:00:00.000+00:00".to_string(),
+            user_id: 1,
+            username: "user".to_string(),
+            chat_id: 1,
+            message_id: 1,
+            input: input.to_string(),
+            command: "exec".to_string(),
+            argv: None,
+            exit_code: "0".to_string(),
+            duration_ms: 0,
+            stdout_bytes: 0,
+            stderr_bytes: 0,
+            attachment_bytes: 0,
+            decision: Decision::Allowed,
+            prev_hash: None,
+            hash: None,
+        }
+    }
+
+    fn record(audit: &AuditLog, inputs: &[&str]) {
+        for input in inputs {
+            audit.record(entry(input)).unwrap();
+        }
+    }
+
+    fn inputs(entries: Vec<AuditEntry>) -> Vec<String> {
+        entries.into_iter().map(|entry| entry.input).collect()
+    }
+
+    #[test]
+    fn the_log_is_rotated_by_size_and_the_oldest_file_is_removed() {
+        let log = TempLog::new("rotated");
+        let line_len = serde_json::to_string(&entry("/cmd 0")).unwrap().len() as u64 + 1;
+        let max_file_bytes = (2 * line_len).to_string();
+        let audit = audit_log(&log, &[("AUDIT_MAX_FILE_BYTES", max_file_bytes.as_str()), ("AUDIT_MAX_FILES", "2")]);
+
+        record(&audit, &["/cmd 0", "/cmd 1", "/cmd 2", "/cmd 3", "/cmd 4", "/cmd 5", "/cmd 6"]);
+
+        assert_eq!(AuditLog::read(format!("{}.2", log.0).as_str()).unwrap().len(), 2);
+        assert!(fs::metadata(format!("{}.3", log.0)).is_err());
+        assert_eq!(inputs(audit.recent(10).unwrap()), ["/cmd 2", "/cmd 3", "/cmd 4", "/cmd 5", "/cmd 6"]);
+        assert_eq!(inputs(audit.recent(2).unwrap()), ["/cmd 5", "/cmd 6"]);
+    }
+
+    #[test]
+    fn an_intact_chain_is_verified_across_the_files() {
+        let log = TempLog::new("intact");
+        let audit = audit_log(&log, &[("IS_AUDIT_HASH_CHAINED", "true"), ("AUDIT_MAX_FILE_BYTES", "1024")]);
+
+        record(&audit, &["/cmd 0", "/cmd 1", "/cmd 2", "/cmd 3", "/cmd 4"]);
+
+        assert!(fs::metadata(format!("{}.1", log.0)).is_ok());
+        assert_eq!(audit.verify().unwrap(), 5);
+    }
+
+    #[test]
+    fn an_edited_removed_or_inserted_entry_breaks_the_chain() {
+        let log = TempLog::new("tampered");
+        let audit = audit_log(&log, &[("IS_AUDIT_HASH_CHAINED", "true")]);
+        record(&audit, &["/cmd 0", "/cmd 1", "/cmd 2", "/cmd 3"]);
+        let lines = log.lines();
+
+        let edited = [lines[..1].to_vec(), vec![lines[1].replace("/cmd 1", "/cmd 9")], lines[2..].to_vec()].concat();
+        let removed = [lines[..1].to_vec(), lines[2..].to_vec()].concat();
+        let inserted = [lines[..2].to_vec(), vec![lines[0].clone()], lines[2..].to_vec()].concat();
+        for (tampered, line) in [(edited, 2), (removed, 2), (inserted, 3)] {
+            log.write(&tampered);
+            let error = audit.verify().unwrap_err();
+
+            assert!(error.is::<AuditTamperedError>());
+            assert!(error.to_string().contains(format!("at line {} of", line).as_str()), "{}", error);
+        }
+    }
+
+    #[test]
+    fn an_unchained_entry_resets_the_chain() {
+        let log = TempLog::new("reset");
+        record(&audit_log(&log, &[("IS_AUDIT_HASH_CHAINED", "true")]), &["/cmd 0", "/cmd 1"]);
+        record(&audit_log(&log, &[]), &["/cmd 2"]);
+        let audit = audit_log(&log, &[("IS_AUDIT_HASH_CHAINED", "true")]);
+        record(&audit, &["/cmd 3", "/cmd 4"]);
+
+        assert_eq!(audit.verify().unwrap(), 4);
+
+        // an unchained entry which is inserted into a chain breaks the link of the next one
+        let lines = log.lines();
+        log.write(&[lines[..1].to_vec(), vec![lines[2].clone()], lines[1..].to_vec()].concat());
+        assert!(audit.verify().unwrap_err().to_string().contains("at line 3 of"));
+    }
+}
@@ -0,0 +1,49 @@
+use crate::domain::model::audit::AuditEntry;
+use crate::domain::model::lifecycle::Lifecycle;
+use crate::domain::r#enum::topic::Topic;
+use crate::domain::service::audit::log::AuditLogTrait;
+use crate::domain::service::event::bus::Subscriber;
+use crate::infrastructure::broadcasting::mpsc::queue::Overflow;
+use std::sync::Arc;
+
+// AuditSubscriber records the finished commands of the chats, the events of the app itself
+// are not audited.
+pub struct AuditSubscriber {
+    audit: Arc<Box<dyn AuditLogTrait>>,
+}
+
+impl AuditSubscriber {
+    pub fn new(audit: Arc<Box<dyn AuditLogTrait>>) -> AuditSubscriber {
+        AuditSubscriber { audit }
+    }
+}
+
+impl Subscriber for AuditSubscriber {
+    fn name(&self) -> String {
+        "audit".to_string()
+    }
+
+    fn topics(&self) -> Vec<Topic> {
+        vec![Topic::CommandFinished]
+    }
+
+    // no entry may be lost, so the loop waits for the audit log rather than drops an event
+    fn overflow(&self) -> Option<Overflow> {
+        Some(Overflow::Block)
+    }
+
+    fn notify(&self, event: &Lifecycle) {
+        let (Some(command), Some(exit)) = (&event.command, &event.exit) else {
+            return;
+        };
+
+        if let Err(e) = self.audit.record(AuditEntry::new(command, exit, event.duration.unwrap_or_default())) {
+            log::error!(
+                target: "audit",
+                chat_id = command.message.chat_id,
+                message_id = command.message.id;
+                "Failed to record the audit entry: {}.", e
+            );
+        }
+    }
+}
@@ -0,0 +1,247 @@
+use crate::domain::model::lifecycle::Lifecycle;
+use crate::domain::r#enum::topic::Topic;
+use crate::infrastructure::broadcasting::mpsc::channel::Channel;
+use crate::infrastructure::broadcasting::mpsc::queue::Overflow;
+use crate::infrastructure::metrics::metrics::Metrics;
+use std::collections::HashMap;
+use std::error::Error;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+
+pub trait Subscriber: Send + Sync {
+    // name tells the subscribers apart, it must be unique on the bus
+    fn name(&self) -> String;
+    // topics returns the topics the subscriber is notified of
+    fn topics(&self) -> Vec<Topic>;
+    // notify handles the published event, it is called by the thread of the subscriber
+    fn notify(&self, event: &Lifecycle);
+    // overflow tells what happens when the queue of the subscriber is full, None is the bus default
+    fn overflow(&self) -> Option<Overflow> {
+        None
+    }
+}
+
+pub trait EventBus: Send + Sync {
+    // subscribe starts to notify the subscriber of its topics
+    fn subscribe(&self, subscriber: Arc<Box<dyn Subscriber>>) -> Result<(), Box<dyn Error>>;
+    // publish sends the event to the subscribers of its topic, it does not wait for them to handle it,
+    // but it waits for a room in the full queue of a subscriber whose overflow policy is Block (e.g. the audit log)
+    fn publish(&self, event: Lifecycle);
+    // close drops the subscriptions and waits until the published events have been handled
+    fn close(&self);
+}
+
+// LifecycleBus is a pub/sub bus on top of the keyed channel: each subscriber has a receiver keyed
+// by its name which is read by its own thread, so a slow subscriber does not hold the loop and
+// it is notified in the order of publishing.
+pub struct LifecycleBus {
+    channel: Arc<Box<dyn Channel<Arc<Lifecycle>>>>,
+    // the overflow policy of the subscribers which have no own one
+    overflow: Overflow,
+    metrics: Arc<Metrics>,
+    // the keys of the subscribers by topic
+    keys: Mutex<HashMap<Topic, Vec<String>>>,
+    // how many events of each subscriber have been dropped and are accounted by the metrics
+    dropped: Mutex<HashMap<String, u64>>,
+    threads: Mutex<Vec<JoinHandle<()>>>,
+}
+
+impl LifecycleBus {
+    pub fn new(channel: Box<dyn Channel<Arc<Lifecycle>>>, overflow: Overflow, metrics: Arc<Metrics>) -> LifecycleBus {
+        LifecycleBus {
+            channel: Arc::new(channel),
+            overflow,
+            metrics,
+            keys: Mutex::new(HashMap::new()),
+            dropped: Mutex::new(HashMap::new()),
+            threads: Mutex::new(vec![]),
+        }
+    }
+
+    fn unsubscribe(&self, key: &str) {
+        for keys in self.keys.lock().unwrap().values_mut() {
+            keys.retain(|k| k != key);
+        }
+        self.channel.remove(key.to_string());
+    }
+
+    // observe updates the lag of the subscriber and returns how many of its events have been dropped
+    // since the last call
+    fn observe(&self, key: &str) -> u64 {
+        let (lag, dropped) = self.channel.lag(key);
+        self.metrics.bus_subscriber_lag.set(&[key], lag as i64);
+
+        let mut accounted = self.dropped.lock().unwrap();
+        let accounted = accounted.entry(key.to_string()).or_default();
+        let n = dropped.saturating_sub(*accounted);
+        *accounted = dropped;
+        n
+    }
+}
+
+impl EventBus for LifecycleBus {
+    fn subscribe(&self, subscriber: Arc<Box<dyn Subscriber>>) -> Result<(), Box<dyn Error>> {
+        let key = subscriber.name();
+        let receiver = self.channel.add_with(key.clone(), subscriber.overflow().unwrap_or(self.overflow))?;
+        let mut keys = self.keys.lock().unwrap();
+        for topic in subscriber.topics() {
+            keys.entry(topic).or_default().push(key.clone());
+        }
+
+        let metrics = self.metrics.clone();
+        let channel = self.channel.clone();
+        let thread = thread::Builder::new().name(format!("subscriber-{}", key)).spawn(move || {
+            // the thread is finished once the subscription has been dropped
+            for event in receiver.iter() {
+                // a panicked subscriber misses the event alone
+                if panic::catch_unwind(AssertUnwindSafe(|| subscriber.notify(&event))).is_err() {
+                    log::error!(target: "bus", "Subscriber `{}` has panicked on {} `{}`.", key, event.topic, event.name);
+                }
+                metrics.bus_subscriber_lag.set(&[key.as_str()], channel.lag(key.as_str()).0 as i64);
+            }
+        })?;
+        self.threads.lock().unwrap().push(thread);
+        Ok(())
+    }
+
+    fn publish(&self, event: Lifecycle) {
+        let topic = event.topic;
+        let keys = self.keys.lock().unwrap().get(&topic).cloned().unwrap_or_default();
+        if keys.is_empty() {
+            return;
+        }
+
+        let event = Arc::new(event);
+        for key in keys {
+            // the send is held by a full queue or drops an event, as the overflow policy tells
+            if let Err(e) = self.channel.send(Some(key.clone()), event.clone()) {
+                // the subscription is dropped if its thread has gone away
+                log::error!(target: "bus", "Failed to publish {} `{}` to `{}`: {}.", topic, event.name, key, e);
+                self.unsubscribe(key.as_str());
+                continue;
+            }
+
+            let dropped = self.observe(key.as_str());
+            if dropped > 0 {
+                self.metrics.bus_dropped_events.add(&[key.as_str()], dropped);
+                log::warn!(target: "bus", "Subscriber `{}` lags behind, {} event(s) have been dropped.", key, dropped);
+            }
+        }
+    }
+
+    fn close(&self) {
+        let mut keys: Vec<String> = self.keys.lock().unwrap().values().flatten().cloned().collect();
+        keys.sort();
+        keys.dedup();
+        for key in keys {
+            self.unsubscribe(key.as_str());
+        }
+
+        for thread in self.threads.lock().unwrap().drain(..) {
+            if thread.join().is_err() {
+                log::error!(target: "bus", "Subscriber thread has panicked.");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::infrastructure::broadcasting::mpsc::channel::Chan;
+    use std::sync::mpsc::{self, Sender};
+    use std::time::{Duration, Instant};
+
+    const TIMEOUT: Duration = Duration::from_millis(500);
+
+    // TestSubscriber reports the names of the events it is notified of, it panics on the ones
+    // named "panic" and takes its time on the ones named "slow"
+    struct TestSubscriber {
+        name: String,
+        notified: Mutex<Sender<String>>,
+    }
+    impl Subscriber for TestSubscriber {
+        fn name(&self) -> String {
+            self.name.clone()
+        }
+        fn topics(&self) -> Vec<Topic> {
+            vec![Topic::EventFired]
+        }
+        fn notify(&self, event: &Lifecycle) {
+            match event.name.as_str() {
+                "panic" => panic!("the subscriber has panicked"),
+                "slow" => thread::sleep(Duration::from_millis(50)),
+                _ => {}
+            }
+            let _ = self.notified.lock().unwrap().send(format!("{} {}", self.name, event.name));
+        }
+    }
+
+    fn bus() -> LifecycleBus {
+        LifecycleBus::new(Box::new(Chan::with_capacity(8)), Overflow::Block, Arc::new(Metrics::new()))
+    }
+
+    fn subscribe(bus: &LifecycleBus, name: &str) -> mpsc::Receiver<String> {
+        let (sender, notified) = mpsc::channel();
+        let subscriber = TestSubscriber { name: name.to_string(), notified: Mutex::new(sender) };
+        bus.subscribe(Arc::new(Box::new(subscriber))).unwrap();
+        notified
+    }
+
+    fn fired(name: &str) -> Lifecycle {
+        Lifecycle::fired(name.to_string(), None)
+    }
+
+    #[test]
+    fn a_panicking_subscriber_misses_the_event_alone() {
+        let bus = bus();
+        let a = subscribe(&bus, "a");
+        let b = subscribe(&bus, "b");
+
+        bus.publish(fired("panic"));
+        bus.publish(fired("next"));
+
+        // the panicked subscriber goes on with the next events and the other one gets all of them
+        assert_eq!(a.recv_timeout(TIMEOUT).unwrap(), "a next");
+        assert_eq!(b.recv_timeout(TIMEOUT).unwrap(), "b next");
+        bus.close();
+    }
+
+    #[test]
+    fn a_subscription_is_dropped_after_a_failed_send() {
+        let bus = bus();
+        let kept = subscribe(&bus, "kept");
+        // a subscriber whose receiver has gone away along with its thread
+        drop(bus.channel.add("gone".to_string()).unwrap());
+        bus.keys.lock().unwrap().entry(Topic::EventFired).or_default().push("gone".to_string());
+
+        // the channel finds out the receiver has gone away by the first event it hands over
+        let deadline = Instant::now() + TIMEOUT;
+        let mut published = 0;
+        while bus.keys.lock().unwrap()[&Topic::EventFired].len() > 1 && Instant::now() < deadline {
+            bus.publish(fired("event"));
+            published += 1;
+        }
+
+        assert_eq!(bus.keys.lock().unwrap()[&Topic::EventFired], vec!["kept".to_string()]);
+        assert!(bus.channel.sender(Some("gone".to_string())).is_err());
+        assert_eq!(kept.iter().take(published).count(), published);
+        bus.close();
+    }
+
+    #[test]
+    fn close_waits_until_the_published_events_have_been_handled() {
+        let bus = bus();
+        let notified = subscribe(&bus, "a");
+        for _ in 0..3 {
+            bus.publish(fired("slow"));
+        }
+
+        bus.close();
+
+        assert_eq!(notified.try_iter().count(), 3);
+        assert!(bus.threads.lock().unwrap().is_empty());
+        assert!(bus.keys.lock().unwrap().values().all(|keys| keys.is_empty()));
+    }
+}
@@ -0,0 +1,70 @@
+use crate::domain::model::dead_letter::DeadLetter;
+use crate::domain::model::event::ExecutableEvent;
+use crate::infrastructure::model::command::Exit;
+use chrono::Local;
+use std::sync::{Arc, Mutex};
+
+// How many dead letters are kept, the oldest one is dropped when a new one is over it.
+const MAX_DEAD_LETTERS: usize = 100;
+
+struct Letters {
+    list: Vec<DeadLetter>,
+    next_id: u64,
+}
+
+// DeadLetterQueue keeps the events which have failed all of their attempts.
+pub struct DeadLetterQueue {
+    letters: Mutex<Letters>,
+}
+
+impl DeadLetterQueue {
+    pub fn new() -> DeadLetterQueue {
+        DeadLetterQueue { letters: Mutex::new(Letters { list: vec![], next_id: 1 }) }
+    }
+
+    // push keeps the failed event and returns its id
+    pub fn push(&self, event: Arc<Box<dyn ExecutableEvent>>, undelivered: Option<Exit>, error: String, attempts: u32) -> u64 {
+        let mut letters = self.letters.lock().unwrap();
+        let id = letters.next_id;
+        letters.next_id += 1;
+
+        if letters.list.len() >= MAX_DEAD_LETTERS {
+            let dropped = letters.list.remove(0);
+            log::warn!(target: "loop", "Dead letter [{}] `{}` has been dropped, the queue is full.", dropped.id, dropped.event.name());
+        }
+        letters.list.push(DeadLetter { id, event, undelivered, error, attempts, failed_at: Local::now() });
+
+        id
+    }
+
+    // list returns the dead letters, the oldest one first
+    pub fn list(&self) -> Vec<DeadLetter> {
+        self.letters.lock().unwrap().list.clone()
+    }
+
+    // take removes the dead letter by id
+    pub fn take(&self, id: u64) -> Option<DeadLetter> {
+        let mut letters = self.letters.lock().unwrap();
+        let i = letters.list.iter().position(|letter| letter.id == id)?;
+        Some(letters.list.remove(i))
+    }
+
+    // take_all removes all of the dead letters
+    pub fn take_all(&self) -> Vec<DeadLetter> {
+        self.letters.lock().unwrap().list.drain(..).collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.letters.lock().unwrap().list.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.letters.lock().unwrap().list.is_empty()
+    }
+}
+
+impl Default for DeadLetterQueue {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -1,105 +1,673 @@
 use crate::app;
+use crate::app::model::health::{self, Health};
+use crate::domain::error::executor::{DeadLetterNotFoundError, DeliveryFailedError};
+use crate::domain::model::dead_letter::DeadLetter;
 use crate::domain::model::event::ExecutableEvent;
+use crate::domain::model::lifecycle::Lifecycle;
 use crate::domain::r#enum::event::Repeat;
+use crate::domain::service::event::bus::EventBus;
+use crate::domain::service::event::dead_letter::DeadLetterQueue;
+use crate::domain::service::event::queue::EventQueue;
 use crate::domain::service::executor::executor::Executor;
-use crate::infrastructure::broadcasting::mpsc::channel::Channel;
-use std::sync::{Arc, Mutex};
+use crate::infrastructure::metrics::metrics::Metrics;
+use crate::infrastructure::model::command::Exit;
+use std::error::Error;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+// A repeated event is executed once in this interval at most.
+const REPEAT_INTERVAL: Duration = Duration::from_secs(1);
+// An event which is not ready yet is checked again no sooner than in this interval.
+const MIN_RECHECK_INTERVAL: Duration = Duration::from_millis(100);
 
 pub trait EventLoop: Send + Sync {
     fn serve(&self);
+    // close wakes the loop up, so serve returns without waiting for the next event
+    fn close(&self);
     fn add_event(&self, event: Arc<Box<dyn ExecutableEvent>>);
+    // dead_letters returns the events which have failed all of their attempts
+    fn dead_letters(&self) -> Vec<DeadLetter>;
+    // replay puts the dead letter back into the loop (all of them if id is None) and returns how
+    // many have been put, an executed event is not executed again, just its result is sent
+    fn replay(&self, id: Option<u64>) -> Result<usize, DeadLetterNotFoundError>;
+    // discard drops the dead letter (all of them if id is None) and returns how many have been dropped
+    fn discard(&self, id: Option<u64>) -> Result<usize, DeadLetterNotFoundError>;
+}
+
+// Entry is an event in the queue along with its remaining runs and its failed attempts, the loop
+// accounts the repetitions of the events instead of the events themselves.
+struct Entry {
+    event: Arc<Box<dyn ExecutableEvent>>,
+    // how many runs are left including the current one (forever if None)
+    remaining: Option<i64>,
+    // how long the next run is after the start of the current one
+    interval: Duration,
+    attempts: u32,
+    // the result which has not been delivered, the event is not executed again then
+    undelivered: Option<Exit>,
+}
+impl Entry {
+    fn new(event: Arc<Box<dyn ExecutableEvent>>) -> Entry {
+        let (remaining, interval) = match event.repeats() {
+            Repeat::Once => (Some(1), REPEAT_INTERVAL),
+            Repeat::Always => (None, REPEAT_INTERVAL),
+            Repeat::Times(n) => (Some(n), REPEAT_INTERVAL),
+            Repeat::Every(interval, n) => (Some(n), interval),
+        };
+        Entry { event, remaining, interval, attempts: 0, undelivered: None }
+    }
 }
 
 pub struct CommandEventLoop {
     state: Arc<Box<dyn app::model::state::State>>,
-    events: Arc<Mutex<Vec<Arc<Box<dyn ExecutableEvent>>>>>,
-    channel: Box<dyn Channel<Arc<Box<dyn ExecutableEvent>>>>,
+    events: EventQueue<Entry>,
+    dead_letters: DeadLetterQueue,
+    bus: Arc<Box<dyn EventBus>>,
     executor: Arc<Box<dyn Executor>>,
+    metrics: Arc<Metrics>,
+    health: Arc<Health>,
 }
 
 impl CommandEventLoop {
     pub fn new(
         state: Arc<Box<dyn app::model::state::State>>,
-        channel: Box<dyn Channel<Arc<Box<dyn ExecutableEvent>>>>,
+        bus: Arc<Box<dyn EventBus>>,
         executor: Arc<Box<dyn Executor>>,
+        metrics: Arc<Metrics>,
+        health: Arc<Health>,
     ) -> Self {
-        Self { state, events: Arc::new(Mutex::new(vec![])), channel, executor }
+        Self {
+            state,
+            events: EventQueue::new(),
+            dead_letters: DeadLetterQueue::new(),
+            bus,
+            executor,
+            metrics,
+            health,
+        }
     }
 }
 
 impl CommandEventLoop {
-    fn handle_event_repeats(&self, event: Arc<Box<dyn ExecutableEvent>>) {
-        match event.repeats() {
-            Repeat::Once => {}
-            Repeat::Always => {
-                // return an event back always
-                // TODO potentially memory licking (arc or mutex can handle links permanently)
-                self.events.lock().unwrap().push(event.clone());
-            }
-            Repeat::Times(n) => {
-                if n > 0 {
-                    // return an event back N times
-                    // TODO potentially memory licking (arc or mutex can handle links permanently)
-                    self.events.lock().unwrap().push(event.clone());
+    // next_run puts the event back if it has runs left, the next run is due in the interval
+    // after the start of the current one and when the event is ready again
+    fn next_run(&self, entry: Entry, started_at: Instant) {
+        let remaining = entry.remaining.map(|n| n - 1);
+        if remaining.is_some_and(|n| n <= 0) {
+            return;
+        }
+
+        let at = started_at + entry.interval.max(entry.event.delay());
+        self.events.push(Entry { remaining, attempts: 0, undelivered: None, ..entry }, at);
+    }
+
+    fn handle(&self, mut entry: Entry) {
+        let started_at = Instant::now();
+        let event = entry.event.clone();
+        // a result which is sent again is not another run of the event
+        if entry.undelivered.is_none() {
+            self.bus.publish(Lifecycle::fired(event.name(), event.command().cloned()));
+        }
+        match event.sender() {
+            Some(ready_event) => {
+                // the receiver may have gone away along with its consumer thread
+                if let Err(e) = ready_event.send(event.clone()) {
+                    log::error!(target: "loop", "Error: {} occurred while sending command: {}.", e, event.name());
                 }
+                // back event to the heap if necessary
+                self.next_run(entry, started_at);
+            }
+            None => {
+                // an executed event which has not been delivered is just sent again
+                let result = match entry.undelivered.take() {
+                    Some(exit) => self.executor.deliver(exit),
+                    None => self.execute(event.clone()),
+                };
+                match result {
+                    Ok(_) => {
+                        // back event to the heap if necessary
+                        self.next_run(entry, started_at);
+                        log::debug!(target: "loop", "Command: {} successfully executed.", event.name())
+                    }
+                    Err(e) => self.fail(entry, started_at, e),
+                };
             }
+        };
+    }
+
+    // execute runs the command and delivers its result, the subscribers are notified of the run
+    fn execute(&self, event: Arc<Box<dyn ExecutableEvent>>) -> Result<(), Box<dyn Error>> {
+        let command = event.command().cloned();
+        self.bus.publish(Lifecycle::started(event.name(), command.clone()));
+
+        let started_at = Instant::now();
+        let exit = self.executor.exec(event.clone())?;
+        let duration = exit.duration.unwrap_or(started_at.elapsed());
+        self.bus.publish(Lifecycle::finished(event.name(), command, exit.clone(), duration));
+
+        self.executor.deliver(exit)
+    }
+
+    // fail retries the event by its policy, the one which is out of attempts or may not be
+    // retried is dead-lettered
+    fn fail(&self, entry: Entry, started_at: Instant, error: Box<dyn Error>) {
+        let name = entry.event.name();
+        let reason = error.to_string();
+        let policy = entry.event.retry_policy();
+        let attempts = entry.attempts + 1;
+
+        // a delivery is retried apart from an execution, the command is not executed twice then
+        let (undelivered, is_retried) = match error.downcast::<DeliveryFailedError>() {
+            Ok(error) => (Some(error.into_exit()), policy.is_delivery_retried),
+            Err(_) => (None, policy.is_execution_retried),
+        };
+
+        if is_retried && attempts < policy.max_attempts {
+            let delay = policy.delay(attempts);
+            log::warn!(
+                target: "loop",
+                "Error: {} occurred while execution command: {}, attempt {} of {}, retrying in {:?}.",
+                reason, name, attempts, policy.max_attempts, delay
+            );
+            self.events.push(Entry { attempts, undelivered, ..entry }, Instant::now() + delay);
+            return;
+        }
+
+        log::error!(target: "loop", "Error: {} occurred while execution command: {}, attempt {} is the last one.", reason, name, attempts);
+        if policy.is_dead_lettered {
+            let id = self.dead_letters.push(entry.event.clone(), undelivered, reason, attempts);
+            log::warn!(target: "loop", "Command: {} has been dead-lettered as [{}].", name, id);
+        }
+        // the next run of a repeated event is not cancelled by the failed one
+        self.next_run(entry, started_at);
+    }
+
+    // observe updates the gauges of the queue, it is done when an event is taken or handled,
+    // the loop does not wake up for it
+    fn observe(&self) {
+        self.metrics.event_loop_queue_length.set(&[], self.events.len() as i64);
+        self.metrics.event_loop_pending_events.set(&[], self.events.pending() as i64);
+        self.metrics.event_loop_dead_letters.set(&[], self.dead_letters.len() as i64);
+    }
+
+    // takes the dead letter by id or all of them if it is None
+    fn take_dead_letters(&self, id: Option<u64>) -> Result<Vec<DeadLetter>, DeadLetterNotFoundError> {
+        match id {
+            Some(id) => match self.dead_letters.take(id) {
+                Some(letter) => Ok(vec![letter]),
+                None => Err(DeadLetterNotFoundError::new(id.to_string())),
+            },
+            None => Ok(self.dead_letters.take_all()),
         }
     }
 }
 
 impl EventLoop for CommandEventLoop {
-    // Method serve is infinitely iterating over events and checks whether one or
-    // more events will be ready for send to them Receivers or execute at place
-    // (depends on if an event has a sender into or not). Technical details:
-    // have a timeout between each new iteration in 1 second due to decrease CPU
-    // consumption.
+    // Method serve takes the events by the time they are due at and sends them to their
+    // Receivers or executes them at place (depends on if an event has a sender into or not).
+    // Technical details: the loop sleeps until the nearest event is due or a new one is added,
+    // so an added command is executed at once and nothing is done while there are no events.
     fn serve(&self) {
         loop {
             // check the application status is still active
             if self.state.is_closed() {
                 return;
             }
+            self.health.beat(health::LOOP);
 
-            // (vec![]).drain(0..) -> removes all elements from vector, if an event is not ready, you need put it back
-            for event in self.events.lock().unwrap().drain(0..) {
-                if event.is_ready() {
-                    match event.sender() {
-                        Some(ready_event) => {
-                            // unwrap is safe if you do not have a failures in another thread which consume from receiver
-                            ready_event.send(event.clone()).unwrap();
-                            // back event to the heap if necessary
-                            self.handle_event_repeats(event);
-                        }
-                        None => {
-                            match self.executor.exec(event.clone()) {
-                                Ok(_) => {
-                                    let name = event.name().to_string();
-                                    // back event to the heap if necessary
-                                    self.handle_event_repeats(event);
-                                    println!("Command: {} successfully executed.", name)
-                                }
-                                Err(e) => {
-                                    let name = event.name().to_string();
-                                    self.events.lock().unwrap().push(event);
-                                    println!("Error: {} occurred while execution command: {}.", e, name)
-                                }
-                            };
-                        }
-                    };
-                } else {
-                    // return the event which is not ready back to the vector
-                    self.events.lock().unwrap().push(event);
-                }
+            let Some(entry) = self.events.pop() else {
+                return;
+            };
+            self.observe();
+
+            // an undelivered result is sent regardless of the event readiness
+            if entry.undelivered.is_some() || entry.event.is_ready() {
+                self.handle(entry);
+                self.observe();
+            } else {
+                // return the event which is not ready back to the queue
+                let at = Instant::now() + entry.event.delay().max(MIN_RECHECK_INTERVAL);
+                self.events.push(entry, at);
             }
-            // limitation of CPU consumption (once in a second)
-            std::thread::sleep(std::time::Duration::from_secs(1));
         }
     }
 
-    // add_event - pushes new event into the loop event heap and creates a new one
-    // pair of Sender and Receiver for event.key() if necessary of course.
+    fn close(&self) {
+        self.events.close();
+    }
+
+    // add_event - pushes new event into the loop event queue, it is due at once and
+    // the loop is woken up if it is waiting.
     fn add_event(&self, event: Arc<Box<dyn ExecutableEvent>>) {
-        // handle lock and push new event into the loop
-        self.events.lock().unwrap().push(event);
+        self.events.push(Entry::new(event), Instant::now());
+    }
+
+    fn dead_letters(&self) -> Vec<DeadLetter> {
+        self.dead_letters.list()
+    }
+
+    fn replay(&self, id: Option<u64>) -> Result<usize, DeadLetterNotFoundError> {
+        let letters = self.take_dead_letters(id)?;
+        let n = letters.len();
+        for letter in letters {
+            log::info!(target: "loop", "Dead letter [{}] `{}` is replayed.", letter.id, letter.event.name());
+            // the failed run is replayed alone, the attempts start over
+            let entry = Entry { remaining: Some(1), undelivered: letter.undelivered, ..Entry::new(letter.event) };
+            self.events.push(entry, Instant::now());
+        }
+        Ok(n)
+    }
+
+    fn discard(&self, id: Option<u64>) -> Result<usize, DeadLetterNotFoundError> {
+        let n = self.take_dead_letters(id)?.len();
+        self.observe();
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::model::state::{AppState, State};
+    use crate::domain::error::executor::ExecutionFailedError;
+    use crate::domain::model::command::Executable;
+    use crate::domain::model::event::{Event, EventSender};
+    use crate::domain::model::retry::RetryPolicy;
+    use crate::domain::r#enum::exit_code::ExitCode;
+    use crate::domain::r#enum::topic::Topic;
+    use crate::domain::service::event::bus::{LifecycleBus, Subscriber};
+    use crate::infrastructure::broadcasting::mpsc::channel::Chan;
+    use crate::infrastructure::broadcasting::mpsc::queue::Overflow;
+    use crate::infrastructure::model::command::{Command, Exit};
+    use std::error::Error;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::mpsc::{self, Receiver, Sender};
+    use std::sync::Mutex;
+    use std::thread::{self, JoinHandle};
+
+    // how long a slow command is executed for
+    const SLOW: Duration = Duration::from_millis(500);
+    // add_event is responsive if it returns within it
+    const RESPONSIVE: Duration = Duration::from_millis(50);
+    // the delay between the attempts of a failed event
+    const BACKOFF: Duration = Duration::from_millis(10);
+
+    struct TestEvent {
+        name: String,
+        repeats: Repeat,
+    }
+    impl Executable for TestEvent {
+        fn exec(&self) -> Exit {
+            Exit::new(ExitCode::Success, "".to_string(), "".to_string(), None)
+        }
+    }
+    impl Event for TestEvent {
+        fn name(&self) -> String {
+            self.name.clone()
+        }
+        fn is_ready(&self) -> bool {
+            true
+        }
+        fn repeats(&self) -> Repeat {
+            self.repeats
+        }
+        fn retry_policy(&self) -> RetryPolicy {
+            RetryPolicy { max_attempts: 3, backoff: BACKOFF, max_backoff: BACKOFF, ..RetryPolicy::default() }
+        }
+    }
+    impl ExecutableEvent for TestEvent {
+        fn sender(&self) -> Option<EventSender> {
+            None
+        }
+        fn command(&self) -> Option<&Command> {
+            None
+        }
+    }
+
+    // HandedOverEvent is run by a worker instead of the loop, as the session commands are.
+    struct HandedOverEvent {
+        worker: EventSender,
+    }
+    impl Executable for HandedOverEvent {
+        fn exec(&self) -> Exit {
+            Exit::new(ExitCode::Success, "".to_string(), "".to_string(), None)
+        }
+    }
+    impl Event for HandedOverEvent {
+        fn name(&self) -> String {
+            "handed-over".to_string()
+        }
+        fn is_ready(&self) -> bool {
+            true
+        }
+        fn repeats(&self) -> Repeat {
+            Repeat::Once
+        }
+    }
+    impl ExecutableEvent for HandedOverEvent {
+        fn sender(&self) -> Option<EventSender> {
+            Some(self.worker.clone())
+        }
+        fn command(&self) -> Option<&Command> {
+            None
+        }
+    }
+
+    fn event(name: &str) -> Arc<Box<dyn ExecutableEvent>> {
+        Arc::new(Box::new(TestEvent { name: name.to_string(), repeats: Repeat::Once }))
+    }
+
+    // TestExecutor reports the names of the events it starts, finishes and delivers. The ones named
+    // "slow..." take SLOW to execute, "panicking..." ones fail to execute and the results are not
+    // delivered while the executor is offline.
+    struct TestExecutor {
+        started: Mutex<Sender<String>>,
+        finished: Mutex<Sender<String>>,
+        delivered: Mutex<Sender<String>>,
+        is_online: Arc<AtomicBool>,
+    }
+    impl Executor for TestExecutor {
+        fn exec(&self, cmd: Arc<Box<dyn ExecutableEvent>>) -> Result<Exit, Box<dyn Error>> {
+            let name = cmd.name();
+            let _ = self.started.lock().unwrap().send(name.clone());
+            if name.starts_with("slow") {
+                thread::sleep(SLOW);
+            }
+            if name.starts_with("panicking") {
+                return Err(Box::new(ExecutionFailedError::new(name, "panic".to_string())));
+            }
+            let _ = self.finished.lock().unwrap().send(name.clone());
+            Ok(Exit::new(ExitCode::Success, name, "".to_string(), None))
+        }
+        fn deliver(&self, exit: Exit) -> Result<(), Box<dyn Error>> {
+            if !self.is_online.load(Ordering::SeqCst) {
+                return Err(Box::new(DeliveryFailedError::new(exit, "offline".into())));
+            }
+            let _ = self.delivered.lock().unwrap().send(exit.stdout);
+            Ok(())
+        }
+    }
+
+    // TestSubscriber reports the lifecycle events as "<topic> <name>".
+    struct TestSubscriber {
+        published: Mutex<Sender<String>>,
+    }
+    impl Subscriber for TestSubscriber {
+        fn name(&self) -> String {
+            "test".to_string()
+        }
+        fn topics(&self) -> Vec<Topic> {
+            vec![Topic::EventFired, Topic::CommandStarted, Topic::CommandFinished]
+        }
+        fn notify(&self, event: &Lifecycle) {
+            let _ = self.published.lock().unwrap().send(format!("{} {}", event.topic, event.name));
+        }
+    }
+
+    struct Harness {
+        state: Arc<Box<dyn State>>,
+        event_loop: Arc<CommandEventLoop>,
+        started: Receiver<String>,
+        finished: Receiver<String>,
+        delivered: Receiver<String>,
+        published: Receiver<String>,
+        is_online: Arc<AtomicBool>,
+        thread: JoinHandle<()>,
+    }
+    impl Harness {
+        fn start() -> Harness {
+            let (started_tx, started) = mpsc::channel();
+            let (finished_tx, finished) = mpsc::channel();
+            let (delivered_tx, delivered) = mpsc::channel();
+            let (published_tx, published) = mpsc::channel();
+            let is_online = Arc::new(AtomicBool::new(true));
+            let state: Arc<Box<dyn State>> = Arc::new(Box::new(AppState::new()));
+            let executor: Arc<Box<dyn Executor>> = Arc::new(Box::new(TestExecutor {
+                started: Mutex::new(started_tx),
+                finished: Mutex::new(finished_tx),
+                delivered: Mutex::new(delivered_tx),
+                is_online: is_online.clone(),
+            }));
+            let metrics = Arc::new(Metrics::new());
+            let bus: Arc<Box<dyn EventBus>> = Arc::new(Box::new(LifecycleBus::new(Box::new(Chan::with_capacity(100)), Overflow::Block, metrics.clone())));
+            bus.subscribe(Arc::new(Box::new(TestSubscriber { published: Mutex::new(published_tx) }))).unwrap();
+            let event_loop = Arc::new(CommandEventLoop::new(
+                state.clone(),
+                bus,
+                executor,
+                metrics,
+                Arc::new(Health::new()),
+            ));
+
+            let serving = event_loop.clone();
+            let thread = thread::spawn(move || serving.serve());
+
+            Harness { state, event_loop, started, finished, delivered, published, is_online, thread }
+        }
+
+        // waits until the event loop has dead-lettered n events
+        fn dead_letters(&self, n: usize) -> Vec<DeadLetter> {
+            let deadline = Instant::now() + SLOW;
+            while self.event_loop.dead_letters().len() < n && Instant::now() < deadline {
+                thread::sleep(BACKOFF);
+            }
+            self.event_loop.dead_letters()
+        }
+
+        fn stop(self) {
+            self.state.close();
+            self.event_loop.close();
+            self.thread.join().unwrap();
+        }
+    }
+
+    #[test]
+    fn add_event_stays_responsive_during_a_slow_command() {
+        let harness = Harness::start();
+        harness.event_loop.add_event(event("slow"));
+        assert_eq!(harness.started.recv_timeout(SLOW).unwrap(), "slow");
+
+        for i in 0..100 {
+            let started_at = Instant::now();
+            harness.event_loop.add_event(event(format!("fast-{}", i).as_str()));
+            assert!(started_at.elapsed() < RESPONSIVE, "add_event has been blocked for {:?}", started_at.elapsed());
+        }
+
+        harness.stop();
+    }
+
+    #[test]
+    fn add_event_stays_responsive_for_concurrent_producers() {
+        let harness = Harness::start();
+        harness.event_loop.add_event(event("slow"));
+        assert_eq!(harness.started.recv_timeout(SLOW).unwrap(), "slow");
+
+        let producers: Vec<JoinHandle<Duration>> = (0..8)
+            .map(|producer| {
+                let event_loop = harness.event_loop.clone();
+                thread::spawn(move || {
+                    let mut slowest = Duration::ZERO;
+                    for i in 0..50 {
+                        let started_at = Instant::now();
+                        event_loop.add_event(event(format!("fast-{}-{}", producer, i).as_str()));
+                        slowest = slowest.max(started_at.elapsed());
+                    }
+                    slowest
+                })
+            })
+            .collect();
+        for producer in producers {
+            let slowest = producer.join().unwrap();
+            assert!(slowest < RESPONSIVE, "add_event has been blocked for {:?}", slowest);
+        }
+
+        // each of the events is executed once
+        let mut finished: Vec<String> = (0..401).map(|_| harness.finished.recv_timeout(SLOW * 4).unwrap()).collect();
+        finished.sort();
+        finished.dedup();
+        assert_eq!(finished.len(), 401);
+
+        harness.stop();
+    }
+
+    #[test]
+    fn a_handed_over_event_does_not_hold_the_loop_and_its_result_comes_back_as_an_event() {
+        let harness = Harness::start();
+        let (worker, handed_over) = mpsc::channel::<Arc<Box<dyn ExecutableEvent>>>();
+        let event_loop = harness.event_loop.clone();
+        thread::spawn(move || {
+            for _ in handed_over.iter() {
+                thread::sleep(SLOW);
+                event_loop.add_event(event("handed-over-result"));
+            }
+        });
+
+        harness.event_loop.add_event(Arc::new(Box::new(HandedOverEvent { worker: Arc::new(worker) })));
+        harness.event_loop.add_event(event("fast"));
+
+        let finished: Vec<String> = (0..2).map(|_| harness.finished.recv_timeout(SLOW * 2).unwrap()).collect();
+        assert_eq!(finished, vec!["fast", "handed-over-result"]);
+
+        harness.stop();
+    }
+
+    #[test]
+    fn events_added_during_a_slow_command_are_executed_after_it_in_order() {
+        let harness = Harness::start();
+        harness.event_loop.add_event(event("slow"));
+        assert_eq!(harness.started.recv_timeout(SLOW).unwrap(), "slow");
+        harness.event_loop.add_event(event("first"));
+        harness.event_loop.add_event(event("second"));
+
+        let finished: Vec<String> = (0..3).map(|_| harness.finished.recv_timeout(SLOW * 2).unwrap()).collect();
+        assert_eq!(finished, vec!["slow", "first", "second"]);
+
+        harness.stop();
+    }
+
+    #[test]
+    fn an_added_event_is_executed_without_delay() {
+        let harness = Harness::start();
+        // the loop is idle and waits for the events
+        thread::sleep(Duration::from_millis(100));
+
+        let started_at = Instant::now();
+        harness.event_loop.add_event(event("fast"));
+        assert_eq!(harness.finished.recv_timeout(SLOW).unwrap(), "fast");
+        assert!(started_at.elapsed() < RESPONSIVE, "the event has been executed in {:?}", started_at.elapsed());
+
+        harness.stop();
+    }
+
+    #[test]
+    fn a_repeated_event_is_not_executed_more_often_than_the_repeat_interval() {
+        let harness = Harness::start();
+        harness.event_loop.add_event(Arc::new(Box::new(TestEvent { name: "always".to_string(), repeats: Repeat::Always })));
+
+        assert_eq!(harness.finished.recv_timeout(SLOW).unwrap(), "always");
+        assert!(harness.finished.recv_timeout(REPEAT_INTERVAL - Duration::from_millis(100)).is_err());
+        assert_eq!(harness.finished.recv_timeout(REPEAT_INTERVAL).unwrap(), "always");
+
+        harness.stop();
+    }
+
+    #[test]
+    fn an_event_repeated_n_times_is_executed_exactly_n_times() {
+        let harness = Harness::start();
+        harness.event_loop.add_event(Arc::new(Box::new(TestEvent { name: "twice".to_string(), repeats: Repeat::Times(2) })));
+
+        assert_eq!(harness.finished.recv_timeout(SLOW).unwrap(), "twice");
+        assert_eq!(harness.finished.recv_timeout(REPEAT_INTERVAL + SLOW).unwrap(), "twice");
+        assert!(harness.finished.recv_timeout(REPEAT_INTERVAL + SLOW).is_err());
+        assert!(harness.event_loop.events.is_empty());
+
+        harness.stop();
+    }
+
+    #[test]
+    fn an_event_repeated_every_interval_is_executed_n_times_in_the_interval() {
+        let harness = Harness::start();
+        let interval = Duration::from_millis(200);
+        harness.event_loop.add_event(Arc::new(Box::new(TestEvent { name: "every".to_string(), repeats: Repeat::Every(interval, 3) })));
+
+        let started_at = Instant::now();
+        for _ in 0..3 {
+            assert_eq!(harness.finished.recv_timeout(SLOW).unwrap(), "every");
+        }
+        assert!(started_at.elapsed() >= interval * 2, "the runs have taken {:?}", started_at.elapsed());
+        assert!(harness.finished.recv_timeout(SLOW).is_err());
+
+        harness.stop();
+    }
+
+    #[test]
+    fn the_lifecycle_of_an_event_is_published_to_the_subscribers_of_its_topics() {
+        let harness = Harness::start();
+        harness.event_loop.add_event(event("fast"));
+        harness.event_loop.add_event(event("panicking"));
+
+        let published: Vec<String> = (0..5).map(|_| harness.published.recv_timeout(SLOW).unwrap()).collect();
+        assert_eq!(
+            published,
+            vec![
+                "event.fired fast",
+                "command.started fast",
+                "command.finished fast",
+                "event.fired panicking",
+                "command.started panicking",
+            ]
+        );
+        // a command which has failed to execute has not finished
+        assert!(harness.published.recv_timeout(RESPONSIVE).is_err());
+
+        harness.stop();
+    }
+
+    #[test]
+    fn a_failed_delivery_is_retried_without_executing_the_command_again() {
+        let harness = Harness::start();
+        harness.is_online.store(false, Ordering::SeqCst);
+        harness.event_loop.add_event(event("undelivered"));
+
+        let letters = harness.dead_letters(1);
+        assert_eq!(letters.len(), 1);
+        assert_eq!(letters[0].attempts, 3);
+        assert!(letters[0].undelivered.is_some());
+        // the command has been executed once, the delivery has been retried
+        assert_eq!(harness.started.try_iter().collect::<Vec<String>>(), vec!["undelivered"]);
+
+        harness.stop();
+    }
+
+    #[test]
+    fn a_replayed_dead_letter_is_delivered_without_executing_it_again() {
+        let harness = Harness::start();
+        harness.is_online.store(false, Ordering::SeqCst);
+        harness.event_loop.add_event(event("undelivered"));
+        let id = harness.dead_letters(1)[0].id;
+
+        harness.is_online.store(true, Ordering::SeqCst);
+        assert_eq!(harness.event_loop.replay(Some(id)).unwrap(), 1);
+        assert_eq!(harness.delivered.recv_timeout(SLOW).unwrap(), "undelivered");
+        assert_eq!(harness.started.try_iter().count(), 1);
+        assert!(harness.event_loop.dead_letters().is_empty());
+        assert!(harness.event_loop.replay(Some(id)).is_err());
+
+        harness.stop();
+    }
+
+    #[test]
+    fn a_failed_execution_is_dead_lettered_without_retries() {
+        let harness = Harness::start();
+        harness.event_loop.add_event(event("panicking"));
+
+        let letters = harness.dead_letters(1);
+        assert_eq!(letters.len(), 1);
+        assert_eq!(letters[0].attempts, 1);
+        assert!(letters[0].undelivered.is_none());
+        assert_eq!(harness.event_loop.discard(None).unwrap(), 1);
+        assert!(harness.event_loop.dead_letters().is_empty());
+
+        harness.stop();
     }
 }
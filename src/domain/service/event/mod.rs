@@ -1 +1,4 @@
+pub mod bus;
+pub mod dead_letter;
 pub mod r#loop;
+pub mod queue;
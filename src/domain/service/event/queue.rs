@@ -0,0 +1,192 @@
+use std::cmp::{Ordering, Reverse};
+use std::collections::BinaryHeap;
+use std::sync::{Condvar, Mutex};
+use std::time::Instant;
+
+// Scheduled is an entry along with the time it is due at, the entries which are due at the same
+// time are taken in the order they have been pushed.
+struct Scheduled<T> {
+    at: Instant,
+    seq: u64,
+    // the entry was not due yet when it was pushed
+    is_delayed: bool,
+    entry: T,
+}
+impl<T> PartialEq for Scheduled<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+impl<T> Eq for Scheduled<T> {}
+impl<T> PartialOrd for Scheduled<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+impl<T> Ord for Scheduled<T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.at, self.seq).cmp(&(other.at, other.seq))
+    }
+}
+
+struct Heap<T> {
+    // min-heap by the due time
+    entries: BinaryHeap<Reverse<Scheduled<T>>>,
+    seq: u64,
+    // how many of the entries were not due when they were pushed, so the count is not made by a scan
+    delayed: usize,
+    is_closed: bool,
+}
+
+// EventQueue keeps the entries of the events by the time they are due at. The lock is held only
+// to push or to take an entry, so the producers are never blocked by an event which is being executed.
+pub struct EventQueue<T> {
+    heap: Mutex<Heap<T>>,
+    // wakes the waiting consumer up when an event is pushed or the queue is closed
+    pushed: Condvar,
+}
+
+impl<T> EventQueue<T> {
+    pub fn new() -> EventQueue<T> {
+        EventQueue {
+            heap: Mutex::new(Heap { entries: BinaryHeap::new(), seq: 0, delayed: 0, is_closed: false }),
+            pushed: Condvar::new(),
+        }
+    }
+
+    // push schedules the entry at the time, the waiting consumer is woken up if it is due earlier
+    // than the ones it waits for
+    pub fn push(&self, entry: T, at: Instant) {
+        let mut heap = self.heap.lock().unwrap();
+        heap.seq += 1;
+        let seq = heap.seq;
+        let is_delayed = at > Instant::now();
+        if is_delayed {
+            heap.delayed += 1;
+        }
+        heap.entries.push(Reverse(Scheduled { at, seq, is_delayed, entry }));
+        self.pushed.notify_one();
+    }
+
+    // pop returns the earliest due entry, it sleeps until the next entry is due or an earlier one
+    // is pushed and returns None once the queue is closed
+    pub fn pop(&self) -> Option<T> {
+        let mut heap = self.heap.lock().unwrap();
+        loop {
+            if heap.is_closed {
+                return None;
+            }
+
+            let now = Instant::now();
+            heap = match heap.entries.peek() {
+                Some(Reverse(next)) if next.at <= now => {
+                    let Reverse(scheduled) = heap.entries.pop()?;
+                    if scheduled.is_delayed {
+                        heap.delayed -= 1;
+                    }
+                    return Some(scheduled.entry);
+                }
+                Some(Reverse(next)) => {
+                    let timeout = next.at - now;
+                    self.pushed.wait_timeout(heap, timeout).unwrap().0
+                }
+                None => self.pushed.wait(heap).unwrap(),
+            };
+        }
+    }
+
+    // close wakes the consumer up, pop returns None since then and the entries are kept
+    pub fn close(&self) {
+        self.heap.lock().unwrap().is_closed = true;
+        self.pushed.notify_all();
+    }
+
+    pub fn len(&self) -> usize {
+        self.heap.lock().unwrap().entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.heap.lock().unwrap().entries.is_empty()
+    }
+
+    // returns how many of the entries have been scheduled ahead (e.g. the next runs and the retries)
+    // and have not been taken yet
+    pub fn pending(&self) -> usize {
+        self.heap.lock().unwrap().delayed
+    }
+}
+
+impl<T> Default for EventQueue<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::Duration;
+
+    const STEP: Duration = Duration::from_millis(50);
+
+    #[test]
+    fn the_entries_are_taken_by_their_due_time_then_by_their_order() {
+        let queue = EventQueue::new();
+        let now = Instant::now();
+        queue.push("second", now);
+        queue.push("delayed", now + STEP);
+        queue.push("first", now - STEP);
+        queue.push("third", now);
+
+        let taken: Vec<&str> = (0..4).filter_map(|_| queue.pop()).collect();
+
+        assert_eq!(taken, vec!["first", "second", "third", "delayed"]);
+        assert!(now.elapsed() >= STEP);
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn the_delayed_entries_are_counted_until_they_are_taken() {
+        let queue = EventQueue::new();
+        queue.push(1, Instant::now());
+        queue.push(2, Instant::now() + STEP);
+        queue.push(3, Instant::now() + STEP * 2);
+        assert_eq!((queue.len(), queue.pending()), (3, 2));
+
+        queue.pop();
+        assert_eq!((queue.len(), queue.pending()), (2, 2));
+        queue.pop();
+        assert_eq!((queue.len(), queue.pending()), (1, 1));
+    }
+
+    #[test]
+    fn a_waiting_consumer_is_woken_up_by_an_earlier_entry() {
+        let queue = Arc::new(EventQueue::new());
+        queue.push("late", Instant::now() + Duration::from_secs(60));
+
+        let consumer = {
+            let queue = queue.clone();
+            thread::spawn(move || queue.pop())
+        };
+        thread::sleep(STEP);
+        queue.push("now", Instant::now());
+
+        assert_eq!(consumer.join().unwrap(), Some("now"));
+    }
+
+    #[test]
+    fn close_wakes_up_a_consumer_of_an_empty_queue() {
+        let queue: Arc<EventQueue<u8>> = Arc::new(EventQueue::new());
+
+        let consumer = {
+            let queue = queue.clone();
+            thread::spawn(move || queue.pop())
+        };
+        thread::sleep(STEP);
+        queue.close();
+
+        assert_eq!(consumer.join().unwrap(), None);
+    }
+}
@@ -1,10 +1,21 @@
+use crate::domain::error::executor::{DeliveryFailedError, ExecutionFailedError, PartiallyDeliveredError};
 use crate::domain::model::event::ExecutableEvent;
+use crate::infrastructure::model::command::Exit;
 use crate::infrastructure::service::executor::responder::Responder;
+use chrono::Local;
+use std::any::Any;
 use std::error::Error;
+use std::panic::{self, AssertUnwindSafe};
 use std::sync::Arc;
+use std::time::Instant;
 
 pub trait Executor: Send + Sync {
-    fn exec(&self, cmd: Arc<Box<dyn ExecutableEvent>>) -> Result<(), Box<dyn Error>>;
+    // exec executes the command and returns its result, it fails with ExecutionFailedError if
+    // the command has not been executed
+    fn exec(&self, cmd: Arc<Box<dyn ExecutableEvent>>) -> Result<Exit, Box<dyn Error>>;
+    // deliver sends the result of the command, it fails with DeliveryFailedError if the result
+    // has not been sent
+    fn deliver(&self, exit: Exit) -> Result<(), Box<dyn Error>>;
 }
 pub struct CommandExecutor {
     responder: Box<dyn Responder>,
@@ -15,7 +26,49 @@ impl CommandExecutor {
     }
 }
 impl Executor for CommandExecutor {
-    fn exec(&self, cmd: Arc<Box<dyn ExecutableEvent>>) -> Result<(), Box<dyn Error>> {
-        self.responder.respond(cmd.exec())
+    fn exec(&self, cmd: Arc<Box<dyn ExecutableEvent>>) -> Result<Exit, Box<dyn Error>> {
+        let started_at = Instant::now();
+        // a panicked command fails alone instead of taking the loop down with it
+        let exit = match panic::catch_unwind(AssertUnwindSafe(|| cmd.exec())) {
+            Ok(exit) => exit,
+            Err(payload) => return Err(Box::new(ExecutionFailedError::new(cmd.name(), panic_message(payload)))),
+        };
+
+        // a result which has been made off the loop (e.g. by a session) keeps its own timing
+        let exit = Exit {
+            finished_at: exit.finished_at.or(Some(Local::now())),
+            duration: exit.duration.or(Some(started_at.elapsed())),
+            ..exit
+        };
+
+        let command = cmd.command();
+        log::info!(
+            target: "executor",
+            chat_id = command.map(|command| command.message.chat_id),
+            message_id = command.map(|command| command.message.id),
+            command = command.map(|command| command.r#type.to_string()),
+            exit_code = exit.code.to_string(),
+            duration_ms = exit.duration.unwrap_or_default().as_millis() as u64;
+            "Command `{}` has been executed.", cmd.name()
+        );
+
+        Ok(exit)
+    }
+
+    fn deliver(&self, exit: Exit) -> Result<(), Box<dyn Error>> {
+        self.responder.respond(exit.clone()).map_err(|e| {
+            // the responders which have sent the result are skipped by the retry
+            let delivered = e.downcast_ref::<PartiallyDeliveredError>().map(|e| e.delivered().to_vec()).unwrap_or_default();
+            let exit = Exit { delivered: [exit.delivered, delivered].concat(), ..exit };
+            Box::new(DeliveryFailedError::new(exit, e)) as Box<dyn Error>
+        })
+    }
+}
+
+// returns the message of a panic, it is a string unless the panic has been raised with another value
+fn panic_message(payload: Box<dyn Any + Send>) -> String {
+    match payload.downcast::<String>() {
+        Ok(message) => *message,
+        Err(payload) => payload.downcast_ref::<&str>().map(|message| message.to_string()).unwrap_or("panic".to_string()),
     }
 }
@@ -0,0 +1,312 @@
+use crate::app::cfg::handle::CfgHandle;
+use crate::domain::error::job::{JobFinishedError, JobNotFoundError};
+use crate::domain::model::command::JobFinishedCmd;
+use crate::domain::model::job::Job;
+use crate::domain::model::message::Message;
+use crate::domain::r#enum::job::JobState;
+use crate::domain::service::event::r#loop::EventLoop;
+use crate::domain::service::process::tracker::ProcessTracker;
+use crate::infrastructure::helper::signal::kill_group;
+use chrono::Local;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::io::{Read, Write};
+use std::os::unix::process::{CommandExt, ExitStatusExt};
+use std::process::{Child, Command as OsCmd, Stdio};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{channel, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+// How many finished jobs are kept for /jobs and /tail, the oldest ones are forgotten first.
+const MAX_FINISHED_JOBS: usize = 20;
+// How long the output of an exited job is still read, a detached grandchild may hold the pipes forever.
+const OUTPUT_GRACE: Duration = Duration::from_secs(1);
+// Size of a single read from the job pipes.
+const CHUNK_SIZE: usize = 8 * 1024;
+
+pub trait JobRegistryTrait: Send + Sync {
+    // spawns the process as a background job of the chat
+    fn start(&self, cmd: OsCmd, command: String, message: Message, stdin: Option<Vec<u8>>) -> Result<Arc<Job>, Box<dyn Error>>;
+    // returns all known jobs ordered by their ids
+    fn list(&self) -> Vec<Arc<Job>>;
+    // returns the job by its id like "1" or "%1"
+    fn get(&self, id: &str) -> Result<Arc<Job>, Box<dyn Error>>;
+    // sends the signal to the process group of the running job
+    fn kill(&self, id: &str, signal: i32) -> Result<Arc<Job>, Box<dyn Error>>;
+}
+
+pub struct JobRegistry {
+    cfg: CfgHandle,
+    event_loop: Arc<Box<dyn EventLoop>>,
+    tracker: Arc<ProcessTracker>,
+    jobs: Mutex<BTreeMap<u64, Arc<Job>>>,
+    next_id: AtomicU64,
+}
+
+impl JobRegistry {
+    pub fn new(cfg: CfgHandle, event_loop: Arc<Box<dyn EventLoop>>, tracker: Arc<ProcessTracker>) -> JobRegistry {
+        JobRegistry {
+            cfg,
+            event_loop,
+            tracker,
+            jobs: Mutex::new(BTreeMap::new()),
+            next_id: AtomicU64::new(1),
+        }
+    }
+
+    // forgets the oldest finished jobs which are over the limit
+    fn prune(jobs: &mut BTreeMap<u64, Arc<Job>>) {
+        let finished: Vec<u64> = jobs
+            .iter()
+            .filter(|(_, job)| job.state() != JobState::Running)
+            .map(|(id, _)| *id)
+            .collect();
+
+        for id in finished.iter().take(finished.len().saturating_sub(MAX_FINISHED_JOBS)) {
+            jobs.remove(id);
+        }
+    }
+
+    // reads the pipe into the job output until it is closed, then reports to done
+    fn read_output(job: Arc<Job>, mut pipe: impl Read + Send + 'static, done: Sender<()>) {
+        thread::spawn(move || {
+            let mut chunk = [0u8; CHUNK_SIZE];
+            loop {
+                match pipe.read(&mut chunk) {
+                    Ok(0) => break,
+                    Ok(n) => job.write_output(&chunk[..n]),
+                    Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+                    Err(_) => break,
+                }
+            }
+            let _ = done.send(());
+        });
+    }
+
+    // waits for the job process, marks the job finished and notifies the chat
+    fn wait(job: Arc<Job>, mut child: Child, event_loop: Arc<Box<dyn EventLoop>>, tracker: Arc<ProcessTracker>) {
+        let (done, readers) = channel();
+        let mut pipes = 0;
+        if let Some(stdout) = child.stdout.take() {
+            Self::read_output(job.clone(), stdout, done.clone());
+            pipes += 1;
+        }
+        if let Some(stderr) = child.stderr.take() {
+            Self::read_output(job.clone(), stderr, done);
+            pipes += 1;
+        }
+
+        thread::spawn(move || {
+            let state = match child.wait() {
+                Ok(status) => match (status.code(), status.signal()) {
+                    (Some(code), _) => JobState::Exited(code),
+                    (None, Some(signal)) => JobState::Signaled(signal),
+                    (None, None) => JobState::Exited(-1),
+                },
+                Err(e) => {
+                    log::error!(target: "jobs", chat_id = job.message.chat_id, job_id = job.id; "Failed to wait for job: {}.", e);
+                    JobState::Exited(-1)
+                }
+            };
+
+            // marked at once, the process group id must not be signaled after the process is reaped
+            job.finish(state);
+            tracker.untrack(job.pid);
+
+            // the remaining output is picked up before the notification is sent
+            for _ in 0..pipes {
+                if readers.recv_timeout(OUTPUT_GRACE).is_err() {
+                    break;
+                }
+            }
+
+            log::info!(
+                target: "jobs",
+                chat_id = job.message.chat_id,
+                job_id = job.id,
+                duration_ms = job.uptime().as_millis() as u64;
+                "Job `{}` has finished: {}.", job.command, state
+            );
+            event_loop.add_event(Arc::new(Box::new(JobFinishedCmd::new(job))));
+        });
+    }
+}
+
+impl JobRegistryTrait for JobRegistry {
+    fn start(&self, mut cmd: OsCmd, command: String, message: Message, stdin: Option<Vec<u8>>) -> Result<Arc<Job>, Box<dyn Error>> {
+        // the job leads its own process group, so /kill reaches the processes it has spawned
+        cmd.process_group(0)
+            .stdin(if stdin.is_some() { Stdio::piped() } else { Stdio::null() })
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+
+        let mut child = cmd.spawn()?;
+        self.tracker.track(child.id());
+
+        if let (Some(mut pipe), Some(stdin)) = (child.stdin.take(), stdin) {
+            thread::spawn(move || {
+                // the job may exit without reading the whole input (broken pipe)
+                let _ = pipe.write_all(&stdin);
+            });
+        }
+
+        let job = Arc::new(Job::new(
+            self.next_id.fetch_add(1, Ordering::SeqCst),
+            child.id(),
+            message,
+            command,
+            Local::now().naive_local(),
+            self.cfg.get().job_output_buffer_bytes,
+        ));
+
+        {
+            let mut jobs = self.jobs.lock().unwrap();
+            jobs.insert(job.id, job.clone());
+            Self::prune(&mut jobs);
+        }
+
+        Self::wait(job.clone(), child, self.event_loop.clone(), self.tracker.clone());
+
+        Ok(job)
+    }
+
+    fn list(&self) -> Vec<Arc<Job>> {
+        self.jobs.lock().unwrap().values().cloned().collect()
+    }
+
+    fn get(&self, id: &str) -> Result<Arc<Job>, Box<dyn Error>> {
+        let job = id
+            .trim()
+            .trim_start_matches('%')
+            .parse::<u64>()
+            .ok()
+            .and_then(|number| self.jobs.lock().unwrap().get(&number).cloned());
+
+        match job {
+            Some(job) => Ok(job),
+            None => Err(Box::new(JobNotFoundError::new(id.trim().to_string()))),
+        }
+    }
+
+    fn kill(&self, id: &str, signal: i32) -> Result<Arc<Job>, Box<dyn Error>> {
+        let job = self.get(id)?;
+        if job.state() != JobState::Running {
+            return Err(Box::new(JobFinishedError::new(job.id)));
+        }
+
+        kill_group(job.pid, signal)?;
+
+        Ok(job)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::cfg::cfg::testing::with_cfg;
+    use crate::domain::error::executor::DeadLetterNotFoundError;
+    use crate::domain::model::event::ExecutableEvent;
+    use crate::domain::model::message::User;
+    use crate::domain::model::dead_letter::DeadLetter;
+    use std::sync::mpsc::Receiver;
+
+    // TestLoop reports the events of the finished jobs instead of executing them
+    struct TestLoop {
+        finished: Mutex<Sender<()>>,
+    }
+    impl EventLoop for TestLoop {
+        fn serve(&self) {}
+        fn close(&self) {}
+        fn add_event(&self, _: Arc<Box<dyn ExecutableEvent>>) {
+            let _ = self.finished.lock().unwrap().send(());
+        }
+        fn dead_letters(&self) -> Vec<DeadLetter> {
+            vec![]
+        }
+        fn replay(&self, _: Option<u64>) -> Result<usize, DeadLetterNotFoundError> {
+            Ok(0)
+        }
+        fn discard(&self, _: Option<u64>) -> Result<usize, DeadLetterNotFoundError> {
+            Ok(0)
+        }
+    }
+
+    fn registry(vars: &[(&str, &str)]) -> (JobRegistry, Receiver<()>) {
+        let (sender, finished) = channel();
+        let event_loop: Arc<Box<dyn EventLoop>> = Arc::new(Box::new(TestLoop { finished: Mutex::new(sender) }));
+        let registry = with_cfg(vars, |cfg| JobRegistry::new(CfgHandle::new(cfg.unwrap()), event_loop, Arc::new(ProcessTracker::new())));
+        (registry, finished)
+    }
+
+    fn start(registry: &JobRegistry, script: &str) -> Arc<Job> {
+        let message = Message {
+            id: 1,
+            chat_id: 1,
+            user: User { id: 1, username: "user".to_string() },
+            date: 0,
+            text: format!("/bg {}", script),
+            caption: None,
+            file: None,
+            reply_to: None,
+        };
+        let mut cmd = OsCmd::new("sh");
+        cmd.args(["-c", script]);
+        registry.start(cmd, script.to_string(), message, None).unwrap()
+    }
+
+    #[test]
+    fn the_jobs_get_sequential_ids_and_are_found_by_them() {
+        let (registry, finished) = registry(&[]);
+
+        let first = start(&registry, "true");
+        let second = start(&registry, "true");
+        finished.recv_timeout(Duration::from_secs(5)).unwrap();
+        finished.recv_timeout(Duration::from_secs(5)).unwrap();
+
+        assert_eq!((first.id, second.id), (1, 2));
+        assert_eq!(registry.get("%2").unwrap().id, 2);
+        assert_eq!(registry.get(" 1 ").unwrap().id, 1);
+        assert!(registry.get("3").is_err());
+        assert!(registry.get("one").is_err());
+        assert_eq!(registry.list().iter().map(|job| job.id).collect::<Vec<u64>>(), vec![1, 2]);
+    }
+
+    #[test]
+    fn the_job_output_and_exit_code_are_kept_once_it_has_finished() {
+        let (registry, finished) = registry(&[]);
+
+        let job = start(&registry, "echo out; echo err >&2; exit 3");
+        finished.recv_timeout(Duration::from_secs(5)).unwrap();
+
+        assert_eq!(job.state(), JobState::Exited(3));
+        let (output, dropped) = job.output();
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.contains("out\n") && output.contains("err\n"), "{}", output);
+        assert_eq!(dropped, 0);
+    }
+
+    #[test]
+    fn just_the_tail_of_the_job_output_is_kept() {
+        let (registry, finished) = registry(&[("JOB_OUTPUT_BUFFER_BYTES", "4")]);
+
+        let job = start(&registry, "printf 0123456789");
+        finished.recv_timeout(Duration::from_secs(5)).unwrap();
+
+        assert_eq!(job.output(), (b"6789".to_vec(), 6));
+    }
+
+    #[test]
+    fn a_running_job_is_killed_and_a_finished_one_is_not() {
+        let (registry, finished) = registry(&[]);
+
+        let job = start(&registry, "sleep 30");
+        registry.kill("%1", libc::SIGTERM).unwrap();
+        finished.recv_timeout(Duration::from_secs(5)).unwrap();
+
+        assert_eq!(job.state(), JobState::Signaled(libc::SIGTERM));
+        assert!(registry.kill("1", libc::SIGTERM).err().unwrap().is::<JobFinishedError>());
+        assert!(registry.kill("2", libc::SIGTERM).err().unwrap().is::<JobNotFoundError>());
+    }
+}
@@ -1,4 +1,9 @@
+pub mod audit;
 pub mod event;
 pub mod executor;
+pub mod job;
+pub mod process;
 pub mod runner;
+pub mod session;
+pub mod transport;
 pub mod wife;
@@ -0,0 +1,257 @@
+use crate::app::cfg::cfg::SECRET_VARS;
+use crate::app::cfg::handle::CfgHandle;
+use crate::domain::error::exec::{EmptyCommandError, RunAsDeniedError, UnknownGroupError, UnknownUserError, WorkingDirNotFoundError};
+use crate::domain::model::exec::ExecOptions;
+use crate::domain::model::limits::Limits;
+use crate::domain::r#enum::exec_mode::ExecMode;
+use crate::domain::service::process::tracker::ProcessTracker;
+use crate::infrastructure::helper::rlimit::{set_rlimit, Resource};
+use crate::infrastructure::helper::user::{drop_privileges, find_group, find_user};
+use std::env;
+use std::error::Error;
+use std::os::unix::process::CommandExt;
+use std::path::Path;
+use std::process::Command as OsCmd;
+use std::sync::Arc;
+use std::time::Duration;
+
+// ProcessBuilder is the single place where the processes spawned on behalf of a chat are
+// made, so the cfg defaults and the per-invocation options are applied to all of them.
+pub struct ProcessBuilder {
+    cfg: CfgHandle,
+    tracker: Arc<ProcessTracker>,
+}
+
+impl ProcessBuilder {
+    pub fn new(cfg: CfgHandle, tracker: Arc<ProcessTracker>) -> ProcessBuilder {
+        ProcessBuilder { cfg, tracker }
+    }
+
+    // returns the tracker of the processes which are spawned of the built commands
+    pub fn tracker(&self) -> &ProcessTracker {
+        self.tracker.as_ref()
+    }
+
+    // argv resolves the input string into a program and its arguments depending on the mode,
+    // all modes are spawned by the same build below, so any check on argv is shared by them.
+    pub fn argv(&self, mode: ExecMode, input: &str) -> Result<Vec<String>, String> {
+        match mode {
+            ExecMode::Direct => shlex::split(input)
+                .ok_or("Failed to split the command, please check the quotes and try again.".to_string()),
+            ExecMode::Shell => {
+                let script = input.trim();
+                if script.is_empty() {
+                    return Ok(vec![]);
+                }
+                // the shell (with its arguments) receives a script as the last argument
+                let mut argv = self.cfg.get().exec_shell.clone();
+                if argv.is_empty() {
+                    return Err("The shell is not configured, please check the EXEC_SHELL variable.".to_string());
+                }
+
+                argv.push(script.to_string());
+                Ok(argv)
+            }
+        }
+    }
+
+    // returns how many bytes the input of a command may take
+    pub fn max_stdin_bytes(&self) -> u64 {
+        self.cfg.get().exec_max_stdin_bytes
+    }
+
+    // returns the timeout of a command or None if commands may run forever
+    pub fn timeout(&self) -> Option<Duration> {
+        Some(self.cfg.get().exec_timeout).filter(|timeout| !timeout.is_zero())
+    }
+
+    // returns the default limits overridden by the ones of the user role (if any)
+    pub fn limits(&self, user_id: i64) -> Limits {
+        self.cfg.get().limits(user_id)
+    }
+
+    // build makes a process of argv for the user with the options applied on top of the cfg defaults
+    pub fn build(
+        &self,
+        argv: &[String],
+        options: &ExecOptions,
+        limits: &Limits,
+        user_id: i64,
+    ) -> Result<OsCmd, Box<dyn Error>> {
+        let Some((program, args)) = argv.split_first() else {
+            return Err(Box::new(EmptyCommandError::new()));
+        };
+
+        let cfg = self.cfg.get();
+        let mut cmd = OsCmd::new(program);
+        cmd.args(args);
+
+        // the daemon environment may hold secrets, so just the allowed variables are inherited
+        // and the ones of the daemon itself are not inherited even if they are allowed
+        if let Some(allowlist) = &cfg.exec_env_allowlist {
+            cmd.env_clear();
+            for name in allowlist {
+                if let Some(value) = env::var_os(name) {
+                    cmd.env(name, value);
+                }
+            }
+        }
+        for name in SECRET_VARS {
+            cmd.env_remove(name);
+        }
+
+        // as= would lift the configured privilege drop, so just the admins may choose another user
+        if let Some(user) = &options.user {
+            if Some(user) != cfg.exec_run_as_user.as_ref() && !cfg.is_admin(user_id) {
+                return Err(Box::new(RunAsDeniedError::new(user.clone())));
+            }
+        }
+        let user = options.user.as_ref().or(cfg.exec_run_as_user.as_ref());
+        let account = match user {
+            Some(user) => match find_user(user)? {
+                Some(account) => Some(account),
+                None => return Err(Box::new(UnknownUserError::new(user.clone()))),
+            },
+            None => None,
+        };
+        let group = match &cfg.exec_run_as_group {
+            Some(group) => match find_group(group)? {
+                Some(gid) => Some(gid),
+                None => return Err(Box::new(UnknownGroupError::new(group.clone()))),
+            },
+            None => None,
+        };
+
+        if let Some(account) = &account {
+            cmd.env("HOME", &account.home)
+                .env("USER", &account.name)
+                .env("LOGNAME", &account.name);
+        }
+        for (key, value) in &options.env {
+            cmd.env(key, value);
+        }
+
+        if let Some(cwd) = options.cwd.as_ref().or(cfg.exec_working_dir.as_ref()) {
+            if !Path::new(cwd).is_dir() {
+                return Err(Box::new(WorkingDirNotFoundError::new(cwd.clone())));
+            }
+            cmd.current_dir(cwd);
+        }
+
+        let rlimits: Vec<(Resource, u64)> = [
+            (Resource::Cpu, limits.cpu_seconds),
+            (Resource::AddressSpace, limits.address_space),
+            (Resource::OpenFiles, limits.open_files),
+            (Resource::Processes, limits.processes),
+        ]
+            .into_iter()
+            .filter_map(|(resource, value)| value.filter(|value| *value > 0).map(|value| (resource, value)))
+            .collect();
+        if !rlimits.is_empty() {
+            // safety: set_rlimit makes async-signal-safe calls only
+            unsafe {
+                cmd.pre_exec(move || {
+                    for (resource, value) in &rlimits {
+                        set_rlimit(*resource, *value)?;
+                    }
+                    Ok(())
+                });
+            }
+        }
+
+        if account.is_some() || group.is_some() {
+            let uid = account.as_ref().map(|account| account.uid);
+            let gid = group.or(account.as_ref().map(|account| account.gid));
+            let groups = account.map(|account| account.groups).unwrap_or_default();
+            // safety: drop_privileges makes async-signal-safe calls only
+            unsafe {
+                cmd.pre_exec(move || drop_privileges(uid, gid, &groups));
+            }
+        }
+
+        Ok(cmd)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::cfg::cfg::testing::with_cfg;
+
+    fn builder(vars: &[(&str, &str)]) -> ProcessBuilder {
+        with_cfg(vars, |cfg| ProcessBuilder::new(CfgHandle::new(cfg.unwrap()), Arc::new(ProcessTracker::new())))
+    }
+
+    #[test]
+    fn the_shell_mode_passes_the_line_to_the_shell_as_a_script() {
+        let builder = builder(&[("EXEC_SHELL", "/bin/bash -c")]);
+
+        let argv = builder.argv(ExecMode::Shell, "  ls -la | grep 'a b' > out  ").unwrap();
+
+        assert_eq!(argv, vec!["/bin/bash", "-c", "ls -la | grep 'a b' > out"]);
+    }
+
+    #[test]
+    fn the_direct_mode_splits_the_line_into_the_argv() {
+        let builder = builder(&[]);
+
+        let argv = builder.argv(ExecMode::Direct, "ls -la | grep 'a b'").unwrap();
+
+        assert_eq!(argv, vec!["ls", "-la", "|", "grep", "a b"]);
+    }
+
+    #[test]
+    fn an_empty_line_makes_an_empty_argv_in_both_modes() {
+        let builder = builder(&[]);
+
+        assert!(builder.argv(ExecMode::Shell, "   ").unwrap().is_empty());
+        assert!(builder.argv(ExecMode::Direct, "   ").unwrap().is_empty());
+    }
+
+    // the variables are inherited by the build, so it is made while they are set
+    fn build(vars: &[(&str, &str)], options: &ExecOptions) -> OsCmd {
+        with_cfg(vars, |cfg| {
+            let builder = ProcessBuilder::new(CfgHandle::new(cfg.unwrap()), Arc::new(ProcessTracker::new()));
+            builder.build(&["env".to_string()], options, &Limits::default(), 1).unwrap()
+        })
+    }
+
+    fn env(cmd: &OsCmd, name: &str) -> Option<Option<String>> {
+        cmd.get_envs()
+            .find(|(key, _)| *key == name)
+            .map(|(_, value)| value.map(|value| value.to_string_lossy().to_string()))
+    }
+
+    #[test]
+    fn the_secrets_of_the_daemon_are_not_inherited_by_the_commands() {
+        // the whole environment is inherited, so the secrets are removed of it
+        let cmd = build(&[("WEBHOOK_SECRET", "secret")], &ExecOptions::default());
+        for name in SECRET_VARS {
+            assert_eq!(env(&cmd, name), Some(None), "{} is inherited", name);
+        }
+
+        // the environment is cleared, so the secrets are not set even if they are allowed
+        let cmd = build(&[("WEBHOOK_SECRET", "secret"), ("EXEC_ENV_ALLOWLIST", "TG_TOKEN,WEBHOOK_SECRET")], &ExecOptions::default());
+        for name in SECRET_VARS {
+            assert_eq!(env(&cmd, name), None, "{} is inherited", name);
+        }
+    }
+
+    #[test]
+    fn just_the_allowed_variables_are_inherited() {
+        let options = ExecOptions { env: vec![("FOO".to_string(), "bar".to_string())], ..ExecOptions::default() };
+
+        let cmd = build(&[("EXEC_ENV_ALLOWLIST", "REPL_TEST_ALLOWED"), ("REPL_TEST_ALLOWED", "1")], &options);
+
+        assert_eq!(env(&cmd, "REPL_TEST_ALLOWED"), Some(Some("1".to_string())));
+        assert_eq!(env(&cmd, "FOO"), Some(Some("bar".to_string())));
+        assert_eq!(env(&cmd, "PATH"), None);
+    }
+
+    #[test]
+    fn the_direct_mode_rejects_unbalanced_quotes() {
+        let builder = builder(&[]);
+
+        assert!(builder.argv(ExecMode::Direct, "echo 'a").is_err());
+    }
+}
@@ -0,0 +1,121 @@
+use crate::domain::service::process::tracker::ProcessTracker;
+use crate::infrastructure::helper::signal::kill_group;
+use std::io;
+use std::io::{Read, Write};
+use std::os::unix::process::CommandExt;
+use std::process::{Command as OsCmd, ExitStatus, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+// How often the running process is checked for completion and exceeded limits.
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+// Size of a single read from the process pipes.
+const CHUNK_SIZE: usize = 8 * 1024;
+
+// Captured is the result of a finished process.
+pub struct Captured {
+    pub status: ExitStatus,
+    pub stdout: Vec<u8>,
+    pub stderr: Vec<u8>,
+    // the output has exceeded the limit, so it was cut and the process has been killed
+    pub is_truncated: bool,
+    // the process has not finished in time, so it has been killed
+    pub is_timed_out: bool,
+}
+
+// Runs the process with the given stdin (if any) and collects its stdout and stderr, each of
+// them is cut by the output limit (if any) and the process is killed as soon as one of them
+// exceeds it or the timeout (if any) expires. The process is tracked while it is running.
+pub fn capture(
+    mut cmd: OsCmd,
+    output_limit: Option<u64>,
+    stdin: Option<Vec<u8>>,
+    timeout: Option<Duration>,
+    tracker: &ProcessTracker,
+) -> io::Result<Captured> {
+    // the process leads its own group, so its children are killed along with it and
+    // do not keep the pipes open after that
+    cmd.process_group(0)
+        .stdin(if stdin.is_some() { Stdio::piped() } else { Stdio::null() })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+
+    let mut child = cmd.spawn()?;
+
+    // written by a separate thread, so a process which does not read its input
+    // or prints a lot before reading it does not block the capturing
+    if let (Some(mut pipe), Some(stdin)) = (child.stdin.take(), stdin) {
+        thread::spawn(move || {
+            // the process may exit without reading the whole input (broken pipe)
+            let _ = pipe.write_all(&stdin);
+        });
+    }
+
+    let overflow = Arc::new(AtomicBool::new(false));
+
+    let stdout = child.stdout.take().map(|pipe| {
+        let overflow = overflow.clone();
+        thread::spawn(move || read_limited(pipe, output_limit, overflow))
+    });
+    let stderr = child.stderr.take().map(|pipe| {
+        let overflow = overflow.clone();
+        thread::spawn(move || read_limited(pipe, output_limit, overflow))
+    });
+
+    let deadline = timeout.map(|timeout| Instant::now() + timeout);
+    let mut is_timed_out = false;
+
+    tracker.track(child.id());
+    let status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break status,
+            Ok(None) => {}
+            Err(e) => {
+                tracker.untrack(child.id());
+                return Err(e);
+            }
+        }
+        if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            is_timed_out = true;
+        }
+        if is_timed_out || overflow.load(Ordering::SeqCst) {
+            // the error is ignored because the processes may have exited by themselves already
+            let _ = kill_group(child.id(), libc::SIGKILL);
+        }
+        thread::sleep(POLL_INTERVAL);
+    };
+    tracker.untrack(child.id());
+
+    Ok(Captured {
+        status,
+        stdout: stdout.map(|reader| reader.join().unwrap_or_default()).unwrap_or_default(),
+        stderr: stderr.map(|reader| reader.join().unwrap_or_default()).unwrap_or_default(),
+        is_truncated: overflow.load(Ordering::SeqCst),
+        is_timed_out,
+    })
+}
+
+fn read_limited(mut pipe: impl Read, limit: Option<u64>, overflow: Arc<AtomicBool>) -> Vec<u8> {
+    let mut output = Vec::new();
+    let mut chunk = [0u8; CHUNK_SIZE];
+
+    loop {
+        let n = match pipe.read(&mut chunk) {
+            Ok(0) => return output,
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(_) => return output,
+        };
+        output.extend_from_slice(&chunk[..n]);
+
+        if let Some(limit) = limit {
+            if output.len() as u64 > limit {
+                output.truncate(limit as usize);
+                overflow.store(true, Ordering::SeqCst);
+                return output;
+            }
+        }
+    }
+}
@@ -0,0 +1,50 @@
+use crate::infrastructure::helper::signal::kill_group;
+use std::collections::HashSet;
+use std::sync::Mutex;
+
+// ProcessTracker knows the process groups which are running on behalf of the chat right now,
+// so they can be terminated on shutdown instead of being left behind.
+pub struct ProcessTracker {
+    groups: Mutex<HashSet<u32>>,
+}
+
+impl ProcessTracker {
+    pub fn new() -> ProcessTracker {
+        ProcessTracker { groups: Mutex::new(HashSet::new()) }
+    }
+
+    // track remembers the process which leads its own group
+    pub fn track(&self, pid: u32) {
+        self.groups.lock().unwrap().insert(pid);
+    }
+
+    // untrack forgets the process once it has been reaped
+    pub fn untrack(&self, pid: u32) {
+        self.groups.lock().unwrap().remove(&pid);
+    }
+
+    // returns how many process groups are running
+    pub fn len(&self) -> usize {
+        self.groups.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // sends the signal to all running process groups
+    pub fn signal_all(&self, signal: i32) {
+        for pid in self.groups.lock().unwrap().iter() {
+            // the group may have exited already, it is forgotten as soon as its leader is reaped
+            if let Err(e) = kill_group(*pid, signal) {
+                log::warn!(target: "processes", "Failed to send signal {} to process group {}: {}.", signal, pid, e);
+            }
+        }
+    }
+}
+
+impl Default for ProcessTracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -1,64 +1,208 @@
-use crate::app::cfg::cfg::Cfg;
+use crate::app::cfg::handle::CfgHandle;
+use crate::app::model::health::{self, Health};
+use crate::app::model::state::State;
+use crate::domain::service::event::bus::EventBus;
 use crate::domain::service::event::r#loop::EventLoop;
+use crate::domain::service::process::tracker::ProcessTracker;
+use crate::infrastructure::metrics::server::Server;
 use crate::infrastructure::service::message;
-use std::sync::{mpsc, Arc, Mutex};
+use signal_hook::consts::{SIGHUP, SIGINT, SIGTERM};
+use signal_hook::iterator::Signals;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{mpsc, Arc, Mutex, PoisonError};
 use std::thread;
 use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+// How often the runner checks the state of the threads on shutdown.
+const WAIT_INTERVAL: Duration = Duration::from_millis(100);
+// How long the remaining processes may handle SIGTERM before they are killed.
+pub const KILL_GRACE: Duration = Duration::from_secs(2);
+// How long the runner waits before a panicked component is started again, the delay is doubled
+// on each panic up to the max one and is reset if the component has been running longer than it.
+const RESTART_BACKOFF_MIN: Duration = Duration::from_secs(1);
+const RESTART_BACKOFF_MAX: Duration = Duration::from_secs(60);
 
 pub trait Runner {
-    fn run(&self) -> ();
+    fn run(&self);
 }
 
 pub struct AppRunner {
-    cfg: Cfg,
+    cfg: CfgHandle,
+    state: Arc<Box<dyn State>>,
+    tracker: Arc<ProcessTracker>,
     event_loop: Arc<Box<dyn EventLoop>>,
     provider: Arc<Mutex<Box<dyn message::provider::Provider>>>,
     consumer: Arc<Mutex<Box<dyn message::consumer::Consumer>>>,
+    metrics_server: Arc<Box<dyn Server>>,
+    bus: Arc<Box<dyn EventBus>>,
+    health: Arc<Health>,
 }
 
 impl AppRunner {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
-        cfg: Cfg,
+        cfg: CfgHandle,
+        state: Arc<Box<dyn State>>,
+        tracker: Arc<ProcessTracker>,
         event_loop: Arc<Box<dyn EventLoop>>,
         provider: Arc<Mutex<Box<dyn message::provider::Provider>>>,
         consumer: Arc<Mutex<Box<dyn message::consumer::Consumer>>>,
+        metrics_server: Arc<Box<dyn Server>>,
+        bus: Arc<Box<dyn EventBus>>,
+        health: Arc<Health>,
     ) -> AppRunner {
         AppRunner {
             cfg,
+            state,
+            tracker,
             event_loop,
             provider,
             consumer,
+            metrics_server,
+            bus,
+            health,
+        }
+    }
+
+    // supervise runs the component in a thread and starts it again with a backoff if it has
+    // panicked, a component which has returned by itself is not restarted.
+    fn supervise(&self, name: &'static str, component: impl Fn() + Send + 'static) -> JoinHandle<()> {
+        let state = self.state.clone();
+        let health = self.health.clone();
+
+        thread::spawn(move || {
+            let mut backoff = RESTART_BACKOFF_MIN;
+            loop {
+                health.start(name);
+                let started_at = Instant::now();
+                if panic::catch_unwind(AssertUnwindSafe(&component)).is_ok() {
+                    health.stop(name);
+                    return;
+                }
+                health.fail(name);
+
+                if state.is_closed() {
+                    return;
+                }
+                if started_at.elapsed() > RESTART_BACKOFF_MAX {
+                    backoff = RESTART_BACKOFF_MIN;
+                }
+                log::error!(target: "runner", component = name; "Component {} has panicked, restarting in {:?}...", name, backoff);
+
+                let deadline = Instant::now() + backoff;
+                while Instant::now() < deadline {
+                    if state.is_closed() {
+                        return;
+                    }
+                    thread::sleep(WAIT_INTERVAL);
+                }
+                backoff = (backoff * 2).min(RESTART_BACKOFF_MAX);
+            }
+        })
+    }
+
+    // waits until the threads have finished by themselves after the app has been closed,
+    // the processes which are still running after the deadline are terminated.
+    fn shutdown(&self, threads: &[JoinHandle<()>]) {
+        let is_finished = || threads.iter().all(|thread| thread.is_finished());
+
+        while !self.state.is_closed() {
+            if is_finished() {
+                return;
+            }
+            thread::sleep(WAIT_INTERVAL);
+        }
+        // the loop sleeps until its next event is due, so it is woken up to see the app is closed
+        self.event_loop.close();
+
+        // in-flight commands are given time to finish and to be responded
+        let deadline = Instant::now() + self.cfg.get().shutdown_timeout;
+        while !is_finished() && Instant::now() < deadline {
+            thread::sleep(WAIT_INTERVAL);
+        }
+
+        // the commands which are out of the deadline and the background jobs
+        if !self.tracker.is_empty() {
+            log::warn!(target: "runner", "Terminating {} remaining process groups...", self.tracker.len());
+            self.tracker.signal_all(libc::SIGTERM);
+
+            let deadline = Instant::now() + KILL_GRACE;
+            while !self.tracker.is_empty() && Instant::now() < deadline {
+                thread::sleep(WAIT_INTERVAL);
+            }
+            self.tracker.signal_all(libc::SIGKILL);
         }
     }
 }
 
 impl Runner for AppRunner {
-    fn run(&self) -> () {
-        let (sender, receiver) = mpsc::sync_channel(self.cfg.event_loop_channel_capacity);
+    fn run(&self) {
+        let (sender, receiver) = mpsc::sync_channel(self.cfg.get().event_loop_channel_capacity);
+        // the receiver is kept out of the consumer, so a restarted one goes on with the same channel
+        let receiver = Mutex::new(receiver);
         let provider = self.provider.clone();
         let consumer = self.consumer.clone();
         let event_loop = self.event_loop.clone();
+        let metrics_server = self.metrics_server.clone();
 
-        let mut threads: Vec<JoinHandle<()>> = Vec::new();
-
-        threads.push(thread::spawn(move || {
+        // a panicked component leaves its mutex poisoned, the component itself is still usable
+        let threads: Vec<JoinHandle<()>> = vec![
             // starts a long poller which asks telegram message updates and provides them
             // into output channel.
-            provider.lock().unwrap().provide(sender);
-        }));
-
-        threads.push(thread::spawn(move || {
+            self.supervise(health::POLLER, move || {
+                provider.lock().unwrap_or_else(PoisonError::into_inner).provide(sender.clone());
+            }),
             // starts a consumer of channel which uses provider as the output.
-            consumer.lock().unwrap().consume(receiver);
-        }));
-
-        threads.push(thread::spawn(move || {
+            self.supervise(health::CONSUMER, move || {
+                let receiver = receiver.lock().unwrap_or_else(PoisonError::into_inner);
+                consumer.lock().unwrap_or_else(PoisonError::into_inner).consume(&receiver);
+            }),
             // starts an event loop which will serve different events in app
-            event_loop.serve();
-        }));
+            self.supervise(health::LOOP, move || event_loop.serve()),
+            // starts the metrics and the health endpoint if it is enabled
+            thread::spawn(move || metrics_server.serve()),
+        ];
 
+        // SIGINT and SIGTERM close the app gracefully, the second one exits at once,
+        // SIGHUP reloads the config
+        let mut signals = Signals::new([SIGHUP, SIGINT, SIGTERM]).unwrap();
+        let signals_handle = signals.handle();
+        let state = self.state.clone();
+        let cfg = self.cfg.clone();
+        let listener = thread::spawn(move || {
+            for signal in signals.forever() {
+                if signal == SIGHUP {
+                    log::info!(target: "runner", "Signal {} has been received, reloading the config...", signal);
+                    if let Err(e) = cfg.reload() {
+                        log::error!(target: "runner", "Failed to reload the config: {}", e);
+                    }
+                    continue;
+                }
+                if state.is_closed() {
+                    log::warn!(target: "runner", "Signal {} has been received again, exiting at once.", signal);
+                    std::process::exit(128 + signal);
+                }
+                log::info!(target: "runner", "Signal {} has been received, shutting down...", signal);
+                state.close();
+            }
+        });
+
+        self.shutdown(&threads);
+
+        signals_handle.close();
+        if listener.join().is_err() {
+            log::error!(target: "runner", "Signal listener thread has panicked.");
+        }
         for descriptor in threads {
-            descriptor.join().unwrap();
+            if descriptor.join().is_err() {
+                log::error!(target: "runner", "Runner thread has panicked.");
+            }
         }
+
+        // the subscribers handle the events which the loop has published before it has stopped
+        self.bus.close();
+
+        log::info!(target: "runner", "Application has been stopped.");
     }
 }
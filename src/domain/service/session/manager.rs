@@ -0,0 +1,212 @@
+use crate::app::cfg::handle::CfgHandle;
+use crate::domain::error::session::{SessionAlreadyExistsError, SessionNotFoundError};
+use crate::domain::model::command::SessionResultCmd;
+use crate::domain::model::event::{EventSender, ExecutableEvent};
+use crate::domain::model::exec::ExecOptions;
+use crate::domain::r#enum::exit_code::ExitCode;
+use crate::domain::service::event::r#loop::EventLoop;
+use crate::domain::service::process::builder::ProcessBuilder;
+use crate::domain::service::session::session::{SessionOutput, ShellSession};
+use crate::infrastructure::helper::signal::kill_group;
+use crate::infrastructure::model::command::Exit;
+use chrono::Local;
+use std::collections::HashMap;
+use std::error::Error;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::mpsc::{channel, Receiver};
+use std::sync::{Arc, Mutex, Weak};
+use std::thread;
+use std::time::{Duration, Instant};
+
+// How often the idle sessions are looked for.
+const REAP_INTERVAL: Duration = Duration::from_secs(5);
+
+pub trait SessionManagerTrait: Send + Sync {
+    // starts a new shell session for the chat
+    fn start(&self, chat_id: i64, user_id: i64, options: &ExecOptions) -> Result<(), Box<dyn Error>>;
+    // stops the shell session of the chat
+    fn stop(&self, chat_id: i64) -> Result<(), Box<dyn Error>>;
+    // returns a human-readable state of the chat session
+    fn status(&self, chat_id: i64) -> Result<String, Box<dyn Error>>;
+    // tells whether the commands of the chat must be sent into its session
+    fn is_active(&self, chat_id: i64) -> bool;
+    // runs the input in the chat session, returns the exit code and the terminal output
+    fn run(&self, chat_id: i64, input: &str) -> Result<SessionOutput, Box<dyn Error>>;
+    // sender returns the queue of the chat session worker, the commands of the session are run
+    // by it in order and their results are put back into the loop, so the loop is not blocked
+    fn sender(&self, chat_id: i64) -> Option<EventSender>;
+}
+
+// Slot is a session along with the queue of its worker, the worker is gone along with the queue.
+// The session is locked by the worker for the whole command, so its pid is kept aside.
+struct Slot {
+    pid: u32,
+    session: Session,
+    worker: EventSender,
+}
+
+type Session = Arc<Mutex<ShellSession>>;
+type Sessions = Mutex<HashMap<i64, Slot>>;
+
+pub struct SessionManager {
+    cfg: CfgHandle,
+    builder: Arc<ProcessBuilder>,
+    event_loop: Arc<Box<dyn EventLoop>>,
+    sessions: Arc<Sessions>,
+}
+
+impl SessionManager {
+    pub fn new(cfg: CfgHandle, builder: Arc<ProcessBuilder>, event_loop: Arc<Box<dyn EventLoop>>) -> SessionManager {
+        let sessions: Arc<Sessions> = Arc::new(Mutex::new(HashMap::new()));
+
+        let weak = Arc::downgrade(&sessions);
+        let reaper_cfg = cfg.clone();
+        std::thread::spawn(move || Self::reap(weak, reaper_cfg));
+
+        SessionManager { cfg, builder, event_loop, sessions }
+    }
+
+    // work runs the commands of a session one by one off the loop thread, the result of each of
+    // them is put back into the loop as an event, so it is delivered and audited there
+    fn work(receiver: Receiver<Arc<Box<dyn ExecutableEvent>>>, event_loop: Arc<Box<dyn EventLoop>>) {
+        thread::spawn(move || {
+            for event in receiver.iter() {
+                let Some(command) = event.command().cloned() else {
+                    continue;
+                };
+
+                let started_at = Instant::now();
+                let exit = match panic::catch_unwind(AssertUnwindSafe(|| event.exec())) {
+                    Ok(exit) => exit,
+                    Err(_) => Exit::new(
+                        ExitCode::Failed,
+                        "".to_string(),
+                        "The session command has panicked.".to_string(),
+                        Some(command.message.clone()),
+                    ),
+                };
+                let exit = Exit { finished_at: Some(Local::now()), duration: Some(started_at.elapsed()), ..exit };
+
+                event_loop.add_event(Arc::new(Box::new(SessionResultCmd::new(command, exit))));
+            }
+        });
+    }
+
+    // reap closes the sessions which are idle for too long or whose shell has exited,
+    // the thread lives as long as the manager does.
+    fn reap(sessions: Weak<Sessions>, cfg: CfgHandle) {
+        loop {
+            std::thread::sleep(REAP_INTERVAL);
+
+            let Some(sessions) = sessions.upgrade() else {
+                return;
+            };
+            let idle_timeout = cfg.get().session_idle_timeout;
+
+            sessions.lock().unwrap().retain(|chat_id, slot| {
+                // a busy session is running a command right now, so it is not idle
+                let Ok(mut session) = slot.session.try_lock() else {
+                    return true;
+                };
+                if !session.is_alive() {
+                    log::info!(target: "sessions", chat_id = *chat_id; "Session has exited.");
+                    return false;
+                }
+                if !idle_timeout.is_zero() && session.idle() > idle_timeout {
+                    log::info!(target: "sessions", chat_id = *chat_id; "Session has been closed by idle timeout.");
+                    session.stop();
+                    return false;
+                }
+                true
+            });
+        }
+    }
+
+    fn get(&self, chat_id: i64) -> Result<(u32, Session), Box<dyn Error>> {
+        match self.sessions.lock().unwrap().get(&chat_id) {
+            Some(slot) => Ok((slot.pid, slot.session.clone())),
+            None => Err(Box::new(SessionNotFoundError::new())),
+        }
+    }
+}
+
+impl SessionManagerTrait for SessionManager {
+    fn start(&self, chat_id: i64, user_id: i64, options: &ExecOptions) -> Result<(), Box<dyn Error>> {
+        if self.is_active(chat_id) {
+            return Err(Box::new(SessionAlreadyExistsError::new()));
+        }
+
+        let limits = self.builder.limits(user_id);
+        let cfg = self.cfg.get();
+        let process = self.builder.build(&cfg.session_shell, options, &limits, user_id)?;
+        let session = ShellSession::start(process, cfg.session_command_timeout, limits.output())?;
+
+        let (worker, receiver) = channel();
+        Self::work(receiver, self.event_loop.clone());
+        let slot = Slot { pid: session.pid(), session: Arc::new(Mutex::new(session)), worker: Arc::new(worker) };
+        self.sessions.lock().unwrap().insert(chat_id, slot);
+
+        Ok(())
+    }
+
+    fn stop(&self, chat_id: i64) -> Result<(), Box<dyn Error>> {
+        // the map lock must not be held while the session is stopped
+        let slot = self.sessions.lock().unwrap().remove(&chat_id);
+        let Some(slot) = slot else {
+            return Err(Box::new(SessionNotFoundError::new()));
+        };
+
+        match slot.session.try_lock() {
+            Ok(mut session) => session.stop(),
+            // the worker is running a command, killing the shell ends the command and
+            // the worker drops the session once it is done with it
+            Err(_) => {
+                if let Err(error) = kill_group(slot.pid, libc::SIGKILL) {
+                    log::warn!(target: "sessions", chat_id = chat_id, pid = slot.pid; "Failed to kill the session shell: {}.", error);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn status(&self, chat_id: i64) -> Result<String, Box<dyn Error>> {
+        let (pid, session) = self.get(chat_id)?;
+        let Ok(session) = session.try_lock() else {
+            return Ok(format!("Session is busy running a command: pid {}.", pid));
+        };
+
+        Ok(format!(
+            "Session is active: pid {}, started at {}, idle for {}s, commands executed {}.",
+            session.pid(),
+            session.started_at().format("%Y-%m-%dT%H:%M:%S"),
+            session.idle().as_secs(),
+            session.commands(),
+        ))
+    }
+
+    fn is_active(&self, chat_id: i64) -> bool {
+        self.sessions.lock().unwrap().contains_key(&chat_id)
+    }
+
+    fn run(&self, chat_id: i64, input: &str) -> Result<SessionOutput, Box<dyn Error>> {
+        // the map lock is released here, so other chats are not blocked by a long command
+        let (_, slot) = self.get(chat_id)?;
+        let mut session = slot.lock().unwrap();
+
+        let result = session.run(input, self.cfg.get().session_command_timeout);
+        if !session.is_alive() {
+            // e.g. `exit` has been sent or the shell has been killed, the chat may have
+            // started a new session meanwhile, so just this one is removed
+            let mut sessions = self.sessions.lock().unwrap();
+            if sessions.get(&chat_id).is_some_and(|current| Arc::ptr_eq(&current.session, &slot)) {
+                sessions.remove(&chat_id);
+            }
+        }
+
+        result
+    }
+
+    fn sender(&self, chat_id: i64) -> Option<EventSender> {
+        self.sessions.lock().unwrap().get(&chat_id).map(|slot| slot.worker.clone())
+    }
+}
@@ -0,0 +1,3 @@
+pub mod manager;
+#[allow(clippy::module_inception)]
+pub mod session;
@@ -0,0 +1,302 @@
+use crate::domain::error::session::{SessionClosedError, SessionTimeoutError};
+use crate::infrastructure::helper::pty::{make_controlling_terminal, open_pty};
+use chrono::{Local, NaiveDateTime};
+use std::error::Error;
+use std::fs::File;
+use std::io::{ErrorKind, Read, Write};
+use std::os::unix::process::CommandExt;
+use std::process::{Child, Command as OsCmd, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::time::{Duration, Instant};
+
+// Each command written into the shell is followed by a printf of this marker with the
+// command exit code, so the output of consecutive commands can be framed.
+const EXIT_MARKER: &str = "__REPL_SESSION_EXIT_";
+// How long an interrupted (timed out) command may take to print its marker.
+const INTERRUPT_GRACE: Duration = Duration::from_secs(2);
+// Gives the shell time to handle SIGINT before the marker is written once again.
+const INTERRUPT_DELAY: Duration = Duration::from_millis(200);
+// End-of-text, the pty line discipline turns it into SIGINT for the foreground job.
+const INTERRUPT: &[u8] = b"\x03";
+// How many of the last bytes are kept beyond the output limit, so the marker is still found.
+const MARKER_WINDOW: usize = 256;
+
+// SessionOutput is the result of a command run in the session.
+pub struct SessionOutput {
+    pub code: i32,
+    // everything the command has printed into the terminal (stdout and stderr)
+    pub output: String,
+    // the output has exceeded the limit, so just its beginning is kept
+    pub is_truncated: bool,
+}
+
+// OutputBuf collects the terminal output of a command, the bytes beyond the limit are
+// dropped except the last ones which may hold the exit marker.
+struct OutputBuf {
+    bytes: Vec<u8>,
+    limit: Option<usize>,
+    is_truncated: bool,
+}
+
+impl OutputBuf {
+    fn new(limit: Option<u64>) -> OutputBuf {
+        OutputBuf { bytes: vec![], limit: limit.map(|limit| limit as usize), is_truncated: false }
+    }
+
+    fn push(&mut self, chunk: &[u8]) {
+        self.bytes.extend_from_slice(chunk);
+        let Some(limit) = self.limit else {
+            return;
+        };
+        if self.bytes.len() > limit + MARKER_WINDOW {
+            let excess = self.bytes.len() - limit - MARKER_WINDOW;
+            self.bytes.drain(limit..limit + excess);
+            self.is_truncated = true;
+        }
+    }
+
+    // returns the output before the given position, cut by the limit
+    fn text(&self, end: usize) -> String {
+        let end = match self.limit {
+            Some(limit) if self.is_truncated => end.min(limit),
+            _ => end,
+        };
+        String::from_utf8_lossy(&self.bytes[..end]).to_string()
+    }
+}
+
+// ShellSession is a long-lived shell process attached to a pseudo-terminal, so the
+// working directory, variables and activated environments survive between commands.
+pub struct ShellSession {
+    child: Child,
+    master: File,
+    output: Receiver<Vec<u8>>,
+    output_limit: Option<u64>,
+    started_at: NaiveDateTime,
+    last_activity: Instant,
+    commands: u64,
+}
+
+impl ShellSession {
+    // start runs the shell process (see ProcessBuilder) attached to a new pseudo-terminal,
+    // the output of each command is cut by the output limit (if any)
+    pub fn start(mut cmd: OsCmd, timeout: Duration, output_limit: Option<u64>) -> Result<ShellSession, Box<dyn Error>> {
+        let pty = open_pty()?;
+        cmd.env("TERM", "dumb")
+            .stdin(Stdio::from(pty.slave.try_clone()?))
+            .stdout(Stdio::from(pty.slave.try_clone()?))
+            .stderr(Stdio::from(pty.slave));
+        // safety: only async-signal-safe calls are made between fork and exec
+        unsafe {
+            cmd.pre_exec(make_controlling_terminal);
+        }
+        let child = cmd.spawn()?;
+        // the slave side must be closed in the parent, otherwise EOF never comes from the master
+        drop(cmd);
+
+        let mut reader = pty.master.try_clone()?;
+        let (sender, output) = mpsc::channel();
+        std::thread::spawn(move || {
+            let mut buf = [0u8; 4096];
+            loop {
+                match reader.read(&mut buf) {
+                    Ok(0) => return,
+                    Ok(n) => {
+                        if sender.send(buf[..n].to_vec()).is_err() {
+                            return;
+                        }
+                    }
+                    Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                    // EIO is returned when the last slave descriptor has been closed
+                    Err(_) => return,
+                }
+            }
+        });
+
+        let mut session = ShellSession {
+            child,
+            master: pty.master,
+            output,
+            output_limit,
+            started_at: Local::now().naive_local(),
+            last_activity: Instant::now(),
+            commands: 0,
+        };
+
+        // an interactive shell prints prompts into the terminal, get rid of them
+        session.run("PS1=''; PS2=''; export PS1 PS2", timeout)?;
+        session.commands = 0;
+
+        Ok(session)
+    }
+
+    // run writes the input into the shell and waits for its exit marker, returns the exit
+    // code and everything the command has printed into the terminal (stdout and stderr).
+    pub fn run(&mut self, input: &str, timeout: Duration) -> Result<SessionOutput, Box<dyn Error>> {
+        self.commands += 1;
+        self.last_activity = Instant::now();
+
+        // drop a leftover output of previously interrupted commands
+        while self.output.try_recv().is_ok() {}
+
+        let marker = format!("{}{}__", EXIT_MARKER, self.commands);
+        self.master.write_all(format!("{}\n", input.trim_end()).as_bytes())?;
+        self.write_marker(marker.as_str())?;
+
+        let mut buf = OutputBuf::new(self.output_limit);
+        let result = match self.read_until_marker(&mut buf, marker.as_str(), timeout) {
+            Ok((code, end)) => Ok(SessionOutput { code, output: buf.text(end), is_truncated: buf.is_truncated }),
+            Err(RecvTimeoutError::Disconnected) => Err(Box::new(SessionClosedError::new()) as Box<dyn Error>),
+            Err(RecvTimeoutError::Timeout) => {
+                // interrupt the command, then ask for the marker once again because the
+                // interrupted line may have swallowed the first one (e.g. unclosed quote)
+                self.master.write_all(INTERRUPT)?;
+                std::thread::sleep(INTERRUPT_DELAY);
+                self.write_marker(marker.as_str())?;
+
+                let output = buf.text(buf.bytes.len());
+                if self.read_until_marker(&mut buf, marker.as_str(), INTERRUPT_GRACE).is_err() {
+                    // the shell does not respond anymore, so it cannot be reused
+                    self.stop();
+                }
+                Err(Box::new(SessionTimeoutError::new(output)) as Box<dyn Error>)
+            }
+        };
+
+        self.last_activity = Instant::now();
+        result
+    }
+
+    pub fn pid(&self) -> u32 {
+        self.child.id()
+    }
+
+    pub fn started_at(&self) -> NaiveDateTime {
+        self.started_at
+    }
+
+    pub fn idle(&self) -> Duration {
+        self.last_activity.elapsed()
+    }
+
+    pub fn commands(&self) -> u64 {
+        self.commands
+    }
+
+    pub fn is_alive(&mut self) -> bool {
+        matches!(self.child.try_wait(), Ok(None))
+    }
+
+    // stop kills the shell, the kernel hangs up its terminal so the foreground job dies too
+    pub fn stop(&mut self) {
+        if self.is_alive() {
+            let _ = self.child.kill();
+        }
+        let _ = self.child.wait();
+    }
+
+    fn write_marker(&mut self, marker: &str) -> std::io::Result<()> {
+        self.master.write_all(format!("printf '\\n{} %d\\n' \"$?\"\n", marker).as_bytes())
+    }
+
+    // returns the exit code and where the output of the command ends in the buffer
+    fn read_until_marker(&self, buf: &mut OutputBuf, marker: &str, timeout: Duration) -> Result<(i32, usize), RecvTimeoutError> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            if let Some(result) = parse(&buf.bytes, marker) {
+                return Ok(result);
+            }
+
+            let now = Instant::now();
+            if now >= deadline {
+                return Err(RecvTimeoutError::Timeout);
+            }
+
+            buf.push(&self.output.recv_timeout(deadline - now)?);
+        }
+    }
+}
+
+impl Drop for ShellSession {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+// parse looks for the "\n<marker> <code>\n" line, returns the code and where the line starts
+fn parse(buf: &[u8], marker: &str) -> Option<(i32, usize)> {
+    let needle = format!("\n{} ", marker);
+    let start = buf.windows(needle.len()).position(|window| window == needle.as_bytes())?;
+    let tail = &buf[start + needle.len()..];
+    let end = tail.iter().position(|byte| *byte == b'\n')?;
+    let code = std::str::from_utf8(&tail[..end]).ok()?.trim().parse::<i32>().ok()?;
+
+    Some((code, start))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MARKER: &str = "__REPL_SESSION_EXIT_3__";
+
+    #[test]
+    fn the_output_is_framed_by_the_marker_line() {
+        let buf = b"total 0\r\nfile\r\n__REPL_SESSION_EXIT_3__ 2\r\n";
+
+        let (code, end) = parse(buf, MARKER).unwrap();
+
+        assert_eq!(code, 2);
+        assert_eq!(&buf[..end], b"total 0\r\nfile\r");
+    }
+
+    #[test]
+    fn an_incomplete_marker_line_is_waited_for() {
+        assert_eq!(parse(b"out\n__REPL_SESSION_EXIT_3__ 0", MARKER), None);
+        assert_eq!(parse(b"out\n__REPL_SESSION_EXIT_3", MARKER), None);
+    }
+
+    #[test]
+    fn the_echoed_printf_and_other_markers_are_not_taken_for_the_marker() {
+        // the terminal echoes the printf command, its \n is not a line break there
+        let echo = b"printf '\\n__REPL_SESSION_EXIT_3__ %d\\n' \"$?\"\r\n";
+        assert_eq!(parse(echo, MARKER), None);
+        // a marker of a previous (interrupted) command
+        assert_eq!(parse(b"\n__REPL_SESSION_EXIT_2__ 0\n", MARKER), None);
+        // a broken code
+        assert_eq!(parse(b"\n__REPL_SESSION_EXIT_3__ %d\n", MARKER), None);
+    }
+
+    #[test]
+    fn a_negative_and_a_signal_exit_code_are_parsed() {
+        assert_eq!(parse(b"\n__REPL_SESSION_EXIT_3__ 130\n", MARKER), Some((130, 0)));
+        assert_eq!(parse(b"\n__REPL_SESSION_EXIT_3__ -1\n", MARKER), Some((-1, 0)));
+    }
+
+    #[test]
+    fn the_output_beyond_the_limit_is_dropped_but_the_marker_is_found() {
+        let mut buf = OutputBuf::new(Some(4));
+        for _ in 0..100 {
+            buf.push(b"0123456789");
+        }
+        buf.push(b"\n__REPL_SESSION_EXIT_3__ 0\n");
+
+        let (code, end) = parse(&buf.bytes, MARKER).unwrap();
+
+        assert_eq!(code, 0);
+        assert!(buf.is_truncated);
+        assert!(buf.bytes.len() <= 4 + MARKER_WINDOW);
+        assert_eq!(buf.text(end), "0123");
+    }
+
+    #[test]
+    fn the_output_within_the_limit_is_kept_as_is() {
+        let mut buf = OutputBuf::new(Some(1024));
+        buf.push(b"hello\n__REPL_SESSION_EXIT_3__ 0\n");
+
+        let (_, end) = parse(&buf.bytes, MARKER).unwrap();
+
+        assert!(!buf.is_truncated);
+        assert_eq!(buf.text(end), "hello");
+    }
+}
@@ -0,0 +1,15 @@
+use crate::domain::model::message::{File, Reply};
+use std::error::Error;
+
+// Transport is the outbound side of a messenger (e.g. telegram), the inbound messages are
+// provided by its provider. The domain talks to the chats just by it.
+pub trait Transport: Send + Sync {
+    // name tells the transports apart in the logs
+    fn name(&self) -> String;
+    // send delivers the reply, it fails if the text or one of the attachments has not been sent,
+    // with PartiallyDeliveredError of the parts (see Reply::without) once some of them have been sent
+    fn send(&self, reply: Reply) -> Result<(), Box<dyn Error>>;
+    // download returns the content of a file of an inbound message, it fails once more than
+    // limit bytes have been received (the size of a file is not always known beforehand)
+    fn download(&self, file: &File, limit: u64) -> Result<Vec<u8>, Box<dyn Error>>;
+}
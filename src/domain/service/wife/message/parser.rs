@@ -2,25 +2,28 @@ use crate::domain::model::wife::Message;
 use csv::ReaderBuilder;
 use std::error::Error;
 use crate::app::app::DataDir;
-use crate::app::cfg::cfg::Cfg;
+use crate::app::cfg::handle::CfgHandle;
 
 pub trait MessageParser: Send + Sync {
     fn parse(&self) -> Result<Vec<Message>, Box<dyn Error>>;
 }
 
 pub struct CsvParser {
-    cfg: Cfg
+    cfg: CfgHandle
 }
 
 impl CsvParser {
-    pub fn new(cfg: Cfg) -> Self {
+    pub fn new(cfg: CfgHandle) -> Self {
         Self { cfg }
     }
 }
 
 impl MessageParser for CsvParser {
     fn parse(&self) -> Result<Vec<Message>, Box<dyn Error>> {
-        let file = DataDir::get(self.cfg.wife_filepath.as_str()).unwrap();
+        let path = self.cfg.get().wife_filepath.clone();
+        let Some(file) = DataDir::get(path.as_str()) else {
+            return Err(format!("The wife messages file {} is not embedded.", path).into());
+        };
 
         let mut reader = ReaderBuilder::new()
             .has_headers(false)
@@ -34,7 +37,7 @@ impl MessageParser for CsvParser {
                     if let Some(str) = record.get(0) {
                         messages.push(Message::new(str.to_string()))
                     } else {
-                        println!("failed to parse record {}", i);
+                        log::warn!(target: "wife", "Failed to parse record {}.", i);
                     }
                 },
                 Err(e) => return Err(Box::new(e)),
@@ -15,10 +15,7 @@ pub struct MessageService {
 
 impl MessageService {
     pub fn new(message_parser: Arc<Box<dyn MessageParser>>) -> Result<Self, Box<dyn Error>> {
-        let messages = match message_parser.parse() {
-            Ok(message) => message,
-            Err(error) => return Err(error),
-        };
+        let messages = message_parser.parse()?;
 
         if messages.is_empty() {
             return Err(Box::new(WifeMessagesVecIsEmptyError::new()));
@@ -1,13 +1,24 @@
 use crate::infrastructure::broadcasting::mpsc::error::{
     AlreadyExistsError, NoEntryWasFoundError, SendOnClosedChannelError,
 };
+use crate::infrastructure::broadcasting::mpsc::queue::{self, Overflow};
 use std::collections::HashMap;
 use std::sync::mpsc::{Receiver, Sender};
 use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+
+// How many items a receiver may lag behind when the channel is made by Chan::new.
+const DEFAULT_CAPACITY: usize = 1024;
 
 pub trait Channel<T: Send + Sync + Clone>: Send + Sync {
     // adds a new one receiver by key
     fn add(&self, key: String) -> Result<Receiver<T>, AlreadyExistsError>;
+    // adds a new one receiver by key whose full queue is handled by the overflow policy,
+    // the channels which are not bounded ignore the policy
+    fn add_with(&self, key: String, overflow: Overflow) -> Result<Receiver<T>, AlreadyExistsError> {
+        let _ = overflow;
+        self.add(key)
+    }
     // sends the data to one receiver by key or to all receivers in case when key is
     // None
     fn send(&self, key: Option<String>, data: T) -> Result<(), SendOnClosedChannelError>;
@@ -15,55 +26,132 @@ pub trait Channel<T: Send + Sync + Clone>: Send + Sync {
     fn remove(&self, key: String);
     // returns a target sender by key
     fn sender(&self, key: Option<String>) -> Result<Vec<Arc<Sender<T>>>, NoEntryWasFoundError>;
+    // returns how many items the receiver by key lags behind and how many have been dropped for it
+    fn lag(&self, key: &str) -> (usize, u64) {
+        let _ = key;
+        (0, 0)
+    }
+}
+
+// Entry is a receiver of the channel: the items are queued by the bounded queue which applies the
+// overflow policy and a pump hands them over to the receiver one by one, so the queue fills up while
+// the receiver is busy. The items of sender() are put into the same queue by another pump.
+struct Entry<T> {
+    queue: Arc<queue::Sender<T>>,
+    sender: Arc<Sender<T>>,
 }
 
+// Chan is a bounded broadcast channel: each receiver has its own queue of the capacity and a
+// send to the full one is handled by the overflow policy of the receiver (Block by add). The
+// receivers which have gone away are pruned on send.
 pub struct Chan<T> {
-    map: Arc<Mutex<HashMap<String, Arc<Sender<T>>>>>,
+    map: Arc<Mutex<HashMap<String, Entry<T>>>>,
+    capacity: usize,
 }
 
 impl<T> Chan<T> {
     pub fn new() -> Chan<T> {
-        let map: HashMap<String, Arc<Sender<T>>> = HashMap::new();
+        Self::with_capacity(DEFAULT_CAPACITY)
+    }
+
+    pub fn with_capacity(capacity: usize) -> Chan<T> {
+        let map: HashMap<String, Entry<T>> = HashMap::new();
         Self {
             map: Arc::new(Mutex::new(map)),
+            capacity,
         }
     }
+
+    // prune removes the receivers which have gone away
+    fn prune(&self) {
+        self.map.lock().unwrap().retain(|key, entry| {
+            if entry.queue.is_disconnected() {
+                log::debug!(target: "bus", "Receiver `{}` has gone away, it has been removed.", key);
+                return false;
+            }
+            true
+        });
+    }
 }
 
-impl<T: Send + Sync + Clone> Channel<T> for Chan<T> {
+impl<T> Default for Chan<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Send + Sync + Clone + 'static> Channel<T> for Chan<T> {
     fn add(&self, key: String) -> Result<Receiver<T>, AlreadyExistsError> {
-        let (sender, receiver) = mpsc::channel::<T>();
+        self.add_with(key, Overflow::Block)
+    }
+
+    fn add_with(&self, key: String, overflow: Overflow) -> Result<Receiver<T>, AlreadyExistsError> {
         // unwrap here is safe due to only already exists concurrency problems can have
         // affect it for example, if other thread was locked mutex and had
         // failed without unlocking it (deadlock).
         let mut map = self.map.lock().unwrap();
 
-        if map.contains_key(&key) {
+        // the key of a receiver which has gone away may be taken again
+        if map.get(&key).is_some_and(|entry| !entry.queue.is_disconnected()) {
             return Err(AlreadyExistsError::new(
                 "map key already exists".to_string(),
             ));
         }
 
-        map.insert(key, Arc::new(sender));
+        let (queue, queued) = queue::bounded::<T>(self.capacity, overflow);
+        // a rendezvous channel, the pump waits until the receiver takes the item
+        let (handover, receiver) = mpsc::sync_channel::<T>(0);
+        thread::spawn(move || {
+            for item in queued.iter() {
+                // the receiver has gone away, the queue is disconnected along with the pump
+                if handover.send(item).is_err() {
+                    return;
+                }
+            }
+        });
+
+        let (sender, sent) = mpsc::channel::<T>();
+        let pumped = queue.clone();
+        thread::spawn(move || {
+            for item in sent.iter() {
+                if pumped.send(item).is_err() {
+                    return;
+                }
+            }
+        });
+
+        map.insert(key, Entry { queue: Arc::new(queue), sender: Arc::new(sender) });
 
         Ok(receiver)
     }
 
     fn send(&self, key: Option<String>, data: T) -> Result<(), SendOnClosedChannelError> {
-        if let Some(key) = key {
+        // the lock is not held while sending, a blocked send must not hold the other ones
+        let queues: Vec<Arc<queue::Sender<T>>> = {
             let map = self.map.lock().unwrap();
-            let sender = map.get(&key).unwrap();
-            if let Err(e) = sender.send(data.clone()) {
-                return Err(SendOnClosedChannelError::new(e.to_string()));
+            match &key {
+                Some(key) => match map.get(key) {
+                    Some(entry) => vec![entry.queue.clone()],
+                    None => return Err(SendOnClosedChannelError::new(NoEntryWasFoundError::new().to_string())),
+                },
+                None => map.values().map(|entry| entry.queue.clone()).collect(),
             }
-        } else {
-            for included_map in self.map.lock().unwrap().iter() {
-                let sender = included_map.1.clone();
-                if let Err(e) = sender.send(data.clone()) {
+        };
+
+        let mut is_disconnected = false;
+        for queue in queues {
+            if let Err(e) = queue.send(data.clone()) {
+                is_disconnected = true;
+                // the rest of the receivers still get the data when it is broadcast
+                if key.is_some() {
+                    self.prune();
                     return Err(SendOnClosedChannelError::new(e.to_string()));
                 }
             }
         }
+        if is_disconnected {
+            self.prune();
+        }
         Ok(())
     }
 
@@ -75,16 +163,161 @@ impl<T: Send + Sync + Clone> Channel<T> for Chan<T> {
         let map = self.map.lock().unwrap();
 
         if let Some(key) = key {
-            if !map.contains_key(&key) {
-                return Err(NoEntryWasFoundError::new());
+            match map.get(&key) {
+                Some(entry) => Ok(vec![entry.sender.clone()]),
+                None => Err(NoEntryWasFoundError::new()),
             }
-            Ok(vec![map.get(&key).unwrap().clone()])
         } else {
-            let mut senders = Vec::with_capacity(map.len());
-            for (_, map) in self.map.lock().unwrap().iter() {
-                senders.push(map.clone());
-            }
-            Ok(senders)
+            Ok(map.values().map(|entry| entry.sender.clone()).collect())
+        }
+    }
+
+    fn lag(&self, key: &str) -> (usize, u64) {
+        match self.map.lock().unwrap().get(key) {
+            Some(entry) => (entry.queue.len(), entry.queue.dropped()),
+            None => (0, 0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+
+    const TIMEOUT: Duration = Duration::from_millis(500);
+
+    // waits until the pump of the receiver has taken the queued items, one of them is held by
+    // the pump until the receiver takes it, so the queue is filled up by the next sends alone
+    fn settle<T: Send + Sync + Clone + 'static>(chan: &Chan<T>, key: &str) {
+        let deadline = Instant::now() + TIMEOUT;
+        while chan.lag(key).0 > 0 && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(1));
         }
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    #[test]
+    fn a_full_queue_drops_the_oldest_items() {
+        let chan = Chan::with_capacity(2);
+        let receiver = chan.add_with("a".to_string(), Overflow::DropOldest).unwrap();
+        chan.send(None, 0).unwrap();
+        settle(&chan, "a");
+        for i in 1..5 {
+            chan.send(None, i).unwrap();
+        }
+
+        assert_eq!(receiver.iter().take(3).collect::<Vec<i32>>(), vec![0, 3, 4]);
+        assert_eq!(chan.lag("a"), (0, 2));
+    }
+
+    #[test]
+    fn a_full_queue_drops_the_newest_items() {
+        let chan = Chan::with_capacity(2);
+        let receiver = chan.add_with("a".to_string(), Overflow::DropNewest).unwrap();
+        chan.send(None, 0).unwrap();
+        settle(&chan, "a");
+        for i in 1..5 {
+            chan.send(None, i).unwrap();
+        }
+
+        assert_eq!(receiver.iter().take(3).collect::<Vec<i32>>(), vec![0, 1, 2]);
+        assert!(receiver.recv_timeout(Duration::from_millis(50)).is_err());
+        assert_eq!(chan.lag("a").1, 2);
+    }
+
+    #[test]
+    fn a_full_queue_blocks_the_sender_until_the_receiver_takes_an_item() {
+        let chan = Arc::new(Chan::with_capacity(1));
+        let receiver = chan.add("a".to_string()).unwrap();
+        chan.send(None, 0).unwrap();
+        settle(&chan, "a");
+        chan.send(None, 1).unwrap();
+
+        let sending = chan.clone();
+        let started_at = Instant::now();
+        let sender = thread::spawn(move || sending.send(None, 2).unwrap());
+        thread::sleep(Duration::from_millis(100));
+        assert_eq!(receiver.recv_timeout(TIMEOUT).unwrap(), 0);
+        sender.join().unwrap();
+
+        assert!(started_at.elapsed() >= Duration::from_millis(100));
+        assert_eq!(receiver.iter().take(2).collect::<Vec<i32>>(), vec![1, 2]);
+    }
+
+    #[test]
+    fn a_receiver_which_has_gone_away_is_pruned_and_the_rest_still_get_the_data() {
+        let chan = Chan::with_capacity(1);
+        let gone = chan.add("gone".to_string()).unwrap();
+        let kept = chan.add("kept".to_string()).unwrap();
+        drop(gone);
+
+        // the pump of the gone receiver finds out it has gone away on the first item
+        let deadline = Instant::now() + TIMEOUT;
+        let mut i = 0;
+        while chan.sender(None).unwrap().len() > 1 && Instant::now() < deadline {
+            chan.send(None, i).unwrap();
+            assert_eq!(kept.recv_timeout(TIMEOUT).unwrap(), i);
+            i += 1;
+        }
+
+        assert_eq!(chan.sender(None).unwrap().len(), 1);
+        assert!(chan.send(Some("gone".to_string()), 1).is_err());
+        // the key is free again
+        assert!(chan.add("gone".to_string()).is_ok());
+    }
+
+    #[test]
+    fn each_receiver_is_handled_by_its_own_overflow_policy() {
+        let chan = Chan::with_capacity(1);
+        let blocking = chan.add_with("blocking".to_string(), Overflow::Block).unwrap();
+        let dropping = chan.add_with("dropping".to_string(), Overflow::DropOldest).unwrap();
+
+        chan.send(Some("dropping".to_string()), 0).unwrap();
+        settle(&chan, "dropping");
+        for i in 1..3 {
+            chan.send(Some("dropping".to_string()), i).unwrap();
+        }
+        chan.send(Some("blocking".to_string()), 0).unwrap();
+
+        assert_eq!(dropping.iter().take(2).collect::<Vec<i32>>(), vec![0, 2]);
+        assert_eq!(chan.lag("blocking").1, 0);
+        assert_eq!(blocking.recv_timeout(TIMEOUT).unwrap(), 0);
+    }
+
+    #[test]
+    fn the_items_of_the_sender_reach_the_receiver_through_its_queue() {
+        let chan = Chan::new();
+        let receiver = chan.add("a".to_string()).unwrap();
+
+        for sender in chan.sender(Some("a".to_string())).unwrap() {
+            sender.send(1).unwrap();
+        }
+        assert_eq!(receiver.recv_timeout(TIMEOUT).unwrap(), 1);
+        chan.send(Some("a".to_string()), 2).unwrap();
+
+        assert_eq!(receiver.recv_timeout(TIMEOUT).unwrap(), 2);
+    }
+
+    #[test]
+    fn a_removed_receiver_gets_the_queued_items_then_is_disconnected() {
+        let chan = Chan::new();
+        let receiver = chan.add("a".to_string()).unwrap();
+        for i in 0..3 {
+            chan.send(None, i).unwrap();
+        }
+
+        chan.remove("a".to_string());
+
+        assert_eq!(receiver.iter().collect::<Vec<i32>>(), vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn a_send_by_a_missing_key_fails_instead_of_panicking() {
+        let chan: Chan<i32> = Chan::new();
+
+        assert!(chan.send(Some("missing".to_string()), 1).is_err());
+        assert!(chan.sender(Some("missing".to_string())).is_err());
+        assert!(chan.sender(None).unwrap().is_empty());
     }
 }
@@ -1,7 +1,7 @@
 use std::error::Error;
 use std::fmt;
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub struct NoEntryWasFoundError {}
 impl NoEntryWasFoundError {
     pub fn new() -> NoEntryWasFoundError {
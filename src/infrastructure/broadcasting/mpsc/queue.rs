@@ -0,0 +1,247 @@
+use std::collections::VecDeque;
+use std::sync::mpsc::{RecvError, RecvTimeoutError, SendError, TryRecvError};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+// Overflow tells what a send does when the queue of a receiver is full.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Overflow {
+    // waits until the receiver has taken an item, the sender is held by a slow receiver
+    Block,
+    // drops the oldest queued item, the receiver gets the recent ones
+    DropOldest,
+    // drops the sent item, the receiver gets the old ones
+    DropNewest,
+}
+impl std::str::FromStr for Overflow {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "block" => Ok(Overflow::Block),
+            "drop-oldest" => Ok(Overflow::DropOldest),
+            "drop-newest" => Ok(Overflow::DropNewest),
+            other => Err(format!("unknown overflow policy `{}`, use block, drop-oldest or drop-newest", other)),
+        }
+    }
+}
+impl std::fmt::Display for Overflow {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Overflow::Block => write!(f, "block"),
+            Overflow::DropOldest => write!(f, "drop-oldest"),
+            Overflow::DropNewest => write!(f, "drop-newest"),
+        }
+    }
+}
+
+struct Inner<T> {
+    items: VecDeque<T>,
+    // how many items have been dropped by the overflow policy
+    dropped: u64,
+    senders: usize,
+    is_received: bool,
+}
+
+struct Queue<T> {
+    inner: Mutex<Inner<T>>,
+    // notified when an item has been pushed or the last sender has gone away
+    readable: Condvar,
+    // notified when an item has been taken or the receiver has gone away
+    writable: Condvar,
+    capacity: usize,
+    overflow: Overflow,
+}
+
+impl<T> Queue<T> {
+    fn lock(&self) -> MutexGuard<'_, Inner<T>> {
+        self.inner.lock().unwrap()
+    }
+}
+
+// bounded returns a channel which keeps up to capacity items (one at least), a send to the full
+// channel is handled by the overflow policy.
+pub fn bounded<T>(capacity: usize, overflow: Overflow) -> (Sender<T>, Receiver<T>) {
+    let queue = Arc::new(Queue {
+        inner: Mutex::new(Inner { items: VecDeque::new(), dropped: 0, senders: 1, is_received: true }),
+        readable: Condvar::new(),
+        writable: Condvar::new(),
+        capacity: capacity.max(1),
+        overflow,
+    });
+    (Sender { queue: queue.clone() }, Receiver { queue })
+}
+
+pub struct Sender<T> {
+    queue: Arc<Queue<T>>,
+}
+
+impl<T> Sender<T> {
+    // send queues the item and returns how many items have been dropped to keep it in the bounds,
+    // it fails if the receiver has gone away
+    pub fn send(&self, item: T) -> Result<usize, SendError<T>> {
+        let queue = &self.queue;
+        let mut inner = queue.lock();
+        if queue.overflow == Overflow::Block {
+            while inner.is_received && inner.items.len() >= queue.capacity {
+                inner = queue.writable.wait(inner).unwrap();
+            }
+        }
+        if !inner.is_received {
+            return Err(SendError(item));
+        }
+
+        let mut dropped = 0;
+        if inner.items.len() >= queue.capacity {
+            match queue.overflow {
+                Overflow::DropOldest => {
+                    inner.items.pop_front();
+                }
+                Overflow::DropNewest | Overflow::Block => {
+                    inner.dropped += 1;
+                    return Ok(1);
+                }
+            }
+            inner.dropped += 1;
+            dropped = 1;
+        }
+        inner.items.push_back(item);
+        queue.readable.notify_one();
+
+        Ok(dropped)
+    }
+
+    // len returns how many items the receiver lags behind
+    pub fn len(&self) -> usize {
+        self.queue.lock().items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // dropped returns how many items have been dropped since the channel has been made
+    pub fn dropped(&self) -> u64 {
+        self.queue.lock().dropped
+    }
+
+    // is_disconnected tells whether the receiver has gone away
+    pub fn is_disconnected(&self) -> bool {
+        !self.queue.lock().is_received
+    }
+}
+
+impl<T> Clone for Sender<T> {
+    fn clone(&self) -> Self {
+        self.queue.lock().senders += 1;
+        Sender { queue: self.queue.clone() }
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        let mut inner = self.queue.lock();
+        inner.senders -= 1;
+        if inner.senders == 0 {
+            self.queue.readable.notify_all();
+        }
+    }
+}
+
+// Receiver takes the items in the order they have been sent, the queued items are still taken
+// after all the senders have gone away.
+pub struct Receiver<T> {
+    queue: Arc<Queue<T>>,
+}
+
+impl<T> Receiver<T> {
+    pub fn recv(&self) -> Result<T, RecvError> {
+        let mut inner = self.queue.lock();
+        loop {
+            if let Some(item) = self.take(&mut inner) {
+                return Ok(item);
+            }
+            if inner.senders == 0 {
+                return Err(RecvError);
+            }
+            inner = self.queue.readable.wait(inner).unwrap();
+        }
+    }
+
+    pub fn recv_timeout(&self, timeout: Duration) -> Result<T, RecvTimeoutError> {
+        let deadline = Instant::now() + timeout;
+        let mut inner = self.queue.lock();
+        loop {
+            if let Some(item) = self.take(&mut inner) {
+                return Ok(item);
+            }
+            if inner.senders == 0 {
+                return Err(RecvTimeoutError::Disconnected);
+            }
+            let now = Instant::now();
+            if now >= deadline {
+                return Err(RecvTimeoutError::Timeout);
+            }
+            inner = self.queue.readable.wait_timeout(inner, deadline - now).unwrap().0;
+        }
+    }
+
+    pub fn try_recv(&self) -> Result<T, TryRecvError> {
+        let mut inner = self.queue.lock();
+        match self.take(&mut inner) {
+            Some(item) => Ok(item),
+            None if inner.senders == 0 => Err(TryRecvError::Disconnected),
+            None => Err(TryRecvError::Empty),
+        }
+    }
+
+    // iter blocks for the items until all the senders have gone away
+    pub fn iter(&self) -> impl Iterator<Item = T> + '_ {
+        std::iter::from_fn(|| self.recv().ok())
+    }
+
+    // len returns how many items the receiver lags behind
+    pub fn len(&self) -> usize {
+        self.queue.lock().items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn take(&self, inner: &mut Inner<T>) -> Option<T> {
+        let item = inner.items.pop_front()?;
+        self.queue.writable.notify_one();
+        Some(item)
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        let mut inner = self.queue.lock();
+        inner.is_received = false;
+        inner.items.clear();
+        self.queue.writable.notify_all();
+    }
+}
+
+impl<T> IntoIterator for Receiver<T> {
+    type Item = T;
+    type IntoIter = IntoIter<T>;
+
+    fn into_iter(self) -> IntoIter<T> {
+        IntoIter { receiver: self }
+    }
+}
+
+pub struct IntoIter<T> {
+    receiver: Receiver<T>,
+}
+
+impl<T> Iterator for IntoIter<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        self.receiver.recv().ok()
+    }
+}
@@ -0,0 +1,109 @@
+use chrono::Local;
+use log::kv::{Error, Key, Value, VisitSource};
+use log::Record;
+use serde_json::{Map, Value as Json};
+use std::io::Write;
+
+// LogFormat is the format of the log lines: the human readable text or one JSON object per line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogFormat {
+    Text,
+    Json,
+}
+impl std::str::FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(LogFormat::Text),
+            "json" => Ok(LogFormat::Json),
+            other => Err(format!("unknown log format `{}`, use text or json", other)),
+        }
+    }
+}
+impl std::fmt::Display for LogFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            LogFormat::Text => write!(f, "text"),
+            LogFormat::Json => write!(f, "json"),
+        }
+    }
+}
+
+// Initializes the logger of the app, RUST_LOG takes precedence over the given filter
+// (e.g. "info" or "info,poller=debug"). The key-values of a record (update_id, chat_id, etc.)
+// are written as the fields, so a command may be traced from the poll to the response.
+pub fn init(format: LogFormat, filter: &str) {
+    let filter = std::env::var("RUST_LOG").unwrap_or(filter.to_string());
+
+    let result = env_logger::Builder::new()
+        .parse_filters(filter.as_str())
+        .format(move |buf, record| match format {
+            LogFormat::Text => writeln!(buf, "{}", text(record)),
+            LogFormat::Json => writeln!(buf, "{}", json(record)),
+        })
+        .try_init();
+
+    // the logger may be set once per process
+    if let Err(e) = result {
+        eprintln!("Failed to init the logger: {}.", e);
+    }
+}
+
+fn text(record: &Record) -> String {
+    let mut line = format!(
+        "{} {:<5} {}: {}",
+        Local::now().format("%Y-%m-%dT%H:%M:%S%.3f%:z"),
+        record.level(),
+        record.target(),
+        record.args(),
+    );
+
+    let mut fields = Fields(vec![]);
+    let _ = record.key_values().visit(&mut fields);
+    for (key, value) in fields.0 {
+        match value {
+            Json::String(value) => line.push_str(format!(" {}={:?}", key, value).as_str()),
+            value => line.push_str(format!(" {}={}", key, value).as_str()),
+        }
+    }
+
+    line
+}
+
+fn json(record: &Record) -> String {
+    let mut object = Map::new();
+    object.insert("ts".to_string(), Json::String(Local::now().format("%Y-%m-%dT%H:%M:%S%.3f%:z").to_string()));
+    object.insert("level".to_string(), Json::String(record.level().to_string()));
+    object.insert("target".to_string(), Json::String(record.target().to_string()));
+    object.insert("message".to_string(), Json::String(record.args().to_string()));
+
+    let mut fields = Fields(vec![]);
+    let _ = record.key_values().visit(&mut fields);
+    for (key, value) in fields.0 {
+        object.insert(key, value);
+    }
+
+    Json::Object(object).to_string()
+}
+
+// Fields collects the key-values of a record, the numbers and the booleans are kept as they are,
+// the fields which are None (e.g. chat_id of a scheduled event) are omitted.
+struct Fields(Vec<(String, Json)>);
+impl<'kvs> VisitSource<'kvs> for Fields {
+    fn visit_pair(&mut self, key: Key<'kvs>, value: Value<'kvs>) -> Result<(), Error> {
+        let value = if value.to_borrowed_str().is_none() && value.to_string() == "None" {
+            return Ok(());
+        } else if let Some(value) = value.to_u64() {
+            Json::from(value)
+        } else if let Some(value) = value.to_i64() {
+            Json::from(value)
+        } else if let Some(value) = value.to_bool() {
+            Json::from(value)
+        } else {
+            Json::String(value.to_string())
+        };
+        self.0.push((key.to_string(), value));
+        Ok(())
+    }
+}
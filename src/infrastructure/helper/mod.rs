@@ -1 +1,7 @@
 pub mod date;
+pub mod logger;
+pub mod output;
+pub mod pty;
+pub mod rlimit;
+pub mod signal;
+pub mod user;
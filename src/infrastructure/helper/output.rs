@@ -0,0 +1,87 @@
+use regex::Regex;
+use std::sync::OnceLock;
+
+// How many leading bytes are inspected to tell whether the output is binary.
+const SAMPLE_SIZE: usize = 8 * 1024;
+// Share of undecodable and control characters in the sample after which the output is binary,
+// so a text in a legacy encoding (e.g. Latin-1) with a few non-ASCII letters is still a text.
+const BINARY_RATIO: f64 = 0.3;
+
+// Tells whether the output is binary data rather than a text (in any encoding).
+pub fn is_binary(bytes: &[u8]) -> bool {
+    let sample = &bytes[..bytes.len().min(SAMPLE_SIZE)];
+    if sample.is_empty() {
+        return false;
+    }
+    if sample.contains(&0) {
+        return true;
+    }
+
+    let text = String::from_utf8_lossy(sample);
+    let (mut total, mut suspicious) = (0usize, 0usize);
+    for c in text.chars() {
+        total += 1;
+        let is_control = c.is_control() && !matches!(c, '\n' | '\r' | '\t' | '\x1b' | '\x08' | '\x0c');
+        if c == char::REPLACEMENT_CHARACTER || is_control {
+            suspicious += 1;
+        }
+    }
+
+    suspicious as f64 / total as f64 > BINARY_RATIO
+}
+
+// Removes ANSI escape sequences (colours, cursor movements, window titles) from the text.
+pub fn strip_ansi(text: &str) -> String {
+    static ANSI: OnceLock<Regex> = OnceLock::new();
+    let ansi = ANSI.get_or_init(|| {
+        // CSI sequences, OSC sequences terminated by BEL or ST, and the rest two-byte escapes
+        Regex::new(r"\x1b\[[0-?]*[ -/]*[@-~]|\x1b\][^\x07\x1b]*(?:\x07|\x1b\\)|\x1b[@-Z\\-_]").unwrap()
+    });
+
+    ansi.replace_all(text, "").to_string()
+}
+
+// Decodes the output for display: invalid UTF-8 sequences are replaced and ANSI escapes are removed.
+pub fn to_text(bytes: &[u8]) -> String {
+    strip_ansi(String::from_utf8_lossy(bytes).as_ref())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn an_output_with_nul_bytes_is_binary() {
+        assert!(is_binary(b"ELF\0\x01\x02text"));
+        assert!(!is_binary(b""));
+    }
+
+    #[test]
+    fn a_latin1_text_stays_a_text() {
+        // "Grüße aus Köln" in Latin-1, the umlauts are not valid UTF-8
+        let text = b"Gr\xfc\xdfe aus K\xf6ln\n";
+
+        assert!(!is_binary(text));
+        assert_eq!(to_text(text), "Gr\u{fffd}\u{fffd}e aus K\u{fffd}ln\n");
+    }
+
+    #[test]
+    fn random_bytes_are_binary() {
+        // pseudo-random bytes without NUL, so it is the share of undecodable ones which counts
+        let bytes: Vec<u8> = (0..4096u32).map(|i| (i.wrapping_mul(2654435761) >> 13) as u8 | 0x80).collect();
+
+        assert!(is_binary(&bytes));
+    }
+
+    #[test]
+    fn colour_codes_are_stripped() {
+        assert_eq!(strip_ansi("\x1b[1;31merror\x1b[0m: \x1b[32mok\x1b[m"), "error: ok");
+        assert_eq!(to_text(b"\x1b[38;5;208morange\x1b[0m\n"), "orange\n");
+    }
+
+    #[test]
+    fn window_titles_are_stripped_whether_they_end_in_bel_or_st() {
+        assert_eq!(strip_ansi("\x1b]0;user@host: ~\x07$ ls"), "$ ls");
+        assert_eq!(strip_ansi("\x1b]2;title\x1b\\done"), "done");
+    }
+}
@@ -0,0 +1,61 @@
+use std::fs::File;
+use std::io;
+use std::os::fd::FromRawFd;
+use std::ptr;
+
+// Pty is a pair of pseudo-terminal descriptors: the master side is held by the REPL
+// and the slave side becomes stdin/stdout/stderr of a spawned shell.
+pub struct Pty {
+    pub master: File,
+    pub slave: File,
+}
+
+// Opens a new pseudo-terminal with disabled echo and output post-processing, so the
+// master side reads exactly what the shell and its children have written.
+pub fn open_pty() -> io::Result<Pty> {
+    let mut master: libc::c_int = -1;
+    let mut slave: libc::c_int = -1;
+
+    // safety: the pointers are valid for the whole call, name/termp/winp are optional
+    if unsafe { libc::openpty(&mut master, &mut slave, ptr::null_mut(), ptr::null(), ptr::null()) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    // safety: openpty has returned two freshly opened descriptors which are owned here only
+    let pty = unsafe {
+        Pty {
+            master: File::from_raw_fd(master),
+            slave: File::from_raw_fd(slave),
+        }
+    };
+
+    // safety: termios is a plain C struct which is fully initialized by tcgetattr
+    unsafe {
+        let mut termios: libc::termios = std::mem::zeroed();
+        if libc::tcgetattr(slave, &mut termios) != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        termios.c_lflag &= !(libc::ECHO | libc::ECHONL);
+        termios.c_oflag &= !libc::OPOST;
+        if libc::tcsetattr(slave, libc::TCSANOW, &termios) != 0 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+
+    Ok(pty)
+}
+
+// Makes the slave side of a pty the controlling terminal of the current process,
+// must be called in a child between fork and exec (see CommandExt::pre_exec).
+pub fn make_controlling_terminal() -> io::Result<()> {
+    // safety: both calls are async-signal-safe and affect the calling process only
+    unsafe {
+        if libc::setsid() == -1 {
+            return Err(io::Error::last_os_error());
+        }
+        if libc::ioctl(libc::STDIN_FILENO, libc::TIOCSCTTY as _, 0) == -1 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,41 @@
+use std::io;
+
+// Resource is a kind of limit which may be set on a process by setrlimit.
+#[derive(Debug, Clone, Copy)]
+pub enum Resource {
+    Cpu,
+    AddressSpace,
+    OpenFiles,
+    Processes,
+}
+
+// Sets both soft and hard limits of the current process, must be called in a child between
+// fork and exec (see CommandExt::pre_exec). The CPU hard limit is one second above the soft
+// one, so the process receives SIGXCPU first and may be told apart from a plain kill.
+// The limits are never raised above the current hard ones (it is not permitted anyway).
+pub fn set_rlimit(resource: Resource, value: u64) -> io::Result<()> {
+    let (resource, hard) = match resource {
+        Resource::Cpu => (libc::RLIMIT_CPU, value.saturating_add(1)),
+        Resource::AddressSpace => (libc::RLIMIT_AS, value),
+        Resource::OpenFiles => (libc::RLIMIT_NOFILE, value),
+        Resource::Processes => (libc::RLIMIT_NPROC, value),
+    };
+
+    // safety: getrlimit/setrlimit are async-signal-safe and affect the calling process only
+    unsafe {
+        let mut current: libc::rlimit = std::mem::zeroed();
+        if libc::getrlimit(resource, &mut current) != 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        let max = (hard as libc::rlim_t).min(current.rlim_max);
+        let limit = libc::rlimit {
+            rlim_cur: (value as libc::rlim_t).min(max),
+            rlim_max: max,
+        };
+        if libc::setrlimit(resource, &limit) != 0 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,47 @@
+// Known signals by their names (without the SIG prefix).
+const SIGNALS: &[(&str, i32)] = &[
+    ("HUP", libc::SIGHUP),
+    ("INT", libc::SIGINT),
+    ("QUIT", libc::SIGQUIT),
+    ("ABRT", libc::SIGABRT),
+    ("KILL", libc::SIGKILL),
+    ("USR1", libc::SIGUSR1),
+    ("USR2", libc::SIGUSR2),
+    ("SEGV", libc::SIGSEGV),
+    ("PIPE", libc::SIGPIPE),
+    ("ALRM", libc::SIGALRM),
+    ("TERM", libc::SIGTERM),
+    ("CONT", libc::SIGCONT),
+    ("STOP", libc::SIGSTOP),
+    ("TSTP", libc::SIGTSTP),
+    ("XCPU", libc::SIGXCPU),
+    ("XFSZ", libc::SIGXFSZ),
+];
+
+// Parses a signal given by its number or name, e.g. "9", "KILL", "SIGKILL" or "sigkill".
+pub fn parse_signal(s: &str) -> Option<i32> {
+    if let Ok(number) = s.parse::<i32>() {
+        return Some(number);
+    }
+
+    let name = s.to_ascii_uppercase();
+    let name = name.strip_prefix("SIG").unwrap_or(name.as_str());
+    SIGNALS.iter().find(|(known, _)| *known == name).map(|(_, number)| *number)
+}
+
+// Returns a name of the signal like SIGKILL or None if the signal is unknown.
+pub fn signal_name(signal: i32) -> Option<String> {
+    SIGNALS
+        .iter()
+        .find(|(_, number)| *number == signal)
+        .map(|(name, _)| format!("SIG{}", name))
+}
+
+// Sends the signal to the whole process group of the leader.
+pub fn kill_group(pgid: u32, signal: i32) -> std::io::Result<()> {
+    // safety: a negative pid addresses the process group, kill does not touch the memory
+    if unsafe { libc::kill(-(pgid as libc::pid_t), signal) } != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(())
+}
@@ -0,0 +1,137 @@
+use std::ffi::{CStr, CString};
+use std::io;
+
+// Size of the buffer for getpwnam_r/getgrnam_r string fields.
+const BUF_SIZE: usize = 16 * 1024;
+// Upper bound of supplementary groups of one user.
+const MAX_GROUPS: usize = 256;
+
+// Account is a resolved system user which a command can be run as.
+#[derive(Debug, Clone)]
+pub struct Account {
+    pub name: String,
+    pub uid: u32,
+    pub gid: u32,
+    pub home: String,
+    pub groups: Vec<u32>,
+}
+
+// Resolves a user by name or numeric uid, returns None if there is no such user.
+pub fn find_user(user: &str) -> io::Result<Option<Account>> {
+    let mut pwd: libc::passwd = unsafe { std::mem::zeroed() };
+    let mut buf = vec![0 as libc::c_char; BUF_SIZE];
+    let mut result: *mut libc::passwd = std::ptr::null_mut();
+
+    // safety: all the pointers are valid during the call, the buffer length is passed explicitly
+    let code = match user.parse::<u32>() {
+        Ok(uid) => unsafe { libc::getpwuid_r(uid, &mut pwd, buf.as_mut_ptr(), buf.len(), &mut result) },
+        Err(_) => {
+            let name = CString::new(user).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+            unsafe { libc::getpwnam_r(name.as_ptr(), &mut pwd, buf.as_mut_ptr(), buf.len(), &mut result) }
+        }
+    };
+    if code != 0 {
+        return Err(io::Error::from_raw_os_error(code));
+    }
+    if result.is_null() {
+        return Ok(None);
+    }
+
+    // safety: on success pw_name and pw_dir point to nul-terminated strings inside buf
+    let (name, home) = unsafe {
+        (
+            CStr::from_ptr(pwd.pw_name).to_string_lossy().to_string(),
+            CStr::from_ptr(pwd.pw_dir).to_string_lossy().to_string(),
+        )
+    };
+
+    let groups = supplementary_groups(name.as_str(), pwd.pw_gid)?;
+
+    Ok(Some(Account {
+        name,
+        uid: pwd.pw_uid,
+        gid: pwd.pw_gid,
+        home,
+        groups,
+    }))
+}
+
+// Resolves a group by name or numeric gid, returns None if there is no such group.
+pub fn find_group(group: &str) -> io::Result<Option<u32>> {
+    if let Ok(gid) = group.parse::<u32>() {
+        return Ok(Some(gid));
+    }
+
+    let name = CString::new(group).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    let mut grp: libc::group = unsafe { std::mem::zeroed() };
+    let mut buf = vec![0 as libc::c_char; BUF_SIZE];
+    let mut result: *mut libc::group = std::ptr::null_mut();
+
+    // safety: all the pointers are valid during the call, the buffer length is passed explicitly
+    let code = unsafe { libc::getgrnam_r(name.as_ptr(), &mut grp, buf.as_mut_ptr(), buf.len(), &mut result) };
+    if code != 0 {
+        return Err(io::Error::from_raw_os_error(code));
+    }
+    if result.is_null() {
+        return Ok(None);
+    }
+
+    Ok(Some(grp.gr_gid))
+}
+
+// Drops privileges of the current process to the given groups and uid/gid, must be
+// called in a child between fork and exec (see CommandExt::pre_exec). The order matters:
+// groups and gid can be changed only while the process is still privileged.
+pub fn drop_privileges(uid: Option<u32>, gid: Option<u32>, groups: &[u32]) -> io::Result<()> {
+    // safety: setgroups/setgid/setuid are async-signal-safe and affect the calling process only
+    unsafe {
+        // nothing may be allocated after fork, so the groups are passed as they are
+        // (gid_t is u32 on the supported platforms), they are reset whenever uid or gid is
+        // changed, otherwise the process would keep the supplementary groups of the daemon
+        let is_dropped = uid.is_some() || gid.is_some();
+        if is_dropped && libc::setgroups(groups.len() as _, groups.as_ptr() as *const libc::gid_t) != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        if let Some(gid) = gid {
+            if libc::setgid(gid as libc::gid_t) != 0 {
+                return Err(io::Error::last_os_error());
+            }
+        }
+        if let Some(uid) = uid {
+            if libc::setuid(uid as libc::uid_t) != 0 {
+                return Err(io::Error::last_os_error());
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "macos"))]
+fn supplementary_groups(name: &str, gid: libc::gid_t) -> io::Result<Vec<u32>> {
+    let name = CString::new(name).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    let mut groups: Vec<libc::gid_t> = vec![0; MAX_GROUPS];
+    let mut count = groups.len() as libc::c_int;
+
+    // safety: the groups buffer holds `count` elements, the name is nul-terminated
+    if unsafe { libc::getgrouplist(name.as_ptr(), gid, groups.as_mut_ptr(), &mut count) } == -1 {
+        return Err(io::Error::other("too many supplementary groups"));
+    }
+    groups.truncate(count as usize);
+
+    Ok(groups)
+}
+
+#[cfg(target_os = "macos")]
+fn supplementary_groups(name: &str, gid: libc::gid_t) -> io::Result<Vec<u32>> {
+    let name = CString::new(name).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    let mut groups: Vec<libc::c_int> = vec![0; MAX_GROUPS];
+    let mut count = groups.len() as libc::c_int;
+
+    // safety: the groups buffer holds `count` elements, the name is nul-terminated
+    if unsafe { libc::getgrouplist(name.as_ptr(), gid as libc::c_int, groups.as_mut_ptr(), &mut count) } == -1 {
+        return Err(io::Error::other("too many supplementary groups"));
+    }
+    groups.truncate(count as usize);
+
+    Ok(groups.into_iter().map(|g| g as u32).collect())
+}
@@ -1,6 +1,6 @@
 use crate::infrastructure::integration;
 use integration::telegram;
-use integration::telegram::model::{GetUpdatesResponse, SendMessageResponse};
+use integration::telegram::model::{GetFileResponse, GetMeResponse, GetUpdatesResponse, SendMessageResponse};
 use std::error::Error;
 
 pub trait TelegramFacadeTrait: telegram::service::TelegramServiceTrait + Send + Sync {}
@@ -21,11 +21,28 @@ impl telegram::service::TelegramServiceTrait for TelegramFacade {
     }
     fn send_message(
         &self,
-        chat_id: u64,
+        chat_id: i64,
         message: &str,
     ) -> Result<SendMessageResponse, Box<dyn Error>> {
         self.service.send_message(chat_id, message)
     }
+    fn send_document(
+        &self,
+        chat_id: i64,
+        file_name: &str,
+        data: Vec<u8>,
+    ) -> Result<SendMessageResponse, Box<dyn Error>> {
+        self.service.send_document(chat_id, file_name, data)
+    }
+    fn get_file(&self, file_id: &str) -> Result<GetFileResponse, Box<dyn Error>> {
+        self.service.get_file(file_id)
+    }
+    fn download_file(&self, file_path: &str, limit: u64) -> Result<Vec<u8>, Box<dyn Error>> {
+        self.service.download_file(file_path, limit)
+    }
+    fn get_me(&self) -> Result<GetMeResponse, Box<dyn Error>> {
+        self.service.get_me()
+    }
 }
 
 impl TelegramFacadeTrait for TelegramFacade {}
@@ -1,3 +1,4 @@
+use reqwest::blocking::multipart::{Form, Part};
 use reqwest::blocking::{Client as ReqwestClient, Response};
 use reqwest::Error;
 use std::time::Duration;
@@ -5,10 +6,18 @@ use std::time::Duration;
 const TELEGRAM_API_URL: &str = "https://api.telegram.org";
 const TELEGRAM_API_SEND_MESSAGE_METHOD: &str = "sendMessage";
 const TELEGRAM_API_FETCH_MESSAGES_METHOD: &str = "getUpdates";
+const TELEGRAM_API_GET_FILE_METHOD: &str = "getFile";
+const TELEGRAM_API_SEND_DOCUMENT_METHOD: &str = "sendDocument";
+const TELEGRAM_API_GET_ME_METHOD: &str = "getMe";
 
+// The errors are returned without the url, since it holds the token and the errors are logged.
 pub trait HttpClient: Send + Sync {
-    fn send_message(&self, chat_id: u64, msg: &str) -> Result<Response, Error>;
+    fn send_message(&self, chat_id: i64, msg: &str) -> Result<Response, Error>;
+    fn send_document(&self, chat_id: i64, file_name: &str, data: Vec<u8>) -> Result<Response, Error>;
     fn get_updates(&self, offset: i64) -> Result<Response, Error>;
+    fn get_file(&self, file_id: &str) -> Result<Response, Error>;
+    fn download_file(&self, file_path: &str) -> Result<Response, Error>;
+    fn get_me(&self) -> Result<Response, Error>;
 }
 
 pub struct Client {
@@ -23,7 +32,7 @@ impl Client {
 }
 
 impl HttpClient for Client {
-    fn send_message(&self, chat_id: u64, msg: &str) -> Result<Response, Error> {
+    fn send_message(&self, chat_id: i64, msg: &str) -> Result<Response, Error> {
         ReqwestClient::new()
             .post(format!(
                 "{}/bot{}/{}?parse_mode=Markdown",
@@ -35,6 +44,23 @@ impl HttpClient for Client {
                 "text": msg,
             }))
             .send()
+            .map_err(Error::without_url)
+    }
+
+    fn send_document(&self, chat_id: i64, file_name: &str, data: Vec<u8>) -> Result<Response, Error> {
+        let form = Form::new()
+            .text("chat_id", chat_id.to_string())
+            .part("document", Part::bytes(data).file_name(file_name.to_string()));
+
+        ReqwestClient::new()
+            .post(format!(
+                "{}/bot{}/{}",
+                TELEGRAM_API_URL, self.token, TELEGRAM_API_SEND_DOCUMENT_METHOD
+            ))
+            .timeout(self.timeout)
+            .multipart(form)
+            .send()
+            .map_err(Error::without_url)
     }
 
     fn get_updates(&self, offset: i64) -> Result<Response, Error> {
@@ -46,5 +72,37 @@ impl HttpClient for Client {
                 TELEGRAM_API_URL, self.token, TELEGRAM_API_FETCH_MESSAGES_METHOD, offset
             ))
             .send()
+            .map_err(Error::without_url)
+    }
+
+    fn get_file(&self, file_id: &str) -> Result<Response, Error> {
+        ReqwestClient::builder()
+            .timeout(self.timeout)
+            .build()?
+            .get(format!(
+                "{}/bot{}/{}",
+                TELEGRAM_API_URL, self.token, TELEGRAM_API_GET_FILE_METHOD
+            ))
+            .query(&[("file_id", file_id)])
+            .send()
+            .map_err(Error::without_url)
+    }
+
+    fn download_file(&self, file_path: &str) -> Result<Response, Error> {
+        ReqwestClient::builder()
+            .timeout(self.timeout)
+            .build()?
+            .get(format!("{}/file/bot{}/{}", TELEGRAM_API_URL, self.token, file_path))
+            .send()
+            .map_err(Error::without_url)
+    }
+
+    fn get_me(&self) -> Result<Response, Error> {
+        ReqwestClient::builder()
+            .timeout(self.timeout)
+            .build()?
+            .get(format!("{}/bot{}/{}", TELEGRAM_API_URL, self.token, TELEGRAM_API_GET_ME_METHOD))
+            .send()
+            .map_err(Error::without_url)
     }
 }
@@ -1,4 +1,6 @@
 pub mod facade;
 pub mod http;
 pub mod model;
+pub mod poller;
 pub mod service;
+pub mod transport;
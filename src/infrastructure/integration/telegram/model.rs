@@ -13,6 +13,20 @@ pub struct SendMessageResponse {
     pub result: Message,
 }
 
+// GetFileResponse struct present a telegram response on getFile method.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct GetFileResponse {
+    pub ok: bool,
+    pub result: File,
+}
+
+// GetMeResponse struct present a telegram response on getMe method (the bot itself).
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct GetMeResponse {
+    pub ok: bool,
+    pub result: User,
+}
+
 // Update is a single message structure. Telegram sends a list of Update
 // structs.
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -28,7 +42,12 @@ pub struct Message {
     pub from: User,
     pub chat: Chat,
     pub date: i64,
+    // messages with a document or a photo have no text
+    #[serde(default)]
     pub text: String,
+    pub caption: Option<String>,
+    pub document: Option<Document>,
+    pub reply_to_message: Option<Box<Message>>,
 }
 // User details.
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -48,3 +67,18 @@ pub struct Chat {
     pub username: String,
     pub r#type: String,
 }
+// Document (a general file) details.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Document {
+    pub file_id: String,
+    pub file_name: Option<String>,
+    pub mime_type: Option<String>,
+    pub file_size: Option<u64>,
+}
+// File details, file_path is used to download the file content.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct File {
+    pub file_id: String,
+    pub file_size: Option<u64>,
+    pub file_path: Option<String>,
+}
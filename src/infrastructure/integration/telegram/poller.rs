@@ -0,0 +1,226 @@
+use std::ops::Add;
+use crate::app::model::health::{self, Health};
+use crate::app::model::state::State;
+use crate::domain::error::message::{OffsetFetchError, UnknownMessageTypeError};
+use crate::domain::model::message::Message;
+use crate::infrastructure::service::message::provider::Provider;
+use crate::infrastructure::integration::telegram;
+use std::fs;
+use std::io;
+use std::sync::{mpsc, Arc};
+use std::time::Duration;
+use chrono::Local;
+use crate::app::cfg::handle::CfgHandle;
+use crate::infrastructure::metrics::metrics::Metrics;
+
+// LongPoller is the provider of the telegram transport, it polls the updates of the chat.
+pub struct LongPoller {
+    cfg: CfgHandle,
+    state: Arc<Box<dyn State>>,
+    telegram: Arc<Box<dyn telegram::facade::TelegramFacadeTrait>>,
+    metrics: Arc<Metrics>,
+    health: Arc<Health>,
+}
+impl LongPoller {
+    pub fn new(
+        cfg: CfgHandle,
+        state: Arc<Box<dyn State>>,
+        telegram: Arc<Box<dyn telegram::facade::TelegramFacadeTrait>>,
+        metrics: Arc<Metrics>,
+        health: Arc<Health>,
+    ) -> Self {
+        LongPoller {
+            cfg,
+            state,
+            telegram,
+            metrics,
+            health,
+        }
+    }
+}
+impl LongPoller {
+    // returns a new offset or propagated a panic!
+    fn get_offset_with_retries(&self) -> i64 {
+        let threshold = Local::now().naive_local().add(chrono::Duration::seconds(self.cfg.get().poll_frequency.as_secs() as i64));
+        while Local::now().naive_local() < threshold {
+            self.health.beat(health::POLLER);
+            match self.query_offset() {
+                Ok(offset) => {
+                    log::info!(target: "poller", offset = offset; "Offset has been received, start processing messages...");
+                    return offset;
+                },
+                Err(e) => log::warn!(target: "poller", "{}", e)
+            };
+
+            std::thread::sleep(Duration::from_secs(1));
+        }
+
+        panic!("Failed to query offset, timeout exceeded.");
+    }
+    // returns a new offset (a last msg id + 1)
+    fn query_offset(&self) -> Result<i64, OffsetFetchError> {
+        let response = match self.telegram.get_updates(0) {
+            Ok(response) => response,
+            Err(error) => return Err(OffsetFetchError::new(Some(error))),
+        };
+
+        if !response.ok {
+            return Err(OffsetFetchError::new(None));
+        }
+
+        if response.result.is_empty() {
+            return Ok(0);
+        }
+
+        if let Some(l) = response.result.last() {
+            return Ok(l.update_id + 1);
+        } else {
+            let json = serde_json::to_string(&response.result).unwrap();
+            log::error!(target: "poller", "Unknown response from telegram: {}.", json);
+        }
+
+        Err(OffsetFetchError::new(None))
+    }
+    // returns the offset which has been saved by the previous run (if any)
+    fn load_offset(&self) -> Option<i64> {
+        let cfg = self.cfg.get();
+        let path = cfg.offset_filepath.as_ref()?;
+        match fs::read_to_string(path) {
+            Ok(data) => match data.trim().parse::<i64>() {
+                Ok(offset) => Some(offset),
+                Err(e) => {
+                    log::warn!(target: "poller", "Failed to parse the saved offset in {}: {}.", path, e);
+                    None
+                }
+            },
+            Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+            Err(e) => {
+                log::warn!(target: "poller", "Failed to read the saved offset from {}: {}.", path, e);
+                None
+            }
+        }
+    }
+    // saves the offset, so the messages which arrive while the app is down are handled after a restart
+    fn save_offset(&self, offset: i64) {
+        let cfg = self.cfg.get();
+        let Some(path) = cfg.offset_filepath.as_ref() else {
+            return;
+        };
+
+        // written next to the target and renamed, so a crash does not leave a half written file
+        let tmp = format!("{}.tmp", path);
+        if let Err(e) = fs::write(&tmp, offset.to_string()).and_then(|_| fs::rename(&tmp, path)) {
+            log::error!(target: "poller", "Failed to save the offset into {}: {}.", path, e);
+        }
+    }
+    fn extract_msg(
+        msg: Option<telegram::model::Message>,
+        edited_msg: Option<telegram::model::Message>,
+    ) -> Result<telegram::model::Message, UnknownMessageTypeError> {
+        if msg.is_some() {
+            let Some(message) = msg else {
+                panic!("Logic error, must not be here due to message already is Some.")
+            };
+            Ok(message)
+        } else if edited_msg.is_some() {
+            let Some(message) = edited_msg else {
+                panic!("Logic error, must not be here due to edited_message already is Some.")
+            };
+            Ok(message)
+        } else {
+            log::warn!(
+                target: "poller",
+                "Another one unknown message type. Dump the json and check what's new up there."
+            );
+            Err(UnknownMessageTypeError::new())
+        }
+    }
+    fn is_must_be_skipped(
+        &self,
+        msg: Option<telegram::model::Message>,
+        edited_msg: Option<telegram::model::Message>,
+    ) -> bool {
+        let chat_id = self.cfg.get().chat_id;
+        if let Some(message) = msg {
+            if chat_id != message.chat.id {
+                return true;
+            }
+        }
+        if let Some(message) = edited_msg {
+            if chat_id != message.chat.id {
+                return true;
+            }
+        }
+        false
+    }
+}
+impl Provider for LongPoller {
+    fn provide(&mut self, out: mpsc::SyncSender<Message>) {
+        if self.state.is_closed() {
+            return;
+        }
+
+        let mut offset = match self.load_offset() {
+            Some(offset) => {
+                log::info!(target: "poller", offset = offset; "Offset has been restored, continue processing messages...");
+                offset
+            }
+            None => self.get_offset_with_retries(),
+        };
+
+        loop {
+            if self.state.is_closed() {
+                self.save_offset(offset);
+                return;
+            }
+
+            self.health.beat(health::POLLER);
+            self.metrics.polls.inc(&[]);
+            match self.telegram.get_updates(offset) {
+                Ok(r) => {
+                    let is_empty = r.result.is_empty();
+                    self.metrics.updates.add(&[], r.result.len() as u64);
+                    for update in r.result {
+                        // handle messages just from myself
+                        if !self.is_must_be_skipped(update.message.clone(), update.edited_message.clone()) {
+                            // joining of message and edited message
+                            // (will be selected just one of which is not None),
+                            // the rest of the app gets the transport-neutral one
+                            let msg = Message::from(Self::extract_msg(update.message, update.edited_message).unwrap());
+                            // the update is traced further by the chat and the message ids
+                            log::info!(
+                                target: "poller",
+                                update_id = update.update_id,
+                                chat_id = msg.chat_id,
+                                message_id = msg.id;
+                                "Update has been received."
+                            );
+
+                            // send the message to the other side, the consumer is gone on shutdown
+                            if out.send(msg).is_err() {
+                                self.save_offset(offset);
+                                return;
+                            }
+                        }
+
+                        // calculate a new offset
+                        offset = update.update_id + 1;
+                    }
+                    if !is_empty {
+                        self.save_offset(offset);
+                    }
+                }
+                Err(e) => {
+                    self.metrics.poll_errors.inc(&[]);
+                    log::warn!(target: "poller", "Error getting updates: {}", e)
+                }
+            };
+
+            // the frequency is read on each iteration, so a reloaded one applies at once
+            let freq = self.cfg.get().poll_frequency;
+            if freq > Duration::from_secs(0) {
+                std::thread::sleep(freq);
+            }
+        }
+    }
+}
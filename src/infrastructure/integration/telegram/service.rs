@@ -1,52 +1,138 @@
 use crate::infrastructure::integration;
 use integration::telegram::http::HttpClient;
-use integration::telegram::model::{GetUpdatesResponse, SendMessageResponse};
+use integration::telegram::model::{GetFileResponse, GetMeResponse, GetUpdatesResponse, SendMessageResponse};
+use crate::infrastructure::metrics::metrics::Metrics;
+use crate::domain::error::exec::StdinTooLargeError;
 use std::error::Error;
+use std::io::Read;
+use std::sync::Arc;
+use std::time::Instant;
 
 pub trait TelegramServiceTrait: Send + Sync {
     fn get_updates(&self, offset: i64) -> Result<GetUpdatesResponse, Box<dyn Error>>;
     fn send_message(
         &self,
-        chat_id: u64,
+        chat_id: i64,
         message: &str,
     ) -> Result<SendMessageResponse, Box<dyn Error>>;
+    fn send_document(
+        &self,
+        chat_id: i64,
+        file_name: &str,
+        data: Vec<u8>,
+    ) -> Result<SendMessageResponse, Box<dyn Error>>;
+    fn get_file(&self, file_id: &str) -> Result<GetFileResponse, Box<dyn Error>>;
+    // download_file fails as soon as the file turns out to be larger than the limit
+    fn download_file(&self, file_path: &str, limit: u64) -> Result<Vec<u8>, Box<dyn Error>>;
+    fn get_me(&self) -> Result<GetMeResponse, Box<dyn Error>>;
 }
 
 pub struct TelegramService {
     http_client: Box<dyn HttpClient>,
+    metrics: Arc<Metrics>,
 }
 impl TelegramService {
-    pub fn new(http_client: Box<dyn HttpClient>) -> Self {
-        Self { http_client }
+    pub fn new(http_client: Box<dyn HttpClient>, metrics: Arc<Metrics>) -> Self {
+        Self { http_client, metrics }
+    }
+
+    // observed measures the latency of the request and counts it if it has failed
+    fn observed<T>(&self, method: &str, request: impl FnOnce() -> Result<T, Box<dyn Error>>) -> Result<T, Box<dyn Error>> {
+        let started_at = Instant::now();
+        let result = request();
+        self.metrics.observe_telegram(method, started_at.elapsed(), result.is_err());
+        result
     }
 }
 
 impl TelegramServiceTrait for TelegramService {
     fn get_updates(&self, offset: i64) -> Result<GetUpdatesResponse, Box<dyn Error>> {
-        let data = self.http_client.get_updates(offset)?.text()?;
-        match serde_json::from_str(&data) {
-            Ok(data) => Ok(data),
-            Err(err) => {
-                println!("Failed to decode getUpdates method json response: {}", data);
-                Err(Box::new(err))
+        self.observed("getUpdates", || {
+            let data = self.http_client.get_updates(offset)?.text()?;
+            match serde_json::from_str(&data) {
+                Ok(data) => Ok(data),
+                Err(err) => {
+                    log::error!(target: "telegram", "Failed to decode getUpdates method json response: {}", data);
+                    Err(Box::new(err))
+                }
             }
-        }
+        })
     }
     fn send_message(
         &self,
-        chat_id: u64,
+        chat_id: i64,
         message: &str,
     ) -> Result<SendMessageResponse, Box<dyn Error>> {
-        let data = self.http_client.send_message(chat_id, message)?.text()?;
-        match serde_json::from_str(&data) {
-            Ok(data) => Ok(data),
-            Err(err) => {
-                println!(
-                    "Failed to decode sendMessage method json response: {}",
-                    data
-                );
-                Err(Box::new(err))
+        self.observed("sendMessage", || {
+            let data = self.http_client.send_message(chat_id, message)?.text()?;
+            match serde_json::from_str(&data) {
+                Ok(data) => Ok(data),
+                Err(err) => {
+                    log::error!(target: "telegram", "Failed to decode sendMessage method json response: {}", data);
+                    Err(Box::new(err))
+                }
+            }
+        })
+    }
+    fn send_document(
+        &self,
+        chat_id: i64,
+        file_name: &str,
+        data: Vec<u8>,
+    ) -> Result<SendMessageResponse, Box<dyn Error>> {
+        self.observed("sendDocument", || {
+            let data = self.http_client.send_document(chat_id, file_name, data)?.text()?;
+            match serde_json::from_str(&data) {
+                Ok(data) => Ok(data),
+                Err(err) => {
+                    log::error!(target: "telegram", "Failed to decode sendDocument method json response: {}", data);
+                    Err(Box::new(err))
+                }
+            }
+        })
+    }
+    fn get_file(&self, file_id: &str) -> Result<GetFileResponse, Box<dyn Error>> {
+        self.observed("getFile", || {
+            let data = self.http_client.get_file(file_id)?.text()?;
+            match serde_json::from_str(&data) {
+                Ok(data) => Ok(data),
+                Err(err) => {
+                    log::error!(target: "telegram", "Failed to decode getFile method json response: {}", data);
+                    Err(Box::new(err))
+                }
+            }
+        })
+    }
+    fn download_file(&self, file_path: &str, limit: u64) -> Result<Vec<u8>, Box<dyn Error>> {
+        self.observed("downloadFile", || {
+            let response = self
+                .http_client
+                .download_file(file_path)?
+                .error_for_status()
+                .map_err(reqwest::Error::without_url)?;
+            if response.content_length().is_some_and(|length| length > limit) {
+                return Err(Box::new(StdinTooLargeError::new(limit)));
+            }
+
+            // the length may be unknown, so the body is read just up to one byte over the limit
+            let mut data = vec![];
+            response.take(limit.saturating_add(1)).read_to_end(&mut data)?;
+            if data.len() as u64 > limit {
+                return Err(Box::new(StdinTooLargeError::new(limit)));
+            }
+            Ok(data)
+        })
+    }
+    fn get_me(&self) -> Result<GetMeResponse, Box<dyn Error>> {
+        self.observed("getMe", || {
+            let data = self.http_client.get_me()?.text()?;
+            match serde_json::from_str(&data) {
+                Ok(data) => Ok(data),
+                Err(err) => {
+                    log::error!(target: "telegram", "Failed to decode getMe method json response: {}", data);
+                    Err(Box::new(err))
+                }
             }
-        }
+        })
     }
 }
@@ -0,0 +1,162 @@
+use crate::domain::error::exec::{FileUnavailableError, StdinTooLargeError};
+use crate::domain::error::executor::PartiallyDeliveredError;
+use crate::domain::model::message::{self, File, Reply, TEXT_PART};
+use crate::domain::service::transport::transport::Transport;
+use crate::infrastructure::integration::telegram;
+use std::error::Error;
+use std::sync::Arc;
+
+// TelegramTransport is the telegram adapter of the transport, the messages are polled by the
+// long poller.
+pub struct TelegramTransport {
+    telegram: Arc<Box<dyn telegram::facade::TelegramFacadeTrait>>,
+}
+
+impl TelegramTransport {
+    pub fn new(telegram: Arc<Box<dyn telegram::facade::TelegramFacadeTrait>>) -> TelegramTransport {
+        TelegramTransport { telegram }
+    }
+}
+
+impl Transport for TelegramTransport {
+    fn name(&self) -> String {
+        "telegram".to_string()
+    }
+
+    fn send(&self, reply: Reply) -> Result<(), Box<dyn Error>> {
+        // each part is a message of its own, the ones which have been sent are told by the error
+        let mut sent = vec![];
+        if !reply.text.is_empty() {
+            self.telegram.send_message(reply.chat_id, reply.text.as_str())?;
+            sent.push(TEXT_PART.to_string());
+        }
+        for attachment in reply.attachments {
+            let part = attachment.part();
+            if let Err(e) = self.telegram.send_document(reply.chat_id, attachment.name.as_str(), attachment.data) {
+                return Err(Box::new(PartiallyDeliveredError::new(sent, e.to_string())));
+            }
+            sent.push(part);
+        }
+        Ok(())
+    }
+
+    fn download(&self, file: &File, limit: u64) -> Result<Vec<u8>, Box<dyn Error>> {
+        if file.size.is_some_and(|size| size > limit) {
+            return Err(Box::new(StdinTooLargeError::new(limit)));
+        }
+
+        let response = self.telegram.get_file(file.id.as_str())?.result;
+        // telegram does not give the files over 20MB
+        let Some(file_path) = response.file_path else {
+            return Err(Box::new(FileUnavailableError::new(file.id.clone())));
+        };
+        self.telegram.download_file(file_path.as_str(), limit)
+    }
+}
+
+impl From<telegram::model::Message> for message::Message {
+    fn from(msg: telegram::model::Message) -> Self {
+        message::Message {
+            id: msg.message_id,
+            chat_id: msg.chat.id,
+            user: message::User { id: msg.from.id, username: msg.from.username },
+            date: msg.date,
+            text: msg.text,
+            caption: msg.caption,
+            file: msg.document.map(|document| File {
+                id: document.file_id,
+                name: document.file_name,
+                size: document.file_size,
+            }),
+            reply_to: msg.reply_to_message.map(|reply| Box::new(message::Message::from(*reply))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::model::message::Attachment;
+    use crate::infrastructure::integration::telegram::model::{GetFileResponse, GetMeResponse, GetUpdatesResponse, SendMessageResponse};
+    use std::sync::Mutex;
+
+    const MESSAGE: &str = r#"{
+        "message_id": 1, "date": 0, "text": "",
+        "from": {"id": 10, "is_bot": false, "first_name": "A", "username": "a"},
+        "chat": {"id": 20, "first_name": "A", "username": "a", "type": "private"}
+    }"#;
+
+    // TestTelegram keeps the parts which have been sent, the documents fail while it is offline.
+    struct TestTelegram {
+        sent: Arc<Mutex<Vec<String>>>,
+        is_offline: Arc<Mutex<bool>>,
+    }
+    impl TestTelegram {
+        fn sent(&self, part: String) -> Result<SendMessageResponse, Box<dyn Error>> {
+            self.sent.lock().unwrap().push(part);
+            Ok(SendMessageResponse { ok: true, result: serde_json::from_str(MESSAGE)? })
+        }
+    }
+    impl telegram::service::TelegramServiceTrait for TestTelegram {
+        fn get_updates(&self, _: i64) -> Result<GetUpdatesResponse, Box<dyn Error>> {
+            unimplemented!()
+        }
+        fn send_message(&self, _: i64, message: &str) -> Result<SendMessageResponse, Box<dyn Error>> {
+            self.sent(message.to_string())
+        }
+        fn send_document(&self, _: i64, file_name: &str, _: Vec<u8>) -> Result<SendMessageResponse, Box<dyn Error>> {
+            if *self.is_offline.lock().unwrap() {
+                return Err("offline".into());
+            }
+            self.sent(file_name.to_string())
+        }
+        fn get_file(&self, _: &str) -> Result<GetFileResponse, Box<dyn Error>> {
+            unimplemented!()
+        }
+        fn download_file(&self, _: &str, _: u64) -> Result<Vec<u8>, Box<dyn Error>> {
+            unimplemented!()
+        }
+        fn get_me(&self) -> Result<GetMeResponse, Box<dyn Error>> {
+            unimplemented!()
+        }
+    }
+    impl telegram::facade::TelegramFacadeTrait for TestTelegram {}
+
+    #[test]
+    fn a_retried_reply_does_not_send_the_parts_which_have_been_sent() {
+        let (sent, is_offline) = (Arc::new(Mutex::new(vec![])), Arc::new(Mutex::new(true)));
+        let transport = TelegramTransport::new(Arc::new(Box::new(TestTelegram { sent: sent.clone(), is_offline: is_offline.clone() })));
+        let reply = Reply::new(-20, "result".to_string(), vec![Attachment::new("stdout.bin".to_string(), vec![0])]);
+
+        let error = transport.send(reply.clone()).err().unwrap();
+        let delivered = error.downcast_ref::<PartiallyDeliveredError>().unwrap().delivered().to_vec();
+        assert_eq!(delivered, vec![TEXT_PART.to_string()]);
+
+        *is_offline.lock().unwrap() = false;
+        transport.send(reply.without(&delivered)).unwrap();
+
+        assert_eq!(*sent.lock().unwrap(), vec!["result", "stdout.bin"]);
+    }
+
+    #[test]
+    fn a_telegram_message_is_converted_along_with_its_reply_and_file() {
+        let json = r#"{
+            "message_id": 2, "date": 1, "text": "/cmd wc -l",
+            "from": {"id": 10, "is_bot": false, "first_name": "A", "username": "a"},
+            "chat": {"id": 20, "first_name": "A", "username": "a", "type": "private"},
+            "reply_to_message": {
+                "message_id": 1, "date": 0, "caption": "lines",
+                "from": {"id": 10, "is_bot": false, "first_name": "A", "username": "a"},
+                "chat": {"id": 20, "first_name": "A", "username": "a", "type": "private"},
+                "document": {"file_id": "f1", "file_name": "lines.txt", "file_size": 42}
+            }
+        }"#;
+        let msg = message::Message::from(serde_json::from_str::<telegram::model::Message>(json).unwrap());
+
+        assert_eq!((msg.id, msg.chat_id, msg.user.id, msg.text.as_str()), (2, 20, 10, "/cmd wc -l"));
+        let reply = msg.reply_to.unwrap();
+        assert_eq!((reply.text.as_str(), reply.caption.as_deref()), ("", Some("lines")));
+        let file = reply.file.unwrap();
+        assert_eq!((file.id.as_str(), file.name.as_deref(), file.size), ("f1", Some("lines.txt"), Some(42)));
+    }
+}
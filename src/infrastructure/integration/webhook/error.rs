@@ -0,0 +1,41 @@
+use std::error::Error;
+use std::fmt;
+
+#[derive(Debug)]
+pub struct WebhookStatusError {
+    status: u16,
+}
+impl WebhookStatusError {
+    pub fn new(status: u16) -> WebhookStatusError {
+        WebhookStatusError { status }
+    }
+
+    // tells whether the request may succeed if it is sent again
+    pub fn is_retryable(&self) -> bool {
+        self.status == 429 || self.status >= 500
+    }
+}
+impl fmt::Display for WebhookStatusError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "The webhook has responded with status {}.", self.status)
+    }
+}
+impl Error for WebhookStatusError {}
+
+#[derive(Debug)]
+pub struct WebhookFailedError {
+    // the hosts of the webhooks along with their last errors
+    failures: Vec<(String, String)>,
+}
+impl WebhookFailedError {
+    pub fn new(failures: Vec<(String, String)>) -> WebhookFailedError {
+        WebhookFailedError { failures }
+    }
+}
+impl fmt::Display for WebhookFailedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let failures: Vec<String> = self.failures.iter().map(|(host, error)| format!("{}: {}", host, error)).collect();
+        write!(f, "Failed to post to the webhooks: {}", failures.join("; "))
+    }
+}
+impl Error for WebhookFailedError {}
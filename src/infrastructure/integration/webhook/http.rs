@@ -0,0 +1,41 @@
+use crate::infrastructure::integration::webhook::error::WebhookStatusError;
+use reqwest::blocking::Client as ReqwestClient;
+use std::error::Error;
+use std::time::Duration;
+
+pub trait HttpClient: Send + Sync {
+    // post sends the JSON body to the url, a response other than 2xx is WebhookStatusError
+    fn post(&self, url: &str, headers: &[(&str, String)], body: Vec<u8>, timeout: Duration) -> Result<(), Box<dyn Error>>;
+}
+
+pub struct Client {
+    client: ReqwestClient,
+}
+
+impl Client {
+    pub fn new() -> Self {
+        Self { client: ReqwestClient::new() }
+    }
+}
+
+impl Default for Client {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HttpClient for Client {
+    fn post(&self, url: &str, headers: &[(&str, String)], body: Vec<u8>, timeout: Duration) -> Result<(), Box<dyn Error>> {
+        let mut request = self.client.post(url).timeout(timeout).header("Content-Type", "application/json");
+        for (name, value) in headers {
+            request = request.header(*name, value.as_str());
+        }
+
+        // the url may hold a token, so it is not kept in the error
+        let response = request.body(body).send().map_err(reqwest::Error::without_url)?;
+        if !response.status().is_success() {
+            return Err(Box::new(WebhookStatusError::new(response.status().as_u16())));
+        }
+        Ok(())
+    }
+}
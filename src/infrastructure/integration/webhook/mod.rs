@@ -0,0 +1,5 @@
+pub mod error;
+pub mod http;
+pub mod model;
+pub mod service;
+pub mod subscriber;
@@ -0,0 +1,93 @@
+use crate::domain::model::lifecycle::Lifecycle;
+use crate::infrastructure::model::command::Exit;
+use chrono::Local;
+use serde::Serialize;
+
+// The event of the result payload, the lifecycle ones are named by their topics.
+pub const RESULT_EVENT: &str = "command.result";
+
+const TIMESTAMP_FORMAT: &str = "%Y-%m-%dT%H:%M:%S%.3f%:z";
+
+#[derive(Serialize, Debug, Clone)]
+pub struct AttachmentPayload {
+    pub name: String,
+    pub bytes: usize,
+}
+
+// ResultPayload is the result of a command as it is POSTed to the webhooks.
+#[derive(Serialize, Debug, Clone)]
+pub struct ResultPayload {
+    pub event: String,
+    pub timestamp: String,
+    pub chat_id: Option<i64>,
+    pub message_id: Option<i64>,
+    pub user_id: Option<i64>,
+    pub username: Option<String>,
+    // the text of the message as it has been received
+    pub input: Option<String>,
+    pub argv: Option<Vec<String>>,
+    pub code: String,
+    pub exit_code: String,
+    pub stdout: String,
+    pub stderr: String,
+    // the attachments are not sent, just their names and sizes
+    pub attachments: Vec<AttachmentPayload>,
+    pub finished_at: Option<String>,
+    pub duration_ms: Option<u64>,
+}
+
+impl ResultPayload {
+    pub fn new(exit: &Exit) -> ResultPayload {
+        let message = exit.input_message.as_ref();
+        ResultPayload {
+            event: RESULT_EVENT.to_string(),
+            timestamp: Local::now().format(TIMESTAMP_FORMAT).to_string(),
+            chat_id: message.map(|message| message.chat_id),
+            message_id: message.map(|message| message.id),
+            user_id: message.map(|message| message.user.id),
+            username: message.map(|message| message.user.username.clone()),
+            input: message.map(|message| message.text.clone()),
+            argv: exit.argv.clone(),
+            code: exit.code.to_string(),
+            exit_code: exit.code.label(),
+            stdout: exit.stdout.clone(),
+            stderr: exit.stderr.clone(),
+            attachments: exit
+                .attachments
+                .iter()
+                .map(|attachment| AttachmentPayload { name: attachment.name.clone(), bytes: attachment.data.len() })
+                .collect(),
+            finished_at: exit.finished_at.map(|at| at.format(TIMESTAMP_FORMAT).to_string()),
+            duration_ms: exit.duration.map(|duration| duration.as_millis() as u64),
+        }
+    }
+}
+
+// LifecyclePayload is a lifecycle event of the loop as it is POSTed to the webhooks.
+#[derive(Serialize, Debug, Clone)]
+pub struct LifecyclePayload {
+    pub event: String,
+    pub timestamp: String,
+    pub name: String,
+    pub command: String,
+    pub chat_id: Option<i64>,
+    pub message_id: Option<i64>,
+    pub exit_code: Option<String>,
+    pub duration_ms: Option<u64>,
+}
+
+impl LifecyclePayload {
+    pub fn new(event: &Lifecycle) -> LifecyclePayload {
+        let message = event.command.as_ref().map(|command| &command.message);
+        LifecyclePayload {
+            event: event.topic.to_string(),
+            timestamp: Local::now().format(TIMESTAMP_FORMAT).to_string(),
+            name: event.name.clone(),
+            command: event.kind(),
+            chat_id: message.map(|message| message.chat_id),
+            message_id: message.map(|message| message.id),
+            exit_code: event.exit.as_ref().map(|exit| exit.code.label()),
+            duration_ms: event.duration.map(|duration| duration.as_millis() as u64),
+        }
+    }
+}
@@ -0,0 +1,192 @@
+use crate::app::cfg::handle::CfgHandle;
+use crate::infrastructure::integration::webhook::error::{WebhookFailedError, WebhookStatusError};
+use crate::infrastructure::integration::webhook::http::HttpClient;
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::error::Error;
+use std::thread;
+use std::time::Duration;
+
+// The delay before the second attempt of a request, it is doubled on each next one.
+const RETRY_BACKOFF: Duration = Duration::from_secs(1);
+const RETRY_BACKOFF_MAX: Duration = Duration::from_secs(30);
+
+pub trait WebhookServiceTrait: Send + Sync {
+    // post sends the JSON body of the event to each of the configured webhooks, a failed request
+    // is sent again up to WEBHOOK_MAX_ATTEMPTS times and the webhooks which are out of attempts
+    // are reported at once
+    fn post(&self, event: &str, body: Vec<u8>) -> Result<(), Box<dyn Error>>;
+    // is_enabled tells whether there is a webhook to post to
+    fn is_enabled(&self) -> bool;
+}
+
+pub struct WebhookService {
+    cfg: CfgHandle,
+    http_client: Box<dyn HttpClient>,
+}
+
+impl WebhookService {
+    pub fn new(cfg: CfgHandle, http_client: Box<dyn HttpClient>) -> Self {
+        Self { cfg, http_client }
+    }
+}
+
+impl WebhookServiceTrait for WebhookService {
+    fn post(&self, event: &str, body: Vec<u8>) -> Result<(), Box<dyn Error>> {
+        let cfg = self.cfg.get();
+
+        // each attempt is signed along with its time, so the receiver may refuse a replayed one
+        let headers = || {
+            let mut headers = vec![("X-Repl-Event", event.to_string())];
+            if let Some(secret) = &cfg.webhook_secret {
+                let timestamp = Utc::now().timestamp().to_string();
+                headers.push(("X-Repl-Signature-256", format!("sha256={}", sign(secret.as_str(), timestamp.as_str(), &body))));
+                headers.push(("X-Repl-Timestamp", timestamp));
+            }
+            headers
+        };
+
+        let mut failures = vec![];
+        for url in cfg.webhook_urls.iter() {
+            let result = post_with_retries(
+                self.http_client.as_ref(),
+                url.as_str(),
+                &headers,
+                &body,
+                cfg.webhook_max_attempts,
+                cfg.webhook_timeout,
+                RETRY_BACKOFF,
+            );
+            if let Err(e) = result {
+                log::error!(target: "webhook", "Failed to post {} to webhook {}: {}.", event, host(url), e);
+                failures.push((host(url), e.to_string()));
+            }
+        }
+
+        if !failures.is_empty() {
+            return Err(Box::new(WebhookFailedError::new(failures)));
+        }
+        Ok(())
+    }
+
+    fn is_enabled(&self) -> bool {
+        !self.cfg.get().webhook_urls.is_empty()
+    }
+}
+
+// post_with_retries sends the request up to max_attempts times, the delay between the attempts
+// starts from the backoff and is doubled on each next one
+fn post_with_retries(
+    http_client: &dyn HttpClient,
+    url: &str,
+    headers: &dyn Fn() -> Vec<(&'static str, String)>,
+    body: &[u8],
+    max_attempts: u32,
+    timeout: Duration,
+    mut backoff: Duration,
+) -> Result<(), Box<dyn Error>> {
+    let mut attempt = 1;
+    loop {
+        let error = match http_client.post(url, &headers(), body.to_vec(), timeout) {
+            Ok(()) => return Ok(()),
+            Err(error) => error,
+        };
+        // the request is refused as it is, so it is not sent again
+        let is_retryable = error.downcast_ref::<WebhookStatusError>().is_none_or(|e| e.is_retryable());
+        if !is_retryable || attempt >= max_attempts {
+            return Err(error);
+        }
+
+        log::warn!(
+            target: "webhook",
+            "Error: {} occurred while posting to webhook {}, attempt {} of {}, retrying in {:?}.",
+            error, host(url), attempt, max_attempts, backoff
+        );
+        thread::sleep(backoff);
+        backoff = (backoff * 2).min(RETRY_BACKOFF_MAX);
+        attempt += 1;
+    }
+}
+
+// the url of a webhook may hold a token, so just its host is logged
+fn host(url: &str) -> String {
+    reqwest::Url::parse(url)
+        .ok()
+        .and_then(|url| url.host_str().map(|host| host.to_string()))
+        .unwrap_or("webhook".to_string())
+}
+
+// sign returns the hex HMAC-SHA256 of "<timestamp>.<body>", the receiver checks it by the shared
+// secret and refuses the requests whose timestamp is too old
+pub fn sign(secret: &str, timestamp: &str, body: &[u8]) -> String {
+    // a key of any size is accepted by HMAC
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).unwrap();
+    mac.update(timestamp.as_bytes());
+    mac.update(b".");
+    mac.update(body);
+    mac.finalize().into_bytes().iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    // TestClient responds with the statuses in turn, zero is a success.
+    struct TestClient {
+        statuses: Mutex<Vec<u16>>,
+        requests: Mutex<usize>,
+    }
+    impl HttpClient for TestClient {
+        fn post(&self, _: &str, _: &[(&str, String)], _: Vec<u8>, _: Duration) -> Result<(), Box<dyn Error>> {
+            *self.requests.lock().unwrap() += 1;
+            match self.statuses.lock().unwrap().remove(0) {
+                0 => Ok(()),
+                status => Err(Box::new(WebhookStatusError::new(status))),
+            }
+        }
+    }
+
+    fn post(statuses: Vec<u16>, max_attempts: u32) -> (Result<(), Box<dyn Error>>, usize) {
+        let client = TestClient { statuses: Mutex::new(statuses), requests: Mutex::new(0) };
+        let result = post_with_retries(
+            &client,
+            "https://example.com/hook",
+            &Vec::new,
+            b"{}",
+            max_attempts,
+            Duration::from_secs(1),
+            Duration::from_millis(1),
+        );
+        let requests = *client.requests.lock().unwrap();
+        (result, requests)
+    }
+
+    #[test]
+    fn the_body_is_signed_along_with_the_timestamp_by_hmac_sha256() {
+        assert_eq!(
+            sign("key", "1700000000", br#"{"event":"ping"}"#),
+            "3ace95b7499f60370075a35d7575ce006c2d73b6be8e8c42bd93d0c83f17019d"
+        );
+        assert_ne!(sign("key", "1700000001", br#"{"event":"ping"}"#), sign("key", "1700000000", br#"{"event":"ping"}"#));
+    }
+
+    #[test]
+    fn a_failed_request_is_retried_until_it_succeeds() {
+        let (result, requests) = post(vec![503, 429, 0], 3);
+        assert!(result.is_ok());
+        assert_eq!(requests, 3);
+    }
+
+    #[test]
+    fn a_request_is_not_retried_over_the_attempts_or_if_it_is_refused() {
+        let (result, requests) = post(vec![500, 500, 500], 2);
+        assert!(result.is_err());
+        assert_eq!(requests, 2);
+
+        let (result, requests) = post(vec![400, 0], 3);
+        assert!(result.is_err());
+        assert_eq!(requests, 1);
+    }
+}
@@ -0,0 +1,44 @@
+use crate::app::cfg::handle::CfgHandle;
+use crate::domain::model::lifecycle::Lifecycle;
+use crate::domain::r#enum::topic::Topic;
+use crate::domain::service::event::bus::Subscriber;
+use crate::infrastructure::integration::webhook::model::LifecyclePayload;
+use crate::infrastructure::integration::webhook::service::WebhookServiceTrait;
+use std::sync::Arc;
+
+// WebhookSubscriber posts the lifecycle events of WEBHOOK_TOPICS to the webhooks, it is notified
+// of all the topics, so the config is applied on reload.
+pub struct WebhookSubscriber {
+    cfg: CfgHandle,
+    webhook: Arc<Box<dyn WebhookServiceTrait>>,
+}
+
+impl WebhookSubscriber {
+    pub fn new(cfg: CfgHandle, webhook: Arc<Box<dyn WebhookServiceTrait>>) -> WebhookSubscriber {
+        WebhookSubscriber { cfg, webhook }
+    }
+}
+
+impl Subscriber for WebhookSubscriber {
+    fn name(&self) -> String {
+        "webhook".to_string()
+    }
+
+    fn topics(&self) -> Vec<Topic> {
+        vec![Topic::EventFired, Topic::CommandStarted, Topic::CommandFinished]
+    }
+
+    fn notify(&self, event: &Lifecycle) {
+        if !self.webhook.is_enabled() || !self.cfg.get().webhook_topics.contains(&event.topic) {
+            return;
+        }
+
+        match serde_json::to_vec(&LifecyclePayload::new(event)) {
+            // the failure is logged by the service
+            Ok(body) => {
+                let _ = self.webhook.post(event.topic.to_string().as_str(), body);
+            }
+            Err(e) => log::error!(target: "webhook", "Failed to encode {} `{}`: {}.", event.topic, event.name, e),
+        }
+    }
+}
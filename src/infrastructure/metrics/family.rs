@@ -0,0 +1,237 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::Mutex;
+
+// The families keep a value per combination of the label values, the values are rendered in the
+// Prometheus text format.
+
+pub struct Counter {
+    name: &'static str,
+    help: &'static str,
+    labels: &'static [&'static str],
+    values: Mutex<BTreeMap<Vec<String>, u64>>,
+}
+
+impl Counter {
+    pub fn new(name: &'static str, help: &'static str, labels: &'static [&'static str]) -> Counter {
+        Counter { name, help, labels, values: Mutex::new(BTreeMap::new()) }
+    }
+
+    pub fn inc(&self, label_values: &[&str]) {
+        self.add(label_values, 1);
+    }
+
+    pub fn add(&self, label_values: &[&str], n: u64) {
+        *self.values.lock().unwrap().entry(to_key(label_values)).or_default() += n;
+    }
+
+    pub fn render(&self, out: &mut String) {
+        header(out, self.name, self.help, "counter");
+        let values = self.values.lock().unwrap();
+        // a counter without labels is shown before it has been increased
+        if values.is_empty() && self.labels.is_empty() {
+            let _ = writeln!(out, "{} 0", self.name);
+        }
+        for (key, value) in values.iter() {
+            let _ = writeln!(out, "{}{} {}", self.name, labels(self.labels, key, None), value);
+        }
+    }
+}
+
+pub struct Gauge {
+    name: &'static str,
+    help: &'static str,
+    labels: &'static [&'static str],
+    values: Mutex<BTreeMap<Vec<String>, i64>>,
+}
+
+impl Gauge {
+    pub fn new(name: &'static str, help: &'static str, labels: &'static [&'static str]) -> Gauge {
+        Gauge { name, help, labels, values: Mutex::new(BTreeMap::new()) }
+    }
+
+    pub fn set(&self, label_values: &[&str], value: i64) {
+        self.values.lock().unwrap().insert(to_key(label_values), value);
+    }
+
+    pub fn render(&self, out: &mut String) {
+        header(out, self.name, self.help, "gauge");
+        let values = self.values.lock().unwrap();
+        // a gauge without labels is shown before it has been set
+        if values.is_empty() && self.labels.is_empty() {
+            let _ = writeln!(out, "{} 0", self.name);
+        }
+        for (key, value) in values.iter() {
+            let _ = writeln!(out, "{}{} {}", self.name, labels(self.labels, key, None), value);
+        }
+    }
+}
+
+#[derive(Clone)]
+struct Observations {
+    // the count of the observations per bucket, they are summed up on render
+    buckets: Vec<u64>,
+    sum: f64,
+    count: u64,
+}
+
+pub struct Histogram {
+    name: &'static str,
+    help: &'static str,
+    labels: &'static [&'static str],
+    // the upper bounds of the buckets, ascending
+    bounds: &'static [f64],
+    values: Mutex<BTreeMap<Vec<String>, Observations>>,
+}
+
+impl Histogram {
+    pub fn new(
+        name: &'static str,
+        help: &'static str,
+        labels: &'static [&'static str],
+        bounds: &'static [f64],
+    ) -> Histogram {
+        Histogram { name, help, labels, bounds, values: Mutex::new(BTreeMap::new()) }
+    }
+
+    pub fn observe(&self, label_values: &[&str], value: f64) {
+        let mut values = self.values.lock().unwrap();
+        let observations = values.entry(to_key(label_values)).or_insert_with(|| Observations {
+            buckets: vec![0; self.bounds.len()],
+            sum: 0.0,
+            count: 0,
+        });
+        if let Some(i) = self.bounds.iter().position(|bound| value <= *bound) {
+            observations.buckets[i] += 1;
+        }
+        observations.sum += value;
+        observations.count += 1;
+    }
+
+    pub fn render(&self, out: &mut String) {
+        header(out, self.name, self.help, "histogram");
+        let mut values = self.values.lock().unwrap().clone();
+        // a histogram without labels is shown before anything has been observed
+        if values.is_empty() && self.labels.is_empty() {
+            values.insert(vec![], Observations { buckets: vec![0; self.bounds.len()], sum: 0.0, count: 0 });
+        }
+        for (key, observations) in values.iter() {
+            let mut cumulative = 0;
+            for (bound, count) in self.bounds.iter().zip(observations.buckets.iter()) {
+                cumulative += count;
+                let le = bound.to_string();
+                let _ = writeln!(out, "{}_bucket{} {}", self.name, labels(self.labels, key, Some(&le)), cumulative);
+            }
+            let _ = writeln!(out, "{}_bucket{} {}", self.name, labels(self.labels, key, Some("+Inf")), observations.count);
+            let _ = writeln!(out, "{}_sum{} {}", self.name, labels(self.labels, key, None), observations.sum);
+            let _ = writeln!(out, "{}_count{} {}", self.name, labels(self.labels, key, None), observations.count);
+        }
+    }
+}
+
+fn to_key(label_values: &[&str]) -> Vec<String> {
+    label_values.iter().map(|value| value.to_string()).collect()
+}
+
+fn header(out: &mut String, name: &str, help: &str, kind: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+}
+
+// renders the labels like {type="Exec",exit_code="0"}, le is the bucket bound of a histogram
+fn labels(names: &[&str], values: &[String], le: Option<&str>) -> String {
+    let mut pairs: Vec<String> = names
+        .iter()
+        .zip(values.iter())
+        .map(|(name, value)| format!("{}=\"{}\"", name, escape(value)))
+        .collect();
+    if let Some(le) = le {
+        pairs.push(format!("le=\"{}\"", le));
+    }
+    if pairs.is_empty() {
+        return "".to_string();
+    }
+    format!("{{{}}}", pairs.join(","))
+}
+
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(f: impl FnOnce(&mut String)) -> String {
+        let mut out = String::new();
+        f(&mut out);
+        out
+    }
+
+    #[test]
+    fn a_counter_sums_up_per_labels_and_escapes_them() {
+        let counter = Counter::new("repl_commands_total", "Commands.", &["type", "exit_code"]);
+        counter.inc(&["Exec", "0"]);
+        counter.add(&["Exec", "0"], 2);
+        counter.inc(&["say \"hi\"\\\n", "1"]);
+
+        assert_eq!(
+            render(|out| counter.render(out)),
+            "# HELP repl_commands_total Commands.\n\
+             # TYPE repl_commands_total counter\n\
+             repl_commands_total{type=\"Exec\",exit_code=\"0\"} 3\n\
+             repl_commands_total{type=\"say \\\"hi\\\"\\\\\\n\",exit_code=\"1\"} 1\n"
+        );
+    }
+
+    #[test]
+    fn a_gauge_keeps_the_last_value() {
+        let gauge = Gauge::new("repl_queue_size", "Queue size.", &["queue"]);
+        gauge.set(&["events"], 5);
+        gauge.set(&["events"], -1);
+
+        assert_eq!(
+            render(|out| gauge.render(out)),
+            "# HELP repl_queue_size Queue size.\n# TYPE repl_queue_size gauge\nrepl_queue_size{queue=\"events\"} -1\n"
+        );
+    }
+
+    #[test]
+    fn the_unlabeled_families_are_shown_with_zeros_before_they_are_used() {
+        let counter = Counter::new("repl_up_total", "Up.", &[]);
+        let gauge = Gauge::new("repl_jobs", "Jobs.", &[]);
+        let histogram = Histogram::new("repl_poll_seconds", "Poll.", &[], &[1.0]);
+        let labeled = Counter::new("repl_sent_total", "Sent.", &["chat"]);
+
+        assert!(render(|out| counter.render(out)).ends_with("\nrepl_up_total 0\n"));
+        assert!(render(|out| gauge.render(out)).ends_with("\nrepl_jobs 0\n"));
+        assert!(render(|out| histogram.render(out)).ends_with(
+            "\nrepl_poll_seconds_bucket{le=\"1\"} 0\n\
+             repl_poll_seconds_bucket{le=\"+Inf\"} 0\n\
+             repl_poll_seconds_sum 0\n\
+             repl_poll_seconds_count 0\n"
+        ));
+        // the label values are not known, so there is nothing to show
+        assert_eq!(render(|out| labeled.render(out)), "# HELP repl_sent_total Sent.\n# TYPE repl_sent_total counter\n");
+    }
+
+    #[test]
+    fn a_histogram_has_cumulative_buckets_the_sum_and_the_count() {
+        let histogram = Histogram::new("repl_duration_seconds", "Duration.", &["type"], &[0.5, 1.0, 5.0]);
+        for value in [0.1, 0.5, 0.7, 3.0, 60.0] {
+            histogram.observe(&["Exec"], value);
+        }
+
+        assert_eq!(
+            render(|out| histogram.render(out)),
+            "# HELP repl_duration_seconds Duration.\n\
+             # TYPE repl_duration_seconds histogram\n\
+             repl_duration_seconds_bucket{type=\"Exec\",le=\"0.5\"} 2\n\
+             repl_duration_seconds_bucket{type=\"Exec\",le=\"1\"} 3\n\
+             repl_duration_seconds_bucket{type=\"Exec\",le=\"5\"} 4\n\
+             repl_duration_seconds_bucket{type=\"Exec\",le=\"+Inf\"} 5\n\
+             repl_duration_seconds_sum{type=\"Exec\"} 64.3\n\
+             repl_duration_seconds_count{type=\"Exec\"} 5\n"
+        );
+    }
+}
@@ -0,0 +1,123 @@
+use crate::infrastructure::metrics::family::{Counter, Gauge, Histogram};
+use std::time::Duration;
+
+// the buckets of the durations in seconds, from a quick command to a long running one
+const COMMAND_DURATION_BUCKETS: &[f64] = &[0.01, 0.05, 0.1, 0.5, 1.0, 5.0, 10.0, 30.0, 60.0, 300.0];
+// the buckets of the telegram requests in seconds, getUpdates is held up to the poll frequency
+const TELEGRAM_DURATION_BUCKETS: &[f64] = &[0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0];
+
+// Metrics of the app, they are kept always and exposed by the metrics server if it is enabled.
+pub struct Metrics {
+    pub polls: Counter,
+    pub poll_errors: Counter,
+    pub updates: Counter,
+    pub events_fired: Counter,
+    pub commands: Counter,
+    pub command_duration: Histogram,
+    pub telegram_duration: Histogram,
+    pub telegram_failures: Counter,
+    pub event_loop_queue_length: Gauge,
+    pub event_loop_pending_events: Gauge,
+    pub event_loop_dead_letters: Gauge,
+    pub bus_subscriber_lag: Gauge,
+    pub bus_dropped_events: Counter,
+}
+
+impl Metrics {
+    pub fn new() -> Metrics {
+        Metrics {
+            polls: Counter::new("repl_polls_total", "The getUpdates polls.", &[]),
+            poll_errors: Counter::new("repl_poll_errors_total", "The getUpdates polls which have failed.", &[]),
+            updates: Counter::new("repl_updates_received_total", "The updates which have been received.", &[]),
+            events_fired: Counter::new(
+                "repl_events_fired_total",
+                "The events which have been fired by the event loop by the command type.",
+                &["type"],
+            ),
+            commands: Counter::new(
+                "repl_commands_total",
+                "The executed commands by the type and the exit code.",
+                &["type", "exit_code"],
+            ),
+            command_duration: Histogram::new(
+                "repl_command_duration_seconds",
+                "The execution duration of the commands.",
+                &["type"],
+                COMMAND_DURATION_BUCKETS,
+            ),
+            telegram_duration: Histogram::new(
+                "repl_telegram_request_duration_seconds",
+                "The latency of the telegram requests by the method.",
+                &["method"],
+                TELEGRAM_DURATION_BUCKETS,
+            ),
+            telegram_failures: Counter::new(
+                "repl_telegram_request_failures_total",
+                "The telegram requests which have failed by the method.",
+                &["method"],
+            ),
+            event_loop_queue_length: Gauge::new(
+                "repl_event_loop_queue_length",
+                "The events which are kept by the event loop.",
+                &[],
+            ),
+            event_loop_pending_events: Gauge::new(
+                "repl_event_loop_pending_events",
+                "The events which have been scheduled ahead (the next runs and the retries) and are not taken yet.",
+                &[],
+            ),
+            event_loop_dead_letters: Gauge::new(
+                "repl_event_loop_dead_letters",
+                "The events which have failed all of their attempts.",
+                &[],
+            ),
+            bus_subscriber_lag: Gauge::new(
+                "repl_bus_subscriber_lag",
+                "The lifecycle events which the subscriber has not handled yet.",
+                &["subscriber"],
+            ),
+            bus_dropped_events: Counter::new(
+                "repl_bus_dropped_events_total",
+                "The lifecycle events which have been dropped since the queue of the subscriber was full.",
+                &["subscriber"],
+            ),
+        }
+    }
+
+    pub fn observe_command(&self, r#type: &str, exit_code: &str, duration: Duration) {
+        self.commands.inc(&[r#type, exit_code]);
+        self.command_duration.observe(&[r#type], duration.as_secs_f64());
+    }
+
+    pub fn observe_telegram(&self, method: &str, duration: Duration, is_failed: bool) {
+        self.telegram_duration.observe(&[method], duration.as_secs_f64());
+        if is_failed {
+            self.telegram_failures.inc(&[method]);
+        }
+    }
+
+    // render returns the metrics in the Prometheus text format
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        self.polls.render(&mut out);
+        self.poll_errors.render(&mut out);
+        self.updates.render(&mut out);
+        self.events_fired.render(&mut out);
+        self.commands.render(&mut out);
+        self.command_duration.render(&mut out);
+        self.telegram_duration.render(&mut out);
+        self.telegram_failures.render(&mut out);
+        self.event_loop_queue_length.render(&mut out);
+        self.event_loop_pending_events.render(&mut out);
+        self.event_loop_dead_letters.render(&mut out);
+        self.bus_subscriber_lag.render(&mut out);
+        self.bus_dropped_events.render(&mut out);
+        out
+    }
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
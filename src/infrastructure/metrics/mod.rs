@@ -0,0 +1,5 @@
+pub mod family;
+#[allow(clippy::module_inception)]
+pub mod metrics;
+pub mod server;
+pub mod subscriber;
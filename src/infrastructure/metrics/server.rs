@@ -0,0 +1,109 @@
+use crate::app::cfg::handle::CfgHandle;
+use crate::app::model::health::Health;
+use crate::app::model::state::State;
+use crate::infrastructure::metrics::metrics::Metrics;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+// How often the server checks for a connection and for the shutdown.
+const ACCEPT_INTERVAL: Duration = Duration::from_millis(100);
+// How long a client may take to send the request.
+const READ_TIMEOUT: Duration = Duration::from_secs(5);
+
+pub trait Server: Send + Sync {
+    fn serve(&self);
+}
+
+// MetricsServer exposes the metrics by GET /metrics and the liveness of the components by
+// GET /healthz on METRICS_ADDR, nothing is served if it is not set.
+pub struct MetricsServer {
+    cfg: CfgHandle,
+    state: Arc<Box<dyn State>>,
+    metrics: Arc<Metrics>,
+    health: Arc<Health>,
+}
+
+impl MetricsServer {
+    pub fn new(cfg: CfgHandle, state: Arc<Box<dyn State>>, metrics: Arc<Metrics>, health: Arc<Health>) -> MetricsServer {
+        MetricsServer { cfg, state, metrics, health }
+    }
+
+    // healthz returns the status and the components as JSON, 503 tells that one of them is down
+    fn healthz(&self) -> (&'static str, &'static str, String) {
+        let is_healthy = self.health.is_healthy();
+        let body = serde_json::json!({
+            "status": if is_healthy { "ok" } else { "unhealthy" },
+            "started_at": self.health.started_at(),
+            "components": self.health.components(),
+        });
+        let status = if is_healthy { "200 OK" } else { "503 Service Unavailable" };
+        (status, "application/json", format!("{}\n", body))
+    }
+
+    fn handle(&self, stream: TcpStream) -> io::Result<()> {
+        stream.set_nonblocking(false)?;
+        stream.set_read_timeout(Some(READ_TIMEOUT))?;
+
+        let mut request_line = String::new();
+        BufReader::new(&stream).read_line(&mut request_line)?;
+
+        let mut parts = request_line.split_whitespace();
+        let (status, content_type, body) = match (parts.next(), parts.next()) {
+            (Some("GET"), Some("/metrics")) => ("200 OK", "text/plain; version=0.0.4", self.metrics.render()),
+            (Some("GET"), Some("/healthz")) => self.healthz(),
+            (Some("GET"), Some(_)) => ("404 Not Found", "text/plain", "Not found.\n".to_string()),
+            _ => ("405 Method Not Allowed", "text/plain", "Method not allowed.\n".to_string()),
+        };
+
+        let mut stream = stream;
+        write!(
+            stream,
+            "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            status,
+            content_type,
+            body.len(),
+            body,
+        )?;
+        stream.flush()
+    }
+}
+
+impl Server for MetricsServer {
+    fn serve(&self) {
+        // the address is read once, a reloaded one takes effect after a restart
+        let Some(addr) = self.cfg.get().metrics_addr else {
+            return;
+        };
+
+        let listener = match TcpListener::bind(addr).and_then(|listener| {
+            listener.set_nonblocking(true)?;
+            Ok(listener)
+        }) {
+            Ok(listener) => listener,
+            Err(e) => {
+                log::error!(target: "metrics", "Failed to listen on {}: {}.", addr, e);
+                return;
+            }
+        };
+        log::info!(target: "metrics", "Metrics are served on http://{}/metrics and the health on /healthz.", addr);
+
+        // the listener does not block, so the shutdown is noticed between the connections
+        while !self.state.is_closed() {
+            match listener.accept() {
+                Ok((stream, peer)) => {
+                    if let Err(e) = self.handle(stream) {
+                        log::warn!(target: "metrics", "Failed to serve {}: {}.", peer, e);
+                    }
+                }
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => thread::sleep(ACCEPT_INTERVAL),
+                Err(e) => {
+                    log::warn!(target: "metrics", "Failed to accept a connection: {}.", e);
+                    thread::sleep(ACCEPT_INTERVAL);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,40 @@
+use crate::domain::model::lifecycle::Lifecycle;
+use crate::domain::r#enum::topic::Topic;
+use crate::domain::service::event::bus::Subscriber;
+use crate::infrastructure::metrics::metrics::Metrics;
+use std::sync::Arc;
+
+// MetricsSubscriber counts the fired events and the finished commands.
+pub struct MetricsSubscriber {
+    metrics: Arc<Metrics>,
+}
+
+impl MetricsSubscriber {
+    pub fn new(metrics: Arc<Metrics>) -> MetricsSubscriber {
+        MetricsSubscriber { metrics }
+    }
+}
+
+impl Subscriber for MetricsSubscriber {
+    fn name(&self) -> String {
+        "metrics".to_string()
+    }
+
+    fn topics(&self) -> Vec<Topic> {
+        vec![Topic::EventFired, Topic::CommandFinished]
+    }
+
+    fn notify(&self, event: &Lifecycle) {
+        // the events of the app itself (e.g. a finished job) are counted apart from the commands
+        let r#type = event.kind();
+        match (event.topic, &event.exit) {
+            (Topic::EventFired, _) => self.metrics.events_fired.inc(&[r#type.as_str()]),
+            (Topic::CommandFinished, Some(exit)) => self.metrics.observe_command(
+                r#type.as_str(),
+                exit.code.label().as_str(),
+                event.duration.unwrap_or_default(),
+            ),
+            _ => {}
+        }
+    }
+}
@@ -1,5 +1,6 @@
 pub mod broadcasting;
 pub mod helper;
 pub mod integration;
+pub mod metrics;
 pub mod model;
 pub mod service;
@@ -1,6 +1,8 @@
 use crate::domain::r#enum::command::Type;
 use crate::domain::r#enum::exit_code::ExitCode;
-use crate::infrastructure::integration::telegram::model::Message;
+use crate::domain::model::message::{Attachment, File, Message};
+use chrono::{DateTime, Local};
+use std::time::Duration;
 
 pub const DATE_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
 
@@ -9,23 +11,43 @@ pub struct Command {
     pub str: String,
     pub r#type: Type,
     pub message: Message,
+    // payload which is written into stdin of the executed process
+    pub stdin: Option<Stdin>,
 }
 impl Command {
-    pub fn new(str: String, r#type: Type, message: Message) -> Self {
+    pub fn new(str: String, r#type: Type, message: Message, stdin: Option<Stdin>) -> Self {
         Self {
             str,
             r#type,
             message,
+            stdin,
         }
     }
 }
 
-#[derive(Debug)]
+// Stdin is the input of a command: the text of the message or the file which it replies to,
+// the file is downloaded just when the command is executed.
+#[derive(Clone)]
+pub enum Stdin {
+    Data(Vec<u8>),
+    File(File),
+}
+
+#[derive(Debug, Clone)]
 pub struct Exit {
     pub code: ExitCode,
     pub stdout: String,
     pub stderr: String,
     pub input_message: Option<Message>,
+    // files which are sent along with the response (e.g. binary output)
+    pub attachments: Vec<Attachment>,
+    // the program with its arguments if the command has spawned one (kept by the audit log)
+    pub argv: Option<Vec<String>>,
+    // when the command has finished and how long it has taken, set by the executor
+    pub finished_at: Option<DateTime<Local>>,
+    pub duration: Option<Duration>,
+    // the responders which have sent the result already, a retried delivery skips them
+    pub delivered: Vec<String>,
 }
 impl Exit {
     pub fn new(code: ExitCode, stdout: String, stderr: String, input_message: Option<Message>) -> Self {
@@ -34,6 +56,11 @@ impl Exit {
             stdout,
             stderr,
             input_message,
+            attachments: vec![],
+            argv: None,
+            finished_at: None,
+            duration: None,
+            delivered: vec![],
         }
     }
 }
@@ -1,75 +1,302 @@
-use crate::app::cfg::cfg::Cfg;
-use crate::infrastructure::integration::telegram;
+use crate::app::cfg::handle::CfgHandle;
+use crate::domain::error::executor::PartiallyDeliveredError;
+use crate::domain::model::message::Reply;
+use crate::domain::service::transport::transport::Transport;
+use crate::infrastructure::broadcasting::mpsc::queue::{self, Overflow, Sender};
+use crate::infrastructure::integration::webhook::model::{ResultPayload, RESULT_EVENT};
+use crate::infrastructure::integration::webhook::service::WebhookServiceTrait;
 use crate::infrastructure::model::command::Exit;
 use std::error::Error;
 use std::sync::Arc;
+use std::panic::{self, AssertUnwindSafe};
+use std::thread;
+use std::time::Instant;
 use crate::domain::r#enum::exit_code::ExitCode;
 
+// How many results wait for the webhooks, the oldest one is dropped when a new one is over it.
+const WEBHOOK_QUEUE_CAPACITY: usize = 100;
+
 pub trait Responder: Send + Sync {
+    // the name tells the responders apart, a retried delivery skips the ones which have sent the result
+    fn name(&self) -> String;
     fn respond(&self, exit_state: Exit) -> Result<(), Box<dyn Error>>;
 }
 
 pub struct ExitCommandResponder {
-    cfg: Cfg,
-    telegram: Arc<Box<dyn telegram::facade::TelegramFacadeTrait>>,
+    cfg: CfgHandle,
+    transport: Arc<Box<dyn Transport>>,
 }
 
 impl ExitCommandResponder {
     pub fn new(
-        cfg: Cfg,
-        telegram: Arc<Box<dyn telegram::facade::TelegramFacadeTrait>>,
+        cfg: CfgHandle,
+        transport: Arc<Box<dyn Transport>>,
     ) -> ExitCommandResponder {
-        ExitCommandResponder { cfg, telegram }
+        ExitCommandResponder { cfg, transport }
     }
 }
 
 impl Responder for ExitCommandResponder {
+    fn name(&self) -> String {
+        self.transport.name()
+    }
+
     fn respond(&self, exit: Exit) -> Result<(), Box<dyn Error>> {
         if exit.code == ExitCode::Wife {
-            match self.telegram.send_message(
-                self.cfg.wife_chat_id,
-                format!("{}", exit.stdout.as_str()).as_str()
-            ) {
+            match self.transport.send(Reply::new(
+                self.cfg.get().wife_chat_id,
+                exit.stdout.clone(),
+                vec![],
+            )) {
                 Ok(_) => {
-                    println!("Successfully sent wife-chat message: {}.", exit.stdout.as_str());
+                    log::info!(target: "responder", "Successfully sent wife-chat message: {}.", exit.stdout.as_str());
                     Ok(())
                 },
                 Err(e) => {
-                    println!("Failed to send wife-chat message: {}.", e.to_string());
+                    log::error!(target: "responder", "Failed to send wife-chat message: {}.", e);
                     Err(e)
                 },
             }
         } else {
-            match self.telegram.send_message(
-                self.cfg.chat_id,
-                format!(
-                    "```Input:\t{}```
+            let chat_id = exit.input_message.as_ref().map(|msg| msg.chat_id);
+            let message_id = exit.input_message.as_ref().map(|msg| msg.id);
+            // the result goes to the chat of the command, the events of the app itself to the configured one
+            let reply_chat_id = chat_id.unwrap_or(self.cfg.get().chat_id);
+            // the parts of the reply which have been sent by the failed delivery are kept by the result
+            let prefix = format!("{}:", self.transport.name());
+            let sent: Vec<String> = exit.delivered.iter().filter_map(|part| part.strip_prefix(prefix.as_str())).map(str::to_string).collect();
+            let started_at = Instant::now();
+
+            let text = format!(
+                "```Input:\t{}```
                 ```Stdout:\t{}```
                 ```Stderr:\t{}```
                 ```Code:\t{}```",
-                    match exit.input_message {
-                        Some(msg) => msg.text,
-                        None => "".to_string(),
-                    },
-                    exit.stdout.as_str(),
-                    exit.stderr.as_str(),
-                    match exit.code {
-                        ExitCode::Success => 0,
-                        ExitCode::Failed => 1,
-                        ExitCode::Other(code) => code,
-                        _ => panic!("undefined exit code")
-                    },
-                ).as_str(),
-            ) {
+                match exit.input_message {
+                    Some(msg) => msg.text,
+                    None => "".to_string(),
+                },
+                exit.stdout.as_str(),
+                exit.stderr.as_str(),
+                exit.code,
+            );
+
+            match self.transport.send(Reply::new(reply_chat_id, text, exit.attachments).without(&sent)) {
                 Ok(_) => {
-                    println!("Successfully response message: stdout: {}, stderr: {}.", exit.stdout.as_str(), exit.stderr.as_str());
+                    log::debug!(
+                        target: "responder",
+                        chat_id = chat_id,
+                        message_id = message_id;
+                        "Response stdout: {}, stderr: {}.", exit.stdout.as_str(), exit.stderr.as_str()
+                    );
+                    log::info!(
+                        target: "responder",
+                        chat_id = chat_id,
+                        message_id = message_id,
+                        duration_ms = started_at.elapsed().as_millis() as u64;
+                        "Response has been sent."
+                    );
                     Ok(())
                 },
                 Err(e) => {
-                    println!("Failed to response message: {}.", e.to_string());
-                    Err(e)
+                    log::error!(
+                        target: "responder",
+                        chat_id = chat_id,
+                        message_id = message_id;
+                        "Failed to response message by {}: {}.", self.transport.name(), e
+                    );
+                    // a retry does not send the parts which have been sent again
+                    match e.downcast_ref::<PartiallyDeliveredError>() {
+                        Some(partial) => {
+                            let delivered = partial.delivered().iter().map(|part| format!("{}{}", prefix, part)).collect();
+                            Err(Box::new(PartiallyDeliveredError::new(delivered, e.to_string())))
+                        }
+                        None => Err(e),
+                    }
                 },
             }
         }
     }
 }
+
+// WebhookResponder posts the results to the webhooks, the requests are sent by its own thread,
+// so a slow webhook does not hold the loop and the failed ones are retried there.
+pub struct WebhookResponder {
+    queue: Sender<Vec<u8>>,
+}
+
+impl WebhookResponder {
+    pub fn new(webhook: Arc<Box<dyn WebhookServiceTrait>>) -> WebhookResponder {
+        let (queue, receiver) = queue::bounded::<Vec<u8>>(WEBHOOK_QUEUE_CAPACITY, Overflow::DropOldest);
+        thread::spawn(move || {
+            for body in receiver.iter() {
+                // the failure of each webhook is logged by the service, the result is kept by the
+                // audit log, so the thread goes on with the next one even if the post has panicked
+                if panic::catch_unwind(AssertUnwindSafe(|| webhook.post(RESULT_EVENT, body))).is_err() {
+                    log::error!(target: "webhook", "Webhook has panicked while posting a result.");
+                }
+            }
+        });
+        WebhookResponder { queue }
+    }
+}
+
+impl Responder for WebhookResponder {
+    fn name(&self) -> String {
+        "webhook".to_string()
+    }
+
+    fn respond(&self, exit: Exit) -> Result<(), Box<dyn Error>> {
+        // the messages to the wife chat are private
+        if exit.code == ExitCode::Wife {
+            return Ok(());
+        }
+
+        // the webhooks never fail the delivery, otherwise the result would be sent to the chat again
+        let body = match serde_json::to_vec(&ResultPayload::new(&exit)) {
+            Ok(body) => body,
+            Err(e) => {
+                log::error!(target: "webhook", "Failed to encode the result: {}.", e);
+                return Ok(());
+            }
+        };
+        match self.queue.send(body) {
+            Ok(0) => {}
+            Ok(_) => log::warn!(target: "webhook", "Webhook lags behind, the oldest result has been dropped."),
+            Err(e) => log::error!(target: "webhook", "Failed to queue the result: {}.", e),
+        }
+        Ok(())
+    }
+}
+
+// CompositeResponder sends the result by each of the responders, the delivery fails if one of
+// them has failed and then it is retried by the failed ones alone, so the rest of them do not
+// send the result twice (e.g. the webhooks are not posted again when the chat has failed).
+pub struct CompositeResponder {
+    responders: Vec<Box<dyn Responder>>,
+}
+
+impl CompositeResponder {
+    pub fn new(responders: Vec<Box<dyn Responder>>) -> CompositeResponder {
+        CompositeResponder { responders }
+    }
+}
+
+impl Responder for CompositeResponder {
+    fn name(&self) -> String {
+        "composite".to_string()
+    }
+
+    fn respond(&self, exit: Exit) -> Result<(), Box<dyn Error>> {
+        let mut delivered = vec![];
+        let mut errors = vec![];
+        for responder in self.responders.iter().filter(|responder| !exit.delivered.contains(&responder.name())) {
+            match responder.respond(exit.clone()) {
+                Ok(_) => delivered.push(responder.name()),
+                Err(e) => {
+                    // the responder may have sent a part of the result (e.g. the text without the attachments)
+                    if let Some(partial) = e.downcast_ref::<PartiallyDeliveredError>() {
+                        delivered.extend_from_slice(partial.delivered());
+                    }
+                    errors.push(e.to_string());
+                }
+            }
+        }
+
+        if errors.is_empty() {
+            return Ok(());
+        }
+        Err(Box::new(PartiallyDeliveredError::new(delivered, errors.join(" "))))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::cfg::cfg::testing::with_cfg;
+    use crate::domain::model::message::{File, Message, User};
+    use std::sync::Mutex;
+
+    // TestTransport keeps the chats which the replies have been sent to.
+    struct TestTransport {
+        chat_ids: Arc<Mutex<Vec<i64>>>,
+    }
+    impl Transport for TestTransport {
+        fn name(&self) -> String {
+            "test".to_string()
+        }
+        fn send(&self, reply: Reply) -> Result<(), Box<dyn Error>> {
+            self.chat_ids.lock().unwrap().push(reply.chat_id);
+            Ok(())
+        }
+        fn download(&self, _: &File, _: u64) -> Result<Vec<u8>, Box<dyn Error>> {
+            Ok(vec![])
+        }
+    }
+
+    fn message(chat_id: i64) -> Message {
+        Message {
+            id: 1,
+            chat_id,
+            user: User { id: chat_id, username: "user".to_string() },
+            date: 0,
+            text: "/cmd ls".to_string(),
+            caption: None,
+            file: None,
+            reply_to: None,
+        }
+    }
+
+    #[test]
+    fn a_result_is_replied_to_the_chat_of_its_message() {
+        let chat_ids = Arc::new(Mutex::new(vec![]));
+        let transport: Arc<Box<dyn Transport>> = Arc::new(Box::new(TestTransport { chat_ids: chat_ids.clone() }));
+        let responder = with_cfg(&[], |cfg| ExitCommandResponder::new(CfgHandle::new(cfg.unwrap()), transport));
+
+        responder.respond(Exit::new(ExitCode::Success, "".to_string(), "".to_string(), Some(message(42)))).unwrap();
+        responder.respond(Exit::new(ExitCode::Success, "".to_string(), "".to_string(), None)).unwrap();
+
+        // the configured chat (TG_CHAT_ID) gets the results without a message
+        assert_eq!(*chat_ids.lock().unwrap(), vec![42, 1]);
+    }
+
+    // TestResponder counts the results it has been asked to send.
+    struct TestResponder {
+        name: String,
+        is_failed: bool,
+        sent: Arc<Mutex<usize>>,
+    }
+    impl Responder for TestResponder {
+        fn name(&self) -> String {
+            self.name.clone()
+        }
+        fn respond(&self, _: Exit) -> Result<(), Box<dyn Error>> {
+            *self.sent.lock().unwrap() += 1;
+            if self.is_failed {
+                return Err(format!("{} is offline", self.name).into());
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn a_failed_responder_does_not_hide_the_rest_and_is_retried_alone() {
+        let (failed, kept) = (Arc::new(Mutex::new(0)), Arc::new(Mutex::new(0)));
+        let responder = CompositeResponder::new(vec![
+            Box::new(TestResponder { name: "chat".to_string(), is_failed: true, sent: failed.clone() }),
+            Box::new(TestResponder { name: "webhook".to_string(), is_failed: false, sent: kept.clone() }),
+        ]);
+        let exit = Exit::new(ExitCode::Success, "".to_string(), "".to_string(), Some(message(42)));
+
+        let error = responder.respond(exit.clone()).err().unwrap();
+        let error = error.downcast_ref::<PartiallyDeliveredError>().unwrap();
+        assert_eq!(error.delivered(), ["webhook".to_string()]);
+        assert_eq!(error.to_string(), "chat is offline");
+        assert_eq!((*failed.lock().unwrap(), *kept.lock().unwrap()), (1, 1));
+
+        // the retry skips the responder which has sent the result
+        let exit = Exit { delivered: error.delivered().to_vec(), ..exit };
+        assert!(responder.respond(exit).is_err());
+        assert_eq!((*failed.lock().unwrap(), *kept.lock().unwrap()), (2, 1));
+    }
+}
@@ -1,20 +1,25 @@
+use crate::app::model::health::{self, Health};
 use crate::app::model::state::State;
 use crate::domain::factory::command::Factoryer;
 use crate::domain::model::event::ExecutableEvent;
+use crate::domain::model::message::Message;
 use crate::domain::service::event::r#loop::EventLoop;
-use crate::infrastructure::integration;
-use integration::telegram;
-use std::sync::mpsc::Receiver;
+use std::sync::mpsc::{Receiver, RecvTimeoutError};
 use std::sync::Arc;
+use std::time::Duration;
+
+// How long the consumer waits for a message before it checks the application state.
+const RECV_TIMEOUT: Duration = Duration::from_secs(1);
 
 pub trait Consumer: Send + Sync {
-    fn consume(&self, ch: Receiver<telegram::model::Message>);
+    fn consume(&self, ch: &Receiver<Message>);
 }
 
 pub struct MessageConsumer {
     factory: Box<dyn Factoryer>,
     state: Arc<Box<dyn State>>,
     event_loop: Arc<Box<dyn EventLoop>>,
+    health: Arc<Health>,
 }
 
 impl MessageConsumer {
@@ -22,21 +27,31 @@ impl MessageConsumer {
         factory: Box<dyn Factoryer>,
         state: Arc<Box<dyn State>>,
         event_loop: Arc<Box<dyn EventLoop>>,
+        health: Arc<Health>,
     ) -> MessageConsumer {
         MessageConsumer {
             factory,
             state,
             event_loop,
+            health,
         }
     }
 }
 
 impl Consumer for MessageConsumer {
-    fn consume(&self, msg_ch: Receiver<telegram::model::Message>) {
-        for msg in msg_ch {
+    fn consume(&self, msg_ch: &Receiver<Message>) {
+        loop {
             if self.state.is_closed() {
                 return;
             }
+            self.health.beat(health::CONSUMER);
+
+            // the receiver is not blocked forever, so the closed state is noticed in time
+            let msg = match msg_ch.recv_timeout(RECV_TIMEOUT) {
+                Ok(msg) => msg,
+                Err(RecvTimeoutError::Timeout) => continue,
+                Err(RecvTimeoutError::Disconnected) => return,
+            };
 
             let event: Arc<Box<dyn ExecutableEvent>> = Arc::new(self.factory.make(msg.clone()));
 
@@ -1,3 +1,2 @@
 pub mod consumer;
-pub mod poller;
 pub mod provider;
@@ -1,15 +1,8 @@
-use crate::infrastructure::integration;
-use crate::infrastructure::service;
-use integration::telegram::model::Message;
-use service::message::poller::{LongPoller, Poller};
+use crate::domain::model::message::Message;
 use std::sync::mpsc::SyncSender;
 
+// Provider is the inbound side of a transport, it sends the messages of the chats to the channel
+// (e.g. the long poller of telegram).
 pub trait Provider: Send + Sync {
     fn provide(&mut self, ch: SyncSender<Message>);
 }
-
-impl Provider for LongPoller {
-    fn provide(&mut self, ch: SyncSender<Message>) {
-        self.poll(ch)
-    }
-}
@@ -1,10 +1,14 @@
+
 pub mod app;
 pub mod domain;
 pub mod infrastructure;
 
-use crate::app::app::Kernel;
-use app::app::App;
+use app::cli::cli::Cli;
 
 fn main() {
-    App::new().run().unwrap()
+    let result = Cli::parse(std::env::args().skip(1).collect()).map_err(Into::into).and_then(Cli::run);
+    if let Err(e) = result {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
 }
\ No newline at end of file